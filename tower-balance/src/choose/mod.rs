@@ -1,10 +1,20 @@
 use indexmap::IndexMap;
 
+use EndpointMeta;
+
 mod p2c;
+mod prefer_idle;
+mod replay;
 mod round_robin;
+mod weighted_random;
+mod weighted_round_robin;
 
 pub use self::p2c::PowerOfTwoChoices;
+pub use self::prefer_idle::PreferIdle;
+pub use self::replay::{Record, Replay, Selection, SelectionLog};
 pub use self::round_robin::RoundRobin;
+pub use self::weighted_random::WeightedRandom;
+pub use self::weighted_round_robin::WeightedRoundRobin;
 
 /// A strategy for choosing nodes.
 // TODO hide `K`
@@ -40,6 +50,19 @@ impl<'a, K: 'a, S: 'a> Replicas<'a, K, S> {
     }
 }
 
+impl<'a, K: 'a, S: 'a> Replicas<'a, K, S>
+where
+    S: EndpointMeta,
+{
+    /// Returns the metadata of the replica at `idx`.
+    ///
+    /// This allows strategies to make metadata-aware choices, e.g. preferring
+    /// replicas in the same zone.
+    pub fn meta(&self, idx: usize) -> &S::Meta {
+        self[idx].meta()
+    }
+}
+
 impl<'a, K: 'a, S: 'a> ::std::ops::Index<usize> for Replicas<'a, K, S> {
     type Output = S;
 
@@ -1,4 +1,4 @@
-use rand::{rngs::SmallRng, FromEntropy, Rng};
+use rand::{rngs::SmallRng, seq, FromEntropy, Rng};
 
 use choose::{Choose, Replicas};
 use Load;
@@ -16,11 +16,27 @@ use Load;
 /// > The maximum load variance between any two servers is bound by `ln(ln(n))` where `n`
 /// > is the number of servers in the cluster.
 ///
+/// More than two nodes may be sampled with `with_sample_count`, in which case the
+/// least-loaded of the sampled nodes is chosen. This is sometimes called the "power of
+/// K choices"; for large sets of nodes, it may improve balance at the cost of
+/// comparing more loads.
+///
+/// When both nodes report equal load, the tie is broken by index: the first tie goes
+/// to the lower-indexed node, the next to the higher-indexed node, and so on. This
+/// keeps selection reproducible for a given random source while ensuring that neither
+/// position is systematically favored.
+///
 /// [finagle]: https://twitter.github.io/finagle/guide/Clients.html#power-of-two-choices-p2c-least-loaded
 /// [p2c]: http://www.eecs.harvard.edu/~michaelm/postscripts/handbook2001.pdf
 #[derive(Debug)]
 pub struct PowerOfTwoChoices {
     rng: SmallRng,
+
+    /// Whether the next tie is won by the higher-indexed node.
+    prefer_high: bool,
+
+    /// The number of nodes to compare.
+    sample_count: usize,
 }
 
 // ==== impl PowerOfTwoChoices ====
@@ -33,7 +49,22 @@ impl Default for PowerOfTwoChoices {
 
 impl PowerOfTwoChoices {
     pub fn new(rng: SmallRng) -> Self {
-        Self { rng }
+        Self {
+            rng,
+            prefer_high: false,
+            sample_count: 2,
+        }
+    }
+
+    /// Sets the number of nodes sampled for each choice.
+    ///
+    /// `k` is clamped to the number of nodes available when choosing. Values less
+    /// than 2 are treated as 2.
+    pub fn with_sample_count(self, k: usize) -> Self {
+        Self {
+            sample_count: k.max(2),
+            ..self
+        }
     }
 
     /// Returns two random, distinct indices into `ready`.
@@ -54,6 +85,36 @@ impl PowerOfTwoChoices {
         debug_assert!(idx0 != idx1, "random pair must be distinct");
         return (idx0, idx1);
     }
+
+    /// Returns whether node `b` is chosen over node `a`.
+    fn prefers<M>(&mut self, a: usize, a_load: &M, b: usize, b_load: &M) -> bool
+    where
+        M: PartialOrd + ::std::fmt::Debug,
+    {
+        trace!(
+            "choose node[{a}]={a_load:?} node[{b}]={b_load:?}",
+            a = a,
+            b = b,
+            a_load = a_load,
+            b_load = b_load
+        );
+        if b_load < a_load {
+            true
+        } else if a_load < b_load {
+            false
+        } else {
+            self.break_tie(a, b) == b
+        }
+    }
+
+    /// Chooses between two equally-loaded nodes.
+    fn break_tie(&mut self, a: usize, b: usize) -> usize {
+        let (low, high) = if a < b { (a, b) } else { (b, a) };
+        let idx = if self.prefer_high { high } else { low };
+        self.prefer_high = !self.prefer_high;
+        trace!("tie between node[{}] and node[{}]; choosing node[{}]", low, high, idx);
+        idx
+    }
 }
 
 impl<K, L> Choose<K, L> for PowerOfTwoChoices
@@ -61,33 +122,45 @@ where
     L: Load,
     L::Metric: PartialOrd + ::std::fmt::Debug,
 {
-    /// Chooses two distinct nodes at random and compares their load.
+    /// Chooses distinct nodes at random and compares their load.
     ///
-    /// Returns the index of the lesser-loaded node.
+    /// Returns the index of the least-loaded node. Ties alternate between the lower-
+    /// and higher-indexed node.
     fn choose(&mut self, replicas: Replicas<K, L>) -> usize {
-        let (a, b) = self.random_pair(replicas.len());
+        let len = replicas.len();
+        let k = self.sample_count.min(len);
+
+        // Sampling a pair needs no allocation, so it's kept apart from the
+        // general case.
+        if k == 2 {
+            let (a, b) = self.random_pair(len);
+            let a_load = replicas[a].load();
+            let b_load = replicas[b].load();
+            return if self.prefers(a, &a_load, b, &b_load) { b } else { a };
+        }
 
-        let a_load = replicas[a].load();
-        let b_load = replicas[b].load();
-        trace!(
-            "choose node[{a}]={a_load:?} node[{b}]={b_load:?}",
-            a = a,
-            b = b,
-            a_load = a_load,
-            b_load = b_load
-        );
-        if a_load <= b_load {
-            a
-        } else {
-            b
+        let sample = seq::sample_indices(&mut self.rng, len, k);
+        let mut a = sample[0];
+        let mut a_load = replicas[a].load();
+        for &b in &sample[1..] {
+            let b_load = replicas[b].load();
+            if self.prefers(a, &a_load, b, &b_load) {
+                a = b;
+                a_load = b_load;
+            }
         }
+
+        a
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use indexmap::IndexMap;
     use quickcheck::*;
 
+    use choose::replicas;
+    use load::Constant;
     use super::*;
 
     quickcheck! {
@@ -102,4 +175,59 @@ mod tests {
             TestResult::from_bool(a != b)
         }
     }
+
+    #[test]
+    fn equal_load_ties_alternate() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 1));
+        nodes.insert(1, Constant::new((), 1));
+
+        let mut p2c = PowerOfTwoChoices::default();
+        let chosen = (0..6)
+            .map(|_| p2c.choose(replicas(&nodes).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(chosen, vec![0, 1, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn least_loaded_of_sample_is_chosen() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 4));
+        nodes.insert(1, Constant::new((), 1));
+        nodes.insert(2, Constant::new((), 3));
+        nodes.insert(3, Constant::new((), 2));
+
+        // Any three of these nodes include node 1 or node 3, each of which is
+        // less loaded than nodes 0 and 2.
+        let mut p3c = PowerOfTwoChoices::default().with_sample_count(3);
+        for _ in 0..100 {
+            let idx = p3c.choose(replicas(&nodes).unwrap());
+            assert!(idx == 1 || idx == 3, "chose node[{}]", idx);
+        }
+    }
+
+    #[test]
+    fn sample_count_is_clamped() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 3));
+        nodes.insert(1, Constant::new((), 1));
+        nodes.insert(2, Constant::new((), 2));
+
+        let mut p2c = PowerOfTwoChoices::default().with_sample_count(10);
+        for _ in 0..10 {
+            assert_eq!(p2c.choose(replicas(&nodes).unwrap()), 1);
+        }
+    }
+
+    #[test]
+    fn lesser_load_beats_tie_break() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 2));
+        nodes.insert(1, Constant::new((), 1));
+
+        let mut p2c = PowerOfTwoChoices::default();
+        for _ in 0..6 {
+            assert_eq!(p2c.choose(replicas(&nodes).unwrap()), 1);
+        }
+    }
 }
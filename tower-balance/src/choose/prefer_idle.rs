@@ -0,0 +1,140 @@
+use choose::{Choose, Replicas};
+use load::Metric;
+use Load;
+
+/// Chooses an idle node, if any, without comparing loads; otherwise defers to
+/// the inner strategy.
+///
+/// A node is idle when its load is zero: with `PendingRequests`, when it has no
+/// requests in flight, and with `PeakEwma`, when it also has no measured latency.
+/// Such a node is typically new, and preferring it puts fresh capacity to use
+/// quickly.
+///
+/// A cold balancer sees every node as idle. The fast path may be suppressed for
+/// its first choices with `with_warmup`, so that they are spread by the inner
+/// strategy instead.
+#[derive(Debug)]
+pub struct PreferIdle<C> {
+    inner: C,
+
+    /// The number of choices remaining before idle nodes are preferred.
+    warmup: usize,
+
+    /// The index at which the next scan for an idle node starts, so that idle
+    /// nodes are preferred in turn.
+    pos: usize,
+}
+
+// ==== impl PreferIdle ====
+
+impl<C> PreferIdle<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            warmup: 0,
+            pos: 0,
+        }
+    }
+
+    /// Defers the first `choices` choices to the inner strategy, whether or
+    /// not a node is idle.
+    pub fn with_warmup(self, choices: usize) -> Self {
+        Self {
+            warmup: choices,
+            ..self
+        }
+    }
+
+    /// Returns a reference to the inner strategy.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<K, L, C> Choose<K, L> for PreferIdle<C>
+where
+    L: Load,
+    L::Metric: Metric,
+    C: Choose<K, L>,
+{
+    fn choose(&mut self, replicas: Replicas<K, L>) -> usize {
+        if self.warmup > 0 {
+            self.warmup -= 1;
+            return self.inner.choose(replicas);
+        }
+
+        let len = replicas.len();
+        for offset in 0..len {
+            let idx = (self.pos + offset) % len;
+            if replicas[idx].load().to_f64() == 0.0 {
+                trace!("node[{}] is idle", idx);
+                self.pos = (idx + 1) % len;
+                return idx;
+            }
+        }
+
+        self.inner.choose(replicas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use choose::{replicas, PowerOfTwoChoices};
+    use load::Constant;
+    use super::*;
+
+    #[test]
+    fn idle_node_is_chosen_over_loaded_ones() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 3));
+        nodes.insert(1, Constant::new((), 0));
+        nodes.insert(2, Constant::new((), 2));
+
+        let mut choose = PreferIdle::new(PowerOfTwoChoices::default());
+        for _ in 0..10 {
+            assert_eq!(choose.choose(replicas(&nodes).unwrap()), 1);
+        }
+    }
+
+    #[test]
+    fn loaded_nodes_defer_to_inner() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 2));
+        nodes.insert(1, Constant::new((), 1));
+
+        let mut choose = PreferIdle::new(PowerOfTwoChoices::default());
+        for _ in 0..10 {
+            assert_eq!(choose.choose(replicas(&nodes).unwrap()), 1);
+        }
+    }
+
+    #[test]
+    fn warmup_suppresses_idle_preference() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 0));
+        nodes.insert(1, Constant::new((), 0));
+        nodes.insert(2, Constant::new((), 0));
+
+        // During warmup, the inner strategy decides.
+        let mut choose = PreferIdle::new(ConstantChoice(2)).with_warmup(2);
+        assert_eq!(choose.choose(replicas(&nodes).unwrap()), 2);
+        assert_eq!(choose.choose(replicas(&nodes).unwrap()), 2);
+
+        // Afterwards, idle nodes are preferred in turn.
+        let chosen = (0..4)
+            .map(|_| choose.choose(replicas(&nodes).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(chosen, vec![0, 1, 2, 0]);
+    }
+
+    /// Always chooses the same node.
+    struct ConstantChoice(usize);
+
+    impl<K, N> Choose<K, N> for ConstantChoice {
+        fn choose(&mut self, _: Replicas<K, N>) -> usize {
+            self.0
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use choose::{Choose, Replicas};
+use {load, Load};
+
+/// A selection made by a `Choose` strategy, as recorded by `Record`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection<K> {
+    /// The key of the chosen node.
+    pub key: K,
+    /// The index of the chosen node among the candidates.
+    pub index: usize,
+    /// The number of candidates that were chosen between.
+    pub candidates: usize,
+    /// The load of each candidate, if recorded with `Record::with_loads`.
+    pub loads: Vec<f64>,
+}
+
+/// A log of selections, shared between a `Record` and its owner.
+#[derive(Debug)]
+pub struct SelectionLog<K>(Arc<Mutex<Vec<Selection<K>>>>);
+
+/// Records each selection made by a `Choose` strategy into a `SelectionLog`.
+///
+/// This captures production routing decisions, e.g. to investigate a skewed
+/// distribution, so that they may be reproduced with `Replay`. Selections are
+/// only recorded when there are at least two nodes to choose between, since
+/// the balancer does not consult its strategy otherwise.
+pub struct Record<C, K, N> {
+    choose: C,
+    log: SelectionLog<K>,
+    load: Option<fn(&N) -> f64>,
+}
+
+/// Replays selections recorded by `Record`, deterministically.
+///
+/// Each selection chooses the node with the recorded key, so that replay is
+/// unaffected by the order of nodes. If that node is no longer a candidate,
+/// the recorded index is used instead. Once the recorded selections are
+/// exhausted, nodes are chosen in order.
+#[derive(Debug)]
+pub struct Replay<K> {
+    selections: VecDeque<Selection<K>>,
+    pos: usize,
+}
+
+// ===== impl SelectionLog =====
+
+impl<K> SelectionLog<K> {
+    pub fn new() -> Self {
+        SelectionLog(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    /// Returns the selections recorded so far.
+    pub fn selections(&self) -> Vec<Selection<K>>
+    where
+        K: Clone,
+    {
+        self.0.lock().expect("selection log").clone()
+    }
+
+    fn push(&self, selection: Selection<K>) {
+        self.0.lock().expect("selection log").push(selection);
+    }
+}
+
+impl<K> Clone for SelectionLog<K> {
+    fn clone(&self) -> Self {
+        SelectionLog(self.0.clone())
+    }
+}
+
+impl<K> Default for SelectionLog<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ===== impl Record =====
+
+impl<C, K, N> Record<C, K, N> {
+    /// Records each selection made by `choose` into `log`.
+    pub fn new(choose: C, log: SelectionLog<K>) -> Self {
+        Record {
+            choose,
+            log,
+            load: None,
+        }
+    }
+
+    /// Also records the load of each candidate.
+    pub fn with_loads(self) -> Self
+    where
+        N: Load,
+        N::Metric: load::Metric,
+    {
+        Record {
+            load: Some(::load_f64::<N>),
+            ..self
+        }
+    }
+}
+
+impl<C, K, N> Choose<K, N> for Record<C, K, N>
+where
+    C: Choose<K, N>,
+    K: Clone,
+{
+    fn choose(&mut self, replicas: Replicas<K, N>) -> usize {
+        let loads = match self.load {
+            Some(load) => (0..replicas.len()).map(|idx| load(&replicas[idx])).collect(),
+            None => Vec::new(),
+        };
+        let nodes = replicas.0;
+        let index = self.choose.choose(Replicas(nodes));
+        let (key, _) = nodes.get_index(index).expect("invalid choice");
+        self.log.push(Selection {
+            key: key.clone(),
+            index,
+            candidates: nodes.len(),
+            loads,
+        });
+        index
+    }
+}
+
+impl<C, K, N> fmt::Debug for Record<C, K, N>
+where
+    C: fmt::Debug,
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Record")
+            .field("choose", &self.choose)
+            .field("log", &self.log)
+            .finish()
+    }
+}
+
+// ===== impl Replay =====
+
+impl<K> Replay<K> {
+    pub fn new<I>(selections: I) -> Self
+    where
+        I: IntoIterator<Item = Selection<K>>,
+    {
+        Replay {
+            selections: selections.into_iter().collect(),
+            pos: 0,
+        }
+    }
+
+    /// Returns the number of recorded selections not yet replayed.
+    pub fn remaining(&self) -> usize {
+        self.selections.len()
+    }
+}
+
+impl<K, N> Choose<K, N> for Replay<K>
+where
+    K: ::std::hash::Hash + Eq,
+{
+    fn choose(&mut self, replicas: Replicas<K, N>) -> usize {
+        let len = replicas.len();
+        match self.selections.pop_front() {
+            Some(selection) => match replicas.0.get_full(&selection.key) {
+                Some((idx, _, _)) => idx,
+                None => {
+                    debug!("replayed node is not a candidate; using recorded index");
+                    selection.index % len
+                }
+            },
+            None => {
+                let idx = self.pos % len;
+                self.pos = (idx + 1) % len;
+                idx
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use choose::{replicas, PowerOfTwoChoices};
+    use load::Constant;
+    use super::*;
+
+    fn nodes(loads: &[(&'static str, u32)]) -> IndexMap<&'static str, Constant<(), u32>> {
+        loads.iter().map(|&(k, l)| (k, Constant::new((), l))).collect()
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_choices() {
+        let nodes = nodes(&[("a", 3), ("b", 1), ("c", 2), ("d", 1)]);
+        let log = SelectionLog::new();
+        let p2c = PowerOfTwoChoices::new(SmallRng::from_seed([7; 16]));
+        let mut record = Record::new(p2c, log.clone()).with_loads();
+
+        let recorded = (0..20)
+            .map(|_| record.choose(replicas(&nodes).unwrap()))
+            .collect::<Vec<_>>();
+        let selections = log.selections();
+        assert_eq!(selections.len(), 20);
+        assert!(selections.iter().all(|s| s.loads == vec![3.0, 1.0, 2.0, 1.0]));
+
+        let mut replay = Replay::new(selections);
+        let replayed = (0..20)
+            .map(|_| Choose::<_, Constant<(), u32>>::choose(&mut replay, replicas(&nodes).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(replayed, recorded);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn replay_follows_keys_when_nodes_are_reordered() {
+        let log = SelectionLog::new();
+        let mut record = Record::new(PowerOfTwoChoices::default(), log.clone());
+        let original = nodes(&[("a", 1), ("b", 1), ("c", 1)]);
+        for _ in 0..10 {
+            record.choose(replicas(&original).unwrap());
+        }
+
+        let reordered = nodes(&[("c", 1), ("a", 1), ("b", 1)]);
+        let mut replay = Replay::new(log.selections());
+        for selection in log.selections() {
+            let idx = replay.choose(replicas(&reordered).unwrap());
+            assert_eq!(reordered.get_index(idx).unwrap().0, &selection.key);
+        }
+    }
+}
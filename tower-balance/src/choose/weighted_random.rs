@@ -0,0 +1,114 @@
+use rand::{rngs::SmallRng, FromEntropy, Rng};
+
+use choose::{Choose, Replicas};
+use load::Metric;
+use Load;
+
+/// Chooses nodes at random, with probabilities inversely proportional to their load.
+///
+/// This is a load-aware strategy, so this may only be used to choose over services that
+/// implement `Load`.
+///
+/// Unlike `PowerOfTwoChoices`, which picks the least loaded of a small sample, every
+/// node may be chosen, so that load is spread more evenly at the cost of occasionally
+/// choosing a busy node. A node whose load is zero has unbounded weight: if any node is
+/// unloaded, one of the unloaded nodes is chosen uniformly.
+#[derive(Debug)]
+pub struct WeightedRandom {
+    rng: SmallRng,
+}
+
+// ==== impl WeightedRandom ====
+
+impl Default for WeightedRandom {
+    fn default() -> Self {
+        Self::new(SmallRng::from_entropy())
+    }
+}
+
+impl WeightedRandom {
+    pub fn new(rng: SmallRng) -> Self {
+        Self { rng }
+    }
+}
+
+impl<K, L> Choose<K, L> for WeightedRandom
+where
+    L: Load,
+    L::Metric: Metric,
+{
+    /// Draws a node with probability proportional to the inverse of its load.
+    fn choose(&mut self, replicas: Replicas<K, L>) -> usize {
+        let loads = (0..replicas.len())
+            .map(|idx| replicas[idx].load().to_f64())
+            .collect::<Vec<_>>();
+
+        let unloaded = loads.iter().filter(|&&load| load <= 0.0).count();
+        if unloaded > 0 {
+            let nth = self.rng.gen_range(0, unloaded);
+            let idx = loads
+                .iter()
+                .enumerate()
+                .filter(|&(_, &load)| load <= 0.0)
+                .nth(nth)
+                .map(|(idx, _)| idx)
+                .expect("unloaded node");
+            trace!("choose unloaded node[{}]", idx);
+            return idx;
+        }
+
+        let total = loads.iter().map(|load| 1.0 / load).sum::<f64>();
+        let mut point = self.rng.gen::<f64>() * total;
+        for (idx, load) in loads.iter().enumerate() {
+            point -= 1.0 / load;
+            if point < 0.0 {
+                trace!("choose node[{}]={:?} of total weight {}", idx, load, total);
+                return idx;
+            }
+        }
+
+        // Rounding may leave a sliver of weight unaccounted for.
+        loads.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use rand::SeedableRng;
+
+    use choose::replicas;
+    use load::Constant;
+    use super::*;
+
+    fn counts(loads: &[u32], choices: usize) -> Vec<usize> {
+        let mut nodes = IndexMap::new();
+        for (idx, &load) in loads.iter().enumerate() {
+            nodes.insert(idx, Constant::new((), load));
+        }
+
+        let mut choose = WeightedRandom::new(SmallRng::from_seed([7; 16]));
+        let mut counts = vec![0; loads.len()];
+        for _ in 0..choices {
+            counts[choose.choose(replicas(&nodes).unwrap())] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn choices_are_weighted_by_inverse_load() {
+        // Weights of 1, 1/2 and 1/4 give probabilities of 4/7, 2/7 and 1/7.
+        let counts = counts(&[1, 2, 4], 7000);
+        for (&count, &expected) in counts.iter().zip(&[4000, 2000, 1000]) {
+            let error = (count as f64 - expected as f64).abs() / expected as f64;
+            assert!(error < 0.1, "chose {:?}", counts);
+        }
+    }
+
+    #[test]
+    fn unloaded_nodes_are_preferred() {
+        let counts = counts(&[0, 5, 0], 1000);
+        assert_eq!(counts[1], 0);
+        assert!(counts[0] > 400 && counts[2] > 400, "chose {:?}", counts);
+    }
+}
@@ -0,0 +1,78 @@
+use choose::{Choose, Replicas};
+use Load;
+
+/// Chooses nodes sequentially, skipping nodes whose load exceeds a threshold.
+///
+/// This gives the predictable distribution of `RoundRobin` while protecting overloaded
+/// nodes: a node whose load is greater than `threshold` is passed over, and the rotation
+/// continues among the rest. If every node is over the threshold, nodes are chosen
+/// sequentially regardless of load, so that requests are never stalled.
+#[derive(Debug)]
+pub struct WeightedRoundRobin<M> {
+    /// References the index of the next node to be considered.
+    pos: usize,
+
+    /// The greatest load at which a node may be chosen.
+    threshold: M,
+}
+
+// ==== impl WeightedRoundRobin ====
+
+impl<M> WeightedRoundRobin<M> {
+    pub fn new(threshold: M) -> Self {
+        Self { pos: 0, threshold }
+    }
+}
+
+impl<K, N, M> Choose<K, N> for WeightedRoundRobin<M>
+where
+    N: Load<Metric = M>,
+    M: PartialOrd,
+{
+    fn choose(&mut self, nodes: Replicas<K, N>) -> usize {
+        let len = nodes.len();
+        let start = self.pos % len;
+        let idx = (0..len)
+            .map(|i| (start + i) % len)
+            .find(|&idx| nodes[idx].load() <= self.threshold)
+            .unwrap_or(start);
+        self.pos = (idx + 1) % len;
+        idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use choose::replicas;
+    use load::Constant;
+    use super::*;
+
+    #[test]
+    fn overloaded_node_is_skipped() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 1));
+        nodes.insert(1, Constant::new((), 10));
+        nodes.insert(2, Constant::new((), 2));
+
+        let mut wrr = WeightedRoundRobin::new(5);
+        let chosen = (0..4)
+            .map(|_| wrr.choose(replicas(&nodes).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(chosen, vec![0, 2, 0, 2]);
+    }
+
+    #[test]
+    fn rotates_when_all_nodes_are_overloaded() {
+        let mut nodes = IndexMap::new();
+        nodes.insert(0, Constant::new((), 10));
+        nodes.insert(1, Constant::new((), 10));
+
+        let mut wrr = WeightedRoundRobin::new(5);
+        let chosen = (0..4)
+            .map(|_| wrr.choose(replicas(&nodes).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(chosen, vec![0, 1, 0, 1]);
+    }
+}
@@ -0,0 +1,116 @@
+use futures::Poll;
+use std::sync::{Arc, Mutex};
+use tower_discover::Discover;
+use tower_service::Service;
+
+use {Balance, Choose, Error, ResponseFuture};
+
+/// A request annotated with the endpoint that served its previous attempt.
+///
+/// Clones of an `Attempt` share this annotation. A retry policy that clones
+/// its requests with `Clone::clone` therefore issues each retry with the
+/// endpoint of the prior attempt marked for exclusion, which `ExcludePrevious`
+/// honors. A request created with `Attempt::new` excludes no endpoint.
+#[derive(Debug)]
+pub struct Attempt<K, R> {
+    request: R,
+    previous: Arc<Mutex<Option<K>>>,
+}
+
+/// Dispatches `Attempt`s through a `Balance`, avoiding the endpoint that
+/// served the previous attempt of each request.
+///
+/// If the balancer chose the excluded endpoint, the other ready endpoints are
+/// polled for readiness, and the request is dispatched to the first that is
+/// ready. If none is, the excluded endpoint is used.
+#[derive(Debug)]
+pub struct ExcludePrevious<D: Discover, C> {
+    inner: Balance<D, C>,
+}
+
+// ===== impl Attempt =====
+
+impl<K, R> Attempt<K, R> {
+    pub fn new(request: R) -> Self {
+        Attempt {
+            request,
+            previous: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns a reference to the annotated request.
+    pub fn get_ref(&self) -> &R {
+        &self.request
+    }
+
+    /// Returns the key of the endpoint that served the most recent attempt.
+    pub fn previous(&self) -> Option<K>
+    where
+        K: Clone,
+    {
+        self.previous.lock().expect("previous lock").clone()
+    }
+
+    fn record(&self, key: K) {
+        *self.previous.lock().expect("previous lock") = Some(key);
+    }
+}
+
+impl<K, R: Clone> Clone for Attempt<K, R> {
+    fn clone(&self) -> Self {
+        Attempt {
+            request: self.request.clone(),
+            previous: self.previous.clone(),
+        }
+    }
+}
+
+// ===== impl ExcludePrevious =====
+
+impl<D: Discover, C> ExcludePrevious<D, C> {
+    pub fn new(inner: Balance<D, C>) -> Self {
+        ExcludePrevious { inner }
+    }
+
+    /// Returns a reference to the inner balancer.
+    pub fn get_ref(&self) -> &Balance<D, C> {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner balancer.
+    pub fn get_mut(&mut self) -> &mut Balance<D, C> {
+        &mut self.inner
+    }
+
+    /// Consumes `self`, returning the inner balancer.
+    pub fn into_inner(self) -> Balance<D, C> {
+        self.inner
+    }
+}
+
+impl<D, C, Request> Service<Attempt<D::Key, Request>> for ExcludePrevious<D, C>
+where
+    D: Discover,
+    D::Key: Clone,
+    D::Service: Service<Request>,
+    C: Choose<D::Key, D::Service>,
+{
+    type Response = <D::Service as Service<Request>>::Response;
+    type Error = Error<<D::Service as Service<Request>>::Error, D::Error>;
+    type Future = ResponseFuture<<D::Service as Service<Request>>::Future, D::Error>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Service::<Request>::poll_ready(&mut self.inner)
+    }
+
+    fn call(&mut self, attempt: Attempt<D::Key, Request>) -> Self::Future {
+        if let Some(key) = attempt.previous() {
+            self.inner
+                .choose_ready_excluding(&key, <D::Service as Service<Request>>::poll_ready);
+        }
+
+        let key = self.inner.chosen_key().expect("not ready").clone();
+        attempt.record(key);
+        self.inner.call(D::Service::call, attempt.request)
+    }
+}
@@ -10,21 +10,34 @@ extern crate tokio_timer;
 extern crate tower_discover;
 extern crate tower_service;
 extern crate tower_direct_service;
+extern crate tower_timeout;
 
 use futures::{Async, Future, Poll};
 use indexmap::IndexMap;
 use rand::{rngs::SmallRng, SeedableRng};
-use std::{fmt, error};
+use std::{fmt, error, mem};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use tower_discover::Discover;
 use tower_service::Service;
 use tower_direct_service::DirectService;
 
 pub mod choose;
 pub mod load;
+mod exclude;
+mod meta;
+mod shadow;
+mod timeout;
 
 pub use choose::Choose;
+pub use exclude::{Attempt, ExcludePrevious};
 pub use load::Load;
+use load::Seed;
+pub use meta::{EndpointMeta, WithMeta};
+pub use shadow::{Shadow, ShadowFuture};
+pub use timeout::{TimedEndpoint, TimedEndpointFuture, Timeouts, WithEndpointTimeout};
 
 /// Balances requests across a set of inner services.
 #[derive(Debug)]
@@ -32,6 +45,9 @@ pub struct Balance<D: Discover, C> {
     /// Provides endpoints from service discovery.
     discover: D,
 
+    /// Whether `discover` will yield no more changes, so that it is no longer polled.
+    discover_done: bool,
+
     /// Determines which endpoint is ready to be used next.
     choose: C,
 
@@ -48,6 +64,77 @@ pub struct Balance<D: Discover, C> {
 
     /// Newly-added endpoints that have not yet become ready.
     not_ready: IndexMap<D::Key, D::Service>,
+
+    /// Endpoints that have been drained and are not dispatched new requests.
+    drained: IndexMap<D::Key, D::Service>,
+
+    /// Services that will replace the endpoint with the same key once they are ready.
+    replacements: IndexMap<D::Key, D::Service>,
+
+    /// Replaced services, each held with the dispatch epoch in which it was replaced
+    /// until the requests dispatched in that epoch have completed.
+    retiring: Vec<(D::Service, Arc<()>)>,
+
+    /// Held by the response of each request dispatched since a service was last
+    /// replaced, so that a replaced service is not dropped while it may have
+    /// requests in flight.
+    epoch: Arc<()>,
+
+    /// Initial loads for endpoints that have not yet been discovered.
+    seeds: Option<Seeds<D::Key, D::Service>>,
+
+    /// The load above which an endpoint is not dispatched new requests.
+    ceiling: Option<Ceiling<D::Service>>,
+
+    /// Endpoints held idle until the others are saturated.
+    spares: Option<Spares<D::Key, D::Service>>,
+
+    /// The most recent selection, reused rather than consulting `choose` each time.
+    selection: Option<SelectionCache<D::Key>>,
+
+    /// Ejects endpoints whose responses repeatedly time out.
+    ejection: Option<Ejection<D::Key, D::Service>>,
+}
+
+/// Holds initial loads until the endpoints they describe are discovered.
+struct Seeds<K, S> {
+    loads: HashMap<K, f64>,
+    seed: fn(&mut S, f64),
+}
+
+/// Refuses new requests to endpoints whose load exceeds `ceiling`.
+struct Ceiling<S> {
+    ceiling: f64,
+    load: fn(&S) -> f64,
+}
+
+/// Holds a selected endpoint's key so that it may be selected again without consulting
+/// the `Choose` strategy.
+struct SelectionCache<K> {
+    /// The number of selections for which a chosen endpoint is reused.
+    calls: usize,
+    /// The number of further selections for which `key` may be reused.
+    remaining: usize,
+    key: Option<K>,
+    clone_key: fn(&K) -> K,
+}
+
+/// Ejects endpoints once `max_timeouts` of their responses have timed out in a row.
+struct Ejection<K, S> {
+    max_timeouts: usize,
+    timeouts: fn(&S) -> usize,
+    reset: fn(&S),
+    /// Keys of the ejected endpoints that have not been reinstated or removed.
+    ejected: HashSet<K>,
+    clone_key: fn(&K) -> K,
+}
+
+/// Identifies a spare tier of endpoints, used only when every primary endpoint's load
+/// exceeds `threshold`.
+struct Spares<K, S> {
+    is_spare: Box<Fn(&K) -> bool + Send + Sync>,
+    threshold: f64,
+    load: fn(&S) -> f64,
 }
 
 /// Error produced by `Balance`
@@ -56,9 +143,11 @@ pub enum Error<T, U> {
     Inner(T),
     Balance(U),
     NotReady,
+    /// Every ready endpoint's load exceeds the per-endpoint ceiling.
+    Overloaded,
 }
 
-pub struct ResponseFuture<F: Future, E>(F, PhantomData<E>);
+pub struct ResponseFuture<F: Future, E>(F, Arc<()>, PhantomData<E>);
 
 // ===== impl Balance =====
 
@@ -97,6 +186,17 @@ where
         let rng = SmallRng::from_rng(rng)?;
         Ok(Self::new(discover, choose::PowerOfTwoChoices::new(rng)))
     }
+
+    /// Samples `k` endpoints for each choice, rather than 2, choosing the
+    /// least-loaded of those sampled.
+    ///
+    /// `k` is clamped to the number of ready endpoints.
+    pub fn with_sample_count(self, k: usize) -> Self {
+        Self {
+            choose: self.choose.with_sample_count(k),
+            ..self
+        }
+    }
 }
 
 impl<D: Discover> Balance<D, choose::RoundRobin> {
@@ -108,6 +208,59 @@ impl<D: Discover> Balance<D, choose::RoundRobin> {
     }
 }
 
+impl<D: Discover> Balance<D, choose::Replay<D::Key>> {
+    /// Replays selections recorded by `choose::Record`, e.g. to reproduce a production
+    /// routing decision in a test.
+    pub fn replay<I>(discover: D, selections: I) -> Self
+    where
+        I: IntoIterator<Item = choose::Selection<D::Key>>,
+    {
+        Self::new(discover, choose::Replay::new(selections))
+    }
+}
+
+impl<D> Balance<D, choose::WeightedRandom>
+where
+    D: Discover,
+    D::Service: Load,
+    <D::Service as Load>::Metric: load::Metric,
+{
+    /// Chooses services at random, with probabilities inversely proportional to their
+    /// load.
+    ///
+    /// This configuration may be preferred to P2C when load should be spread across all
+    /// services, rather than concentrated on the least loaded of a sample.
+    pub fn weighted_random(discover: D) -> Self {
+        Self::new(discover, choose::WeightedRandom::default())
+    }
+
+    /// Initializes a weighted random load balancer from the provided randomization
+    /// source.
+    pub fn weighted_random_with_rng<R: rand::Rng>(
+        discover: D,
+        rng: &mut R,
+    ) -> Result<Self, rand::Error> {
+        let rng = SmallRng::from_rng(rng)?;
+        Ok(Self::new(discover, choose::WeightedRandom::new(rng)))
+    }
+}
+
+impl<D, M> Balance<D, choose::WeightedRoundRobin<M>>
+where
+    D: Discover,
+    D::Service: Load<Metric = M>,
+    M: PartialOrd,
+{
+    /// Attempts to choose services sequentially, skipping services whose load exceeds
+    /// `threshold`.
+    ///
+    /// This configuration is prefered when a predictable distribution is desired, but
+    /// overloaded services should be avoided.
+    pub fn weighted_round_robin(discover: D, threshold: M) -> Self {
+        Self::new(discover, choose::WeightedRoundRobin::new(threshold))
+    }
+}
+
 impl<D, C> Balance<D, C>
 where
     D: Discover,
@@ -117,14 +270,159 @@ where
     pub fn new(discover: D, choose: C) -> Self {
         Self {
             discover,
+            discover_done: false,
             choose,
             chosen_ready_index: None,
             dispatched_ready_index: None,
             ready: IndexMap::default(),
             not_ready: IndexMap::default(),
+            drained: IndexMap::default(),
+            replacements: IndexMap::default(),
+            retiring: Vec::new(),
+            epoch: Arc::new(()),
+            seeds: None,
+            ceiling: None,
+            spares: None,
+            selection: None,
+            ejection: None,
         }
     }
 
+    /// Reuses each endpoint chosen by the `Choose` strategy for up to `calls` selections
+    /// before choosing again.
+    ///
+    /// For large sets of endpoints, this trades some freshness of load information for
+    /// less work per request. The endpoint is chosen again early if it is no longer
+    /// ready. A value of 0 or 1 chooses for every selection.
+    pub fn with_selection_cache(self, calls: usize) -> Self
+    where
+        D::Key: Clone,
+    {
+        Self {
+            selection: Some(SelectionCache {
+                calls,
+                remaining: 0,
+                key: None,
+                clone_key: <D::Key as Clone>::clone,
+            }),
+            ..self
+        }
+    }
+
+    /// Holds the endpoints for which `is_spare` returns true in reserve, e.g. to avoid
+    /// the cost of using them while the other, primary, endpoints can keep up.
+    ///
+    /// Spare endpoints are dispatched requests only while every ready primary endpoint's
+    /// load exceeds `threshold`. Once any primary endpoint's load falls to `threshold`,
+    /// requests return to the primary tier. If no spare endpoint is ready, primary
+    /// endpoints are used regardless of their load.
+    pub fn with_spare_tier<F, M>(self, is_spare: F, threshold: M) -> Self
+    where
+        D::Service: Load,
+        <D::Service as Load>::Metric: load::Metric,
+        F: Fn(&D::Key) -> bool + Send + Sync + 'static,
+        M: load::Metric,
+    {
+        Self {
+            spares: Some(Spares {
+                is_spare: Box::new(is_spare),
+                threshold: threshold.to_f64(),
+                load: load_f64::<D::Service>,
+            }),
+            ..self
+        }
+    }
+
+    /// Refuses to dispatch new requests to an endpoint whose load exceeds `ceiling`,
+    /// so that a spike on one endpoint is not worsened by further requests.
+    ///
+    /// When the chosen endpoint is over its ceiling, the next ready endpoint that is not
+    /// is used instead. If every ready endpoint is over its ceiling, `poll_ready` fails
+    /// with `Error::Overloaded`, shedding the request.
+    pub fn with_per_endpoint_ceiling<M>(self, ceiling: M) -> Self
+    where
+        D::Service: Load,
+        <D::Service as Load>::Metric: load::Metric,
+        M: load::Metric,
+    {
+        Self {
+            ceiling: Some(Ceiling {
+                ceiling: ceiling.to_f64(),
+                load: load_f64::<D::Service>,
+            }),
+            ..self
+        }
+    }
+
+    /// Ejects an endpoint once `max_timeouts` of its responses have timed out in a row,
+    /// e.g. so that a stuck endpoint does not hold requests while others are available.
+    ///
+    /// Endpoints are given response timeouts by `WithEndpointTimeout`. Ejected endpoints
+    /// are drained, as by `drain_endpoint`, until `undrain_endpoint` reinstates them with
+    /// their timeouts forgotten.
+    pub fn with_timeout_ejection(self, max_timeouts: usize) -> Self
+    where
+        D::Key: Clone,
+        D::Service: Timeouts,
+    {
+        assert!(max_timeouts > 0, "max_timeouts must be positive");
+        Self {
+            ejection: Some(Ejection {
+                max_timeouts,
+                timeouts: D::Service::consecutive_timeouts,
+                reset: D::Service::reset_timeouts,
+                ejected: HashSet::new(),
+                clone_key: D::Key::clone,
+            }),
+            ..self
+        }
+    }
+
+    /// Mirrors `fraction` of requests to endpoints from `shadow`, e.g. a new deployment
+    /// under test, discarding their responses.
+    ///
+    /// Shadow endpoints are chosen in round-robin order. Requests are cloned to be
+    /// mirrored, and are only mirrored while a shadow endpoint is ready. See `Shadow`.
+    pub fn with_shadow<S, Request>(
+        self,
+        shadow: S,
+        fraction: f64,
+    ) -> Shadow<Self, Balance<S, choose::RoundRobin>, Request>
+    where
+        D::Service: Service<Request>,
+        S: Discover,
+        S::Service: Service<Request>,
+        Request: Clone,
+    {
+        Shadow::new(self, Balance::round_robin(shadow), fraction)
+    }
+
+    /// Initializes the loads of endpoints from a snapshot, e.g. one taken before a
+    /// restart, so that load-aware selection does not start cold.
+    ///
+    /// Endpoints that are already known are seeded immediately. Others are seeded as
+    /// they are discovered. Each seed is applied at most once, and replaces any seeds
+    /// that have not yet been applied.
+    pub fn seed_loads<M>(&mut self, loads: HashMap<D::Key, M>)
+    where
+        D::Service: Seed,
+        M: load::Metric,
+    {
+        let mut seeds = Seeds {
+            loads: loads.into_iter().map(|(k, m)| (k, m.to_f64())).collect(),
+            seed: D::Service::seed,
+        };
+
+        for (key, svc) in self.ready.iter_mut()
+            .chain(self.not_ready.iter_mut())
+            .chain(self.drained.iter_mut())
+        {
+            seeds.apply(key, svc);
+        }
+
+        self.seeds = Some(seeds);
+    }
+
     /// Returns true iff there are ready services.
     ///
     /// This is not authoritative and is only useful after `poll_ready` has been called.
@@ -153,29 +451,173 @@ where
         self.not_ready.len()
     }
 
+    /// Returns the metadata of the service identified by `key`, if it is known.
+    pub fn endpoint_meta(&self, key: &D::Key) -> Option<&<D::Service as EndpointMeta>::Meta>
+    where
+        D::Service: EndpointMeta,
+    {
+        self.ready.get(key)
+            .or_else(|| self.not_ready.get(key))
+            .or_else(|| self.drained.get(key))
+            .map(EndpointMeta::meta)
+    }
+
+    /// Removes every service for which `pred` returns true, given its key and metadata,
+    /// e.g. all services running a bad version.
+    ///
+    /// Requests that have already been dispatched to a removed service are unaffected.
+    /// A removed service is inserted again if `discover` later re-inserts it. Returns the
+    /// number of services removed.
+    pub fn remove_where<F>(&mut self, pred: F) -> usize
+    where
+        D::Service: EndpointMeta,
+        F: Fn(&D::Key, &<D::Service as EndpointMeta>::Meta) -> bool,
+    {
+        let before = self.ready.len() + self.not_ready.len() + self.drained.len();
+
+        let keep = |key: &D::Key, svc: &mut D::Service| !pred(key, svc.meta());
+        self.ready.retain(&keep);
+        self.not_ready.retain(&keep);
+        self.drained.retain(&keep);
+
+        let removed = before - (self.ready.len() + self.not_ready.len() + self.drained.len());
+        if let Some(ref mut ejection) = self.ejection {
+            let drained = &self.drained;
+            ejection.ejected.retain(|key| drained.contains_key(key));
+        }
+        if removed > 0 {
+            debug!("removed {} endpoints", removed);
+            // Removal may reorder `ready`, so prior indices are no longer valid.
+            self.chosen_ready_index = None;
+            self.dispatched_ready_index = None;
+        }
+        removed
+    }
+
+    /// Counts the number of drained services.
+    pub fn num_drained(&self) -> usize {
+        self.drained.len()
+    }
+
+    /// Counts the number of services ejected from rotation because their responses
+    /// repeatedly timed out, as configured by `with_timeout_ejection`.
+    ///
+    /// Ejected services are drained, but services drained by `drain_endpoint` are not
+    /// counted. An ejected service is no longer counted once `undrain_endpoint`
+    /// reinstates it or `discover` removes it.
+    pub fn ejected_count(&self) -> usize {
+        self.ejection.as_ref().map_or(0, |ejection| ejection.ejected.len())
+    }
+
+    /// Stops dispatching new requests to the service identified by `key`.
+    ///
+    /// Requests that have already been dispatched to the service are unaffected. A
+    /// drained service is still subject to discovery: it is dropped if `discover`
+    /// removes it, and it remains drained if `discover` replaces it.
+    ///
+    /// `poll_ready` must be called before the next request is dispatched. Returns false
+    /// if no such service is known.
+    pub fn drain_endpoint(&mut self, key: &D::Key) -> bool {
+        let (key, svc) = match self.ready.swap_remove_full(key) {
+            Some((_, key, svc)) => {
+                // Removal may reorder `ready`, so prior indices are no longer valid.
+                self.chosen_ready_index = None;
+                self.dispatched_ready_index = None;
+                (key, svc)
+            }
+            None => match self.not_ready.swap_remove_full(key) {
+                Some((_, key, svc)) => (key, svc),
+                None => return self.drained.contains_key(key),
+            },
+        };
+
+        debug!("draining endpoint");
+        self.drained.insert(key, svc);
+        true
+    }
+
+    /// Resumes dispatching requests to a service drained by `drain_endpoint`.
+    ///
+    /// The service must become ready again before it is used. Returns false if the
+    /// service is not drained.
+    pub fn undrain_endpoint(&mut self, key: &D::Key) -> bool {
+        match self.drained.swap_remove_full(key) {
+            Some((_, key, svc)) => {
+                debug!("undraining endpoint");
+                self.forget_ejected(&key);
+                self.not_ready.insert(key, svc);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the service identified by `key` with `service`, e.g. to upgrade its
+    /// connection, without taking the endpoint out of rotation.
+    ///
+    /// The current service continues to be dispatched requests until `service` becomes
+    /// ready, at which point new requests are dispatched to `service` under the same key.
+    /// Requests that have already been dispatched to the replaced service are
+    /// unaffected: when the balancer is used as a `DirectService`, the replaced service
+    /// is driven by `poll_service` and closed once they complete. Otherwise, it is
+    /// dropped by `poll_ready` once every request dispatched before `service` took its
+    /// place has completed.
+    ///
+    /// If `discover` removes or replaces the endpoint first, `service` is dropped. Returns
+    /// false if no such service is known.
+    pub fn replace_endpoint(&mut self, key: D::Key, service: D::Service) -> bool {
+        let known = self.ready.contains_key(&key)
+            || self.not_ready.contains_key(&key)
+            || self.drained.contains_key(&key);
+        if known {
+            debug!("replacing endpoint");
+            self.replacements.insert(key, service);
+        }
+        known
+    }
+
     /// Polls `discover` for updates, adding new items to `not_ready`.
     ///
     /// Removals may alter the order of either `ready` or `not_ready`.
+    ///
+    /// Once `discover` is done, it is no longer polled, and the services it has yielded
+    /// continue to be used.
     fn update_from_discover<E>(&mut self) -> Result<(), Error<E, D::Error>> {
+        if self.discover_done {
+            trace!("discover is done; skipping update");
+            return Ok(());
+        }
+
         debug!("updating from discover");
         use tower_discover::Change::*;
 
         while let Async::Ready(change) = self.discover.poll().map_err(Error::Balance)? {
             match change {
                 Insert(key, mut svc) => {
+                    if let Some(ref mut seeds) = self.seeds {
+                        seeds.apply(&key, &mut svc);
+                    }
+
                     // If the `Insert`ed service is a duplicate of a service already
                     // in the ready list, remove the ready service first. The new
-                    // service will then be inserted into the not-ready list.
+                    // service will then be inserted into the not-ready list, unless
+                    // the service it replaces has been drained.
                     self.ready.remove(&key);
+                    self.replacements.remove(&key);
 
-                    self.not_ready.insert(key, svc);
+                    if self.drained.contains_key(&key) {
+                        self.drained.insert(key, svc);
+                    } else {
+                        self.not_ready.insert(key, svc);
+                    }
                 }
 
                 Remove(key) => {
-                    let _ejected = match self.ready.remove(&key) {
-                        None => self.not_ready.remove(&key),
-                        Some(s) => Some(s),
-                    };
+                    self.replacements.remove(&key);
+                    self.forget_ejected(&key);
+                    let _ejected = self.ready.remove(&key)
+                        .or_else(|| self.not_ready.remove(&key))
+                        .or_else(|| self.drained.remove(&key));
                     // XXX is it safe to just drop the Service? Or do we need some sort of
                     // graceful teardown?
                     // TODO: poll_close
@@ -183,6 +625,95 @@ where
             }
         }
 
+        if self.discover.is_done() {
+            debug!("discover is done");
+            self.discover_done = true;
+        }
+
+        Ok(())
+    }
+
+    /// Drains all endpoints whose responses have timed out too many times in a row.
+    ///
+    /// Ejection may alter the order of either `ready` or `not_ready`.
+    fn eject_timed_out(&mut self) {
+        let ejection = match self.ejection {
+            Some(ref mut ejection) => ejection,
+            None => return,
+        };
+
+        for idx in (0..self.ready.len()).rev() {
+            if ejection.ejects(self.ready.get_index(idx).expect("invalid ready index").1) {
+                debug!("ready[{}]: timed out repeatedly; ejecting", idx);
+                let (key, svc) = self.ready.swap_remove_index(idx).expect("invalid ready index");
+                ejection.ejected.insert((ejection.clone_key)(&key));
+                self.drained.insert(key, svc);
+                // Removal may reorder `ready`, so prior indices are no longer valid.
+                self.dispatched_ready_index = None;
+            }
+        }
+
+        for idx in (0..self.not_ready.len()).rev() {
+            if ejection.ejects(self.not_ready.get_index(idx).expect("invalid not_ready index").1) {
+                debug!("not_ready[{}]: timed out repeatedly; ejecting", idx);
+                let (key, svc) = self.not_ready
+                    .swap_remove_index(idx)
+                    .expect("invalid not_ready index");
+                ejection.ejected.insert((ejection.clone_key)(&key));
+                self.drained.insert(key, svc);
+            }
+        }
+    }
+
+    /// Stops counting the endpoint identified by `key` as ejected.
+    fn forget_ejected(&mut self, key: &D::Key) {
+        if let Some(ref mut ejection) = self.ejection {
+            ejection.ejected.remove(key);
+        }
+    }
+
+    /// Calls `poll_ready` on all pending replacements.
+    ///
+    /// When `poll_ready` returns ready, the replacement takes the place of the service
+    /// with the same key, which is moved to `retiring`, and a new dispatch epoch begins.
+    /// The order of `ready` is not altered.
+    fn promote_replacements<F, E>(&mut self, mut poll_ready: F) -> Result<(), Error<E, D::Error>>
+    where
+        F: FnMut(&mut D::Service) -> Poll<(), E>,
+    {
+        let mut replaced_any = false;
+        for idx in (0..self.replacements.len()).rev() {
+            let is_ready = {
+                let (_, svc) = self.replacements
+                    .get_index_mut(idx)
+                    .expect("invalid replacements index");
+                poll_ready(svc).map_err(Error::Inner)?.is_ready()
+            };
+            if !is_ready {
+                continue;
+            }
+
+            let (key, svc) = self.replacements
+                .swap_remove_index(idx)
+                .expect("invalid replacements index");
+            let replaced = if let Some(old) = self.ready.get_mut(&key) {
+                mem::replace(old, svc)
+            } else if let Some(old) = self.not_ready.get_mut(&key) {
+                mem::replace(old, svc)
+            } else if let Some(old) = self.drained.get_mut(&key) {
+                mem::replace(old, svc)
+            } else {
+                continue;
+            };
+            debug!("replacement ready; retiring replaced endpoint");
+            self.retiring.push((replaced, self.epoch.clone()));
+            replaced_any = true;
+        }
+
+        if replaced_any {
+            self.epoch = Arc::new(());
+        }
+
         Ok(())
     }
 
@@ -267,10 +798,30 @@ where
             debug!("choosing from {} replicas", n);
             let idx = match n {
                 0 => return Ok(Async::NotReady),
+                // With a single endpoint, there is nothing to choose between, so the
+                // strategy is not consulted.
                 1 => 0,
-                _ => {
-                    let replicas = choose::replicas(&self.ready).expect("too few replicas");
-                    self.choose.choose(replicas)
+                _ => match self.cached_selection() {
+                    Some(idx) => {
+                        trace!("reusing cached selection ready[{}]", idx);
+                        idx
+                    }
+                    None => {
+                        let replicas = choose::replicas(&self.ready).expect("too few replicas");
+                        let idx = self.choose.choose(replicas);
+                        self.cache_selection(idx);
+                        idx
+                    }
+                },
+            };
+
+            // Prefer the active tier, but fall back to any endpoint within its ceiling.
+            let tier = self.active_tier();
+            let idx = match self.admit(idx, tier).or_else(|| self.admit(idx, None)) {
+                Some(idx) => idx,
+                None => {
+                    debug!("all {} ready endpoints are over their ceiling", n);
+                    return Err(Error::Overloaded);
                 }
             };
 
@@ -286,6 +837,74 @@ where
         }
     }
 
+    /// Returns `idx` if its service is within the load ceiling and, if `spare` is set,
+    /// in the given tier; or else the index of the next ready service that is, if there
+    /// is one.
+    fn admit(&self, idx: usize, spare: Option<bool>) -> Option<usize> {
+        let n = self.ready.len();
+        (0..n)
+            .map(|i| (idx + i) % n)
+            .find(|&i| {
+                let (key, svc) = self.ready.get_index(i).expect("invalid ready index");
+                if spare.map_or(false, |spare| self.is_spare(key) != spare) {
+                    return false;
+                }
+
+                match self.ceiling {
+                    Some(ref ceiling) if !ceiling.admits(svc) => {
+                        trace!("ready[{}]: over ceiling; skipping", i);
+                        false
+                    }
+                    _ => true,
+                }
+            })
+    }
+
+    /// Returns whether requests should be dispatched to the spare tier, if there is one.
+    ///
+    /// The spare tier is active while it has a ready endpoint and every ready primary
+    /// endpoint is saturated.
+    fn active_tier(&self) -> Option<bool> {
+        let spares = self.spares.as_ref()?;
+
+        let mut spare_ready = false;
+        for (key, svc) in &self.ready {
+            if (spares.is_spare)(key) {
+                spare_ready = true;
+            } else if !spares.is_saturated(svc) {
+                return Some(false);
+            }
+        }
+
+        trace!("primary tier saturated; spare ready={}", spare_ready);
+        Some(spare_ready)
+    }
+
+    /// Returns the index of the cached selection, if it may be reused and is still ready.
+    fn cached_selection(&mut self) -> Option<usize> {
+        let cache = self.selection.as_mut()?;
+        if cache.remaining == 0 {
+            return None;
+        }
+
+        let (idx, _, _) = self.ready.get_full(cache.key.as_ref()?)?;
+        cache.remaining -= 1;
+        Some(idx)
+    }
+
+    /// Caches the selection of `ready[idx]`, if selections are cached.
+    fn cache_selection(&mut self, idx: usize) {
+        if let Some(ref mut cache) = self.selection {
+            let (key, _) = self.ready.get_index(idx).expect("invalid ready index");
+            cache.key = Some((cache.clone_key)(key));
+            cache.remaining = cache.calls.saturating_sub(1);
+        }
+    }
+
+    fn is_spare(&self, key: &D::Key) -> bool {
+        self.spares.as_ref().map_or(false, |spares| (spares.is_spare)(key))
+    }
+
     fn poll_ready_inner<F, E>(&mut self, mut poll_ready: F) -> Poll<(), Error<E, D::Error>>
     where
         F: FnMut(&mut D::Service) -> Poll<(), E>,
@@ -303,6 +922,8 @@ where
 
         // Update `not_ready` and `ready`.
         self.update_from_discover()?;
+        self.eject_timed_out();
+        self.promote_replacements(&mut poll_ready)?;
         self.promote_to_ready(&mut poll_ready)?;
 
         // Choose the next service to be used by `call`.
@@ -319,7 +940,61 @@ where
         self.dispatched_ready_index = Some(idx);
 
         let rsp = call(svc, request);
-        ResponseFuture(rsp, PhantomData)
+        ResponseFuture(rsp, self.epoch.clone(), PhantomData)
+    }
+
+    /// Returns the key of the service chosen to dispatch the next request.
+    fn chosen_key(&self) -> Option<&D::Key> {
+        let idx = self.chosen_ready_index?;
+        self.ready.get_index(idx).map(|(key, _)| key)
+    }
+
+    /// If `key` was chosen to dispatch the next request, chooses another ready service
+    /// instead, if there is one.
+    ///
+    /// Each alternative is polled for readiness, as by `poll_ready`, so that the request
+    /// is only dispatched to a service that is ready; those that are not are moved to
+    /// `not_ready`. If no alternative is ready, `key` remains chosen.
+    fn choose_ready_excluding<F, E>(&mut self, key: &D::Key, mut poll_ready: F)
+    where
+        D::Key: Clone,
+        F: FnMut(&mut D::Service) -> Poll<(), E>,
+    {
+        let start = match self.chosen_ready_index {
+            Some(idx) if self.chosen_key() == Some(key) => idx,
+            _ => return,
+        };
+
+        let n = self.ready.len();
+        let candidates = (1..n)
+            .filter_map(|i| {
+                let (candidate, svc) = self.ready.get_index((start + i) % n)?;
+                match self.ceiling {
+                    Some(ref ceiling) if !ceiling.admits(svc) => None,
+                    _ => Some(candidate.clone()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for candidate in candidates {
+            // Indices shift as services that are not ready are moved to `not_ready`.
+            let idx = match self.ready.get_full(&candidate) {
+                Some((idx, _, _)) => idx,
+                None => continue,
+            };
+            match self.poll_ready_index(idx, &mut poll_ready) {
+                Some(Ok(Async::Ready(()))) => {
+                    trace!("ready[{}]: chosen instead of excluded endpoint", idx);
+                    self.chosen_ready_index = Some(idx);
+                    return;
+                }
+                Some(Err(_)) => debug!("ready[{}]: failed; not chosen", idx),
+                _ => {}
+            }
+        }
+
+        debug!("no alternative to excluded endpoint is ready");
+        self.chosen_ready_index = self.ready.get_full(key).map(|(idx, _, _)| idx);
     }
 }
 
@@ -338,7 +1013,12 @@ where
     /// When `Async::Ready` is returned, `chosen_ready_index` is set with a valid index
     /// into `ready` referring to a `Service` that is ready to disptach a request.
     fn poll_ready(&mut self) -> Poll<(), Self::Error> {
-        self.poll_ready_inner(D::Service::poll_ready)
+        let ready = self.poll_ready_inner(D::Service::poll_ready);
+        // A `Service` cannot be closed, so a replaced service is dropped once every
+        // request that may have been dispatched to it has completed, since dropping a
+        // service, e.g. its connection, may fail its in-flight responses.
+        self.retiring.retain(|&(_, ref epoch)| Arc::strong_count(epoch) > 1);
+        ready
     }
 
     fn call(&mut self, request: Request) -> Self::Future {
@@ -381,6 +1061,27 @@ where
             }
         }
 
+        // Drained services may still have requests in flight.
+        for (_, svc) in &mut self.drained {
+            if let Async::NotReady = svc.poll_service().map_err(Error::Inner)? {
+                any_not_ready = true;
+            }
+        }
+
+        // Replaced services are closed once their in-flight requests complete.
+        let mut idx = 0;
+        while idx < self.retiring.len() {
+            match self.retiring[idx].0.poll_close().map_err(Error::Inner)? {
+                Async::Ready(()) => {
+                    self.retiring.swap_remove(idx);
+                }
+                Async::NotReady => {
+                    any_not_ready = true;
+                    idx += 1;
+                }
+            }
+        }
+
         if any_not_ready {
             Ok(Async::NotReady)
         } else {
@@ -406,12 +1107,40 @@ where
                 return false;
             }
         });
+        self.drained.retain(|_, svc| match svc.poll_close() {
+            Ok(Async::Ready(())) => return false,
+            Ok(Async::NotReady) => return true,
+            Err(e) => {
+                err = Some(e);
+                return false;
+            }
+        });
+
+        // Replacements have no requests in flight.
+        self.replacements.clear();
+        let mut idx = 0;
+        while idx < self.retiring.len() {
+            match self.retiring[idx].0.poll_close() {
+                Ok(Async::Ready(())) => {
+                    self.retiring.swap_remove(idx);
+                }
+                Ok(Async::NotReady) => idx += 1,
+                Err(e) => {
+                    err = Some(e);
+                    self.retiring.swap_remove(idx);
+                }
+            }
+        }
 
         if let Some(e) = err {
             return Err(Error::Inner(e));
         }
 
-        if self.ready.is_empty() && self.not_ready.is_empty() {
+        if self.ready.is_empty()
+            && self.not_ready.is_empty()
+            && self.drained.is_empty()
+            && self.retiring.is_empty()
+        {
             Ok(Async::Ready(()))
         } else {
             Ok(Async::NotReady)
@@ -419,6 +1148,98 @@ where
     }
 }
 
+// ===== impl Seeds =====
+
+impl<K: Hash + Eq, S> Seeds<K, S> {
+    fn apply(&mut self, key: &K, svc: &mut S) {
+        if let Some(load) = self.loads.remove(key) {
+            (self.seed)(svc, load);
+        }
+    }
+}
+
+impl<K: fmt::Debug + Hash + Eq, S> fmt::Debug for Seeds<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Seeds")
+            .field("loads", &self.loads)
+            .finish()
+    }
+}
+
+// ===== impl Ceiling =====
+
+impl<S> Ceiling<S> {
+    fn admits(&self, svc: &S) -> bool {
+        (self.load)(svc) <= self.ceiling
+    }
+}
+
+impl<S> fmt::Debug for Ceiling<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ceiling")
+            .field("ceiling", &self.ceiling)
+            .finish()
+    }
+}
+
+// ===== impl SelectionCache =====
+
+impl<K: fmt::Debug> fmt::Debug for SelectionCache<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SelectionCache")
+            .field("calls", &self.calls)
+            .field("remaining", &self.remaining)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+// ===== impl Ejection =====
+
+impl<K, S> Ejection<K, S> {
+    /// Returns whether `svc` should be ejected, forgetting its timeouts if so.
+    fn ejects(&self, svc: &S) -> bool {
+        if (self.timeouts)(svc) < self.max_timeouts {
+            return false;
+        }
+        (self.reset)(svc);
+        true
+    }
+}
+
+impl<K, S> fmt::Debug for Ejection<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ejection")
+            .field("max_timeouts", &self.max_timeouts)
+            .field("ejected", &self.ejected.len())
+            .finish()
+    }
+}
+
+// ===== impl Spares =====
+
+impl<K, S> Spares<K, S> {
+    fn is_saturated(&self, svc: &S) -> bool {
+        (self.load)(svc) > self.threshold
+    }
+}
+
+impl<K, S> fmt::Debug for Spares<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Spares")
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+fn load_f64<S>(svc: &S) -> f64
+where
+    S: Load,
+    S::Metric: load::Metric,
+{
+    load::Metric::to_f64(&svc.load())
+}
+
 // ===== impl ResponseFuture =====
 
 impl<F: Future, E> Future for ResponseFuture<F, E> {
@@ -444,6 +1265,7 @@ where
             Error::Balance(ref why) =>
                 write!(f, "load balancing failed: {}", why),
             Error::NotReady => f.pad("not ready"),
+            Error::Overloaded => f.pad("all endpoints are overloaded"),
         }
     }
 }
@@ -466,6 +1288,7 @@ where
             Error::Inner(_) => "inner service error",
             Error::Balance(_) => "load balancing failed",
             Error::NotReady => "not ready",
+            Error::Overloaded => "all endpoints are overloaded",
         }
     }
 }
@@ -473,8 +1296,12 @@ where
 #[cfg(test)]
 mod tests {
     use futures::future;
+    use futures::sync::oneshot;
     use quickcheck::*;
+    use std::cell::{Cell, RefCell};
     use std::collections::VecDeque;
+    use std::rc::Rc;
+    use tower_discover::test_util::Fixed;
     use tower_discover::Change;
 
     use super::*;
@@ -516,6 +1343,552 @@ mod tests {
         }
     }
 
+    type Region = Balance<Fixed<usize, ReluctantService>, choose::RoundRobin>;
+
+    fn region(hosts: usize) -> Region {
+        let changes = (0..hosts)
+            .map(|i| Change::Insert(i, ReluctantService { polls_until_ready: 0 }));
+        Balance::round_robin(Fixed::new(changes))
+    }
+
+    fn num_hosts(balancer: &Balance<Fixed<usize, Region>, choose::RoundRobin>) -> usize {
+        balancer.ready.values().map(Region::num_ready).sum()
+    }
+
+    #[test]
+    fn hierarchical_region_removal_cascades() {
+        let regions = vec![Change::Insert(0, region(2)), Change::Insert(1, region(2))];
+        let mut balancer = Balance::round_robin(Fixed::new(regions));
+
+        assert!(Service::<()>::poll_ready(&mut balancer).unwrap().is_ready());
+        assert_eq!(balancer.num_ready(), 2);
+        assert_eq!(num_hosts(&balancer), 4);
+
+        balancer.discover.push(Change::Remove(0));
+        assert!(Service::<()>::poll_ready(&mut balancer).unwrap().is_ready());
+        assert_eq!(balancer.num_ready(), 1);
+        assert_eq!(num_hosts(&balancer), 2);
+        assert!(balancer.ready.get(&0).is_none());
+
+        balancer.discover.push(Change::Remove(1));
+        assert!(Service::<()>::poll_ready(&mut balancer).unwrap().is_not_ready());
+        assert_eq!(num_hosts(&balancer), 0);
+    }
+
+    /// Records the id of each endpoint called and lets the test complete each call.
+    struct Tracked(usize, Rc<RefCell<Vec<(usize, oneshot::Sender<()>)>>>);
+
+    impl Service<()> for Tracked {
+        type Response = ();
+        type Error = ();
+        type Future = Box<Future<Item = (), Error = ()>>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            let (tx, rx) = oneshot::channel();
+            self.1.borrow_mut().push((self.0, tx));
+            Box::new(rx.map_err(|_| ()))
+        }
+    }
+
+    #[test]
+    fn drained_endpoint_receives_no_new_requests() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let endpoints = (0..3)
+            .map(|i| Change::Insert(i, Tracked(i, calls.clone())));
+        let mut balancer = Balance::round_robin(Fixed::new(endpoints));
+
+        let mut in_flight = Vec::new();
+        for _ in 0..3 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            in_flight.push(Service::call(&mut balancer, ()));
+        }
+        let mut called = calls.borrow().iter().map(|&(i, _)| i).collect::<Vec<_>>();
+        called.sort();
+        assert_eq!(called, vec![0, 1, 2]);
+
+        assert!(balancer.drain_endpoint(&1));
+        assert_eq!(balancer.num_drained(), 1);
+        assert!(!balancer.drain_endpoint(&3));
+
+        let drained = calls.borrow().iter().position(|&(i, _)| i == 1).unwrap();
+        let (_, tx) = calls.borrow_mut().remove(drained);
+        let rsp = in_flight.remove(drained);
+        tx.send(()).unwrap();
+        assert!(rsp.wait().is_ok());
+
+        calls.borrow_mut().clear();
+        for _ in 0..6 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            Service::call(&mut balancer, ());
+        }
+        assert!(calls.borrow().iter().all(|&(i, _)| i != 1));
+
+        assert!(balancer.undrain_endpoint(&1));
+        assert!(!balancer.undrain_endpoint(&1));
+        calls.borrow_mut().clear();
+        for _ in 0..6 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            Service::call(&mut balancer, ());
+        }
+        assert!(calls.borrow().iter().any(|&(i, _)| i == 1));
+        assert_eq!(balancer.num_drained(), 0);
+    }
+
+    #[test]
+    fn replaced_endpoint_keeps_key_and_in_flight_requests() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let endpoints = (0..2)
+            .map(|i| Change::Insert(i, Tracked(i, calls.clone())));
+        let mut balancer = Balance::round_robin(Fixed::new(endpoints));
+
+        let mut in_flight = Vec::new();
+        for _ in 0..2 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            in_flight.push(Service::call(&mut balancer, ()));
+        }
+
+        assert!(balancer.replace_endpoint(1, Tracked(11, calls.clone())));
+        assert!(!balancer.replace_endpoint(2, Tracked(12, calls.clone())));
+
+        let before = calls.borrow_mut().drain(..).collect::<Vec<_>>();
+        for _ in 0..4 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            in_flight.push(Service::call(&mut balancer, ()));
+        }
+        let mut called = calls.borrow().iter().map(|&(i, _)| i).collect::<Vec<_>>();
+        called.sort();
+        assert_eq!(called, vec![0, 0, 11, 11]);
+        assert_eq!(balancer.num_ready(), 2);
+        assert!(balancer.ready.contains_key(&1));
+
+        // Requests dispatched before the replacement still complete.
+        let after = calls.borrow_mut().drain(..).collect::<Vec<_>>();
+        for (_, tx) in before.into_iter().chain(after) {
+            tx.send(()).unwrap();
+        }
+        for rsp in in_flight {
+            assert!(rsp.wait().is_ok());
+        }
+    }
+
+    type Calls = Rc<RefCell<Vec<(usize, oneshot::Sender<()>)>>>;
+
+    /// A connection whose responses fail once it has been dropped.
+    struct Conn(usize, Rc<Cell<bool>>, Calls);
+
+    impl Conn {
+        /// Returns the connection and whether it is still open.
+        fn new(id: usize, calls: &Calls) -> (Conn, Rc<Cell<bool>>) {
+            let open = Rc::new(Cell::new(true));
+            (Conn(id, open.clone(), calls.clone()), open)
+        }
+    }
+
+    impl Drop for Conn {
+        fn drop(&mut self) {
+            self.1.set(false);
+        }
+    }
+
+    impl Service<()> for Conn {
+        type Response = ();
+        type Error = ();
+        type Future = Box<Future<Item = (), Error = ()>>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            let (tx, rx) = oneshot::channel();
+            self.2.borrow_mut().push((self.0, tx));
+            let open = self.1.clone();
+            Box::new(rx.then(move |_| if open.get() { Ok(()) } else { Err(()) }))
+        }
+    }
+
+    #[test]
+    fn replaced_endpoint_is_held_until_in_flight_requests_complete() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let (conn0, _) = Conn::new(0, &calls);
+        let (conn1, open1) = Conn::new(1, &calls);
+        let endpoints = vec![Change::Insert(0, conn0), Change::Insert(1, conn1)];
+        let mut balancer = Balance::round_robin(Fixed::new(endpoints));
+
+        let mut before = Vec::new();
+        for _ in 0..2 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            before.push(Service::call(&mut balancer, ()));
+        }
+        let (conn11, _) = Conn::new(11, &calls);
+        assert!(balancer.replace_endpoint(1, conn11));
+
+        // The replacement takes over, but the replaced connection has a request in
+        // flight, so it is not dropped.
+        for _ in 0..4 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            Service::call(&mut balancer, ());
+        }
+        assert!(open1.get());
+
+        let txs = calls.borrow_mut().drain(..).collect::<Vec<_>>();
+        for (_, tx) in txs {
+            let _ = tx.send(());
+        }
+        for rsp in before {
+            assert!(rsp.wait().is_ok());
+        }
+
+        // Once its requests have completed, the replaced connection is dropped.
+        assert!(balancer.poll_ready().unwrap().is_ready());
+        assert!(!open1.get());
+    }
+
+    #[test]
+    fn drained_endpoints_are_not_ejected() {
+        let endpoints = (0..4)
+            .map(|i| Change::Insert(i, ReluctantService { polls_until_ready: 0 }));
+        let mut balancer = Balance::round_robin(Fixed::new(endpoints));
+        assert!(balancer.poll_ready().unwrap().is_ready());
+
+        assert!(balancer.drain_endpoint(&1));
+        assert!(balancer.drain_endpoint(&3));
+        assert!(balancer.poll_ready().unwrap().is_ready());
+        assert_eq!(balancer.num_drained(), 2);
+        assert_eq!(balancer.ejected_count(), 0);
+    }
+
+    #[test]
+    fn endpoint_meta() {
+        let endpoints = vec![
+            Change::Insert(0, WithMeta::new(ReluctantService { polls_until_ready: 0 }, "us-east")),
+            Change::Insert(1, WithMeta::new(ReluctantService { polls_until_ready: 1 }, "us-west")),
+        ];
+        let mut balancer = Balance::round_robin(Fixed::new(endpoints));
+        assert_eq!(balancer.endpoint_meta(&0), None);
+
+        assert!(balancer.poll_ready().unwrap().is_ready());
+        assert_eq!(balancer.num_not_ready(), 1);
+        assert_eq!(balancer.endpoint_meta(&0), Some(&"us-east"));
+        assert_eq!(balancer.endpoint_meta(&1), Some(&"us-west"));
+        assert_eq!(balancer.endpoint_meta(&2), None);
+
+        balancer.discover.push(Change::Remove(0));
+        assert!(balancer.poll_ready().unwrap().is_ready());
+        assert_eq!(balancer.endpoint_meta(&0), None);
+        assert_eq!(balancer.endpoint_meta(&1), Some(&"us-west"));
+    }
+
+    #[test]
+    fn remove_where_drops_matching_endpoints() {
+        let endpoints = vec![
+            Change::Insert(0, WithMeta::new(ReluctantService { polls_until_ready: 0 }, "v1")),
+            Change::Insert(1, WithMeta::new(ReluctantService { polls_until_ready: 0 }, "v2")),
+            Change::Insert(2, WithMeta::new(ReluctantService { polls_until_ready: 1 }, "v2")),
+            Change::Insert(3, WithMeta::new(ReluctantService { polls_until_ready: 0 }, "v1")),
+        ];
+        let mut balancer = Balance::round_robin(Fixed::new(endpoints));
+        assert!(balancer.poll_ready().unwrap().is_ready());
+        assert!(balancer.drain_endpoint(&3));
+
+        assert_eq!(balancer.remove_where(|_, version| *version == "v2"), 2);
+        assert_eq!(balancer.endpoint_meta(&1), None);
+        assert_eq!(balancer.endpoint_meta(&2), None);
+        assert_eq!(balancer.endpoint_meta(&0), Some(&"v1"));
+        assert_eq!(balancer.endpoint_meta(&3), Some(&"v1"));
+        assert_eq!(balancer.remove_where(|_, version| *version == "v2"), 0);
+
+        for _ in 0..3 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            assert_eq!(balancer.chosen_key(), Some(&0));
+            Service::call(&mut balancer, ());
+        }
+    }
+
+    /// Always chooses the first ready endpoint.
+    struct First;
+
+    impl<K, N> Choose<K, N> for First {
+        fn choose(&mut self, _: choose::Replicas<K, N>) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn retry_excludes_previous_endpoint() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let endpoints = (0..2)
+            .map(|i| Change::Insert(i, Tracked(i, calls.clone())));
+        let mut balancer = ExcludePrevious::new(Balance::new(Fixed::new(endpoints), First));
+        let called = |n: usize| calls.borrow()[n].0;
+
+        let attempt = Attempt::new(());
+        let retry = attempt.clone();
+        assert_eq!(retry.previous(), None);
+
+        assert!(Service::<Attempt<usize, ()>>::poll_ready(&mut balancer).unwrap().is_ready());
+        balancer.call(attempt);
+        let failed = called(0);
+        assert_eq!(retry.previous(), Some(failed));
+
+        // The retry shares the annotation, so the failed endpoint is avoided.
+        assert!(Service::<Attempt<usize, ()>>::poll_ready(&mut balancer).unwrap().is_ready());
+        balancer.call(retry.clone());
+        assert_ne!(called(1), failed);
+        assert_eq!(retry.previous(), Some(called(1)));
+
+        // An unrelated request excludes nothing.
+        assert!(Service::<Attempt<usize, ()>>::poll_ready(&mut balancer).unwrap().is_ready());
+        balancer.call(Attempt::new(()));
+        assert_eq!(called(2), failed);
+    }
+
+    /// Tracks its calls, and is ready only while its gate is open.
+    struct Gated(usize, Rc<Cell<bool>>, Rc<RefCell<Vec<usize>>>);
+
+    impl Service<()> for Gated {
+        type Response = ();
+        type Error = ();
+        type Future = future::FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            if self.1.get() {
+                Ok(Async::Ready(()))
+            } else {
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            assert!(self.1.get(), "endpoint {} called while not ready", self.0);
+            self.2.borrow_mut().push(self.0);
+            future::ok(())
+        }
+    }
+
+    #[test]
+    fn excluded_endpoint_used_when_alternate_not_ready() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let gates = (0..2).map(|_| Rc::new(Cell::new(true))).collect::<Vec<_>>();
+        let endpoints = (0..2)
+            .map(|i| Change::Insert(i, Gated(i, gates[i].clone(), calls.clone())));
+        let mut balancer = ExcludePrevious::new(Balance::new(Fixed::new(endpoints), First));
+
+        let attempt = Attempt::new(());
+        assert!(Service::<Attempt<usize, ()>>::poll_ready(&mut balancer).unwrap().is_ready());
+        balancer.call(attempt.clone());
+        assert_eq!(*calls.borrow(), vec![0]);
+
+        // The alternate endpoint is not ready, so the retry is not sent to it.
+        gates[1].set(false);
+        assert!(Service::<Attempt<usize, ()>>::poll_ready(&mut balancer).unwrap().is_ready());
+        balancer.call(attempt.clone());
+        assert_eq!(*calls.borrow(), vec![0, 0]);
+        assert_eq!(balancer.get_ref().num_not_ready(), 1);
+
+        // Once it is ready again, it is preferred.
+        gates[1].set(true);
+        assert!(Service::<Attempt<usize, ()>>::poll_ready(&mut balancer).unwrap().is_ready());
+        balancer.call(attempt);
+        assert_eq!(*calls.borrow(), vec![0, 0, 1]);
+    }
+
+    fn constant_loads(loads: &[f64]) -> Fixed<usize, load::Constant<ReluctantService, f64>> {
+        let changes = loads
+            .iter()
+            .enumerate()
+            .map(|(i, &l)| {
+                let svc = ReluctantService { polls_until_ready: 0 };
+                Change::Insert(i, load::Constant::new(svc, l))
+            });
+        Fixed::new(changes)
+    }
+
+    #[test]
+    fn weighted_round_robin_skips_overloaded_endpoint() {
+        let mut balancer = Balance::weighted_round_robin(constant_loads(&[1.0, 10.0, 2.0, 3.0]), 5.0);
+
+        let mut chosen = (0..6)
+            .map(|_| {
+                assert!(balancer.poll_ready().unwrap().is_ready());
+                let key = *balancer.chosen_key().unwrap();
+                Service::call(&mut balancer, ());
+                key
+            })
+            .collect::<Vec<_>>();
+        assert!(!chosen.contains(&1));
+
+        // The rotation continues evenly among the rest.
+        chosen.sort();
+        assert_eq!(chosen, vec![0, 0, 2, 2, 3, 3]);
+    }
+
+    #[test]
+    fn endpoint_over_ceiling_is_skipped() {
+        let mut balancer = Balance::round_robin(constant_loads(&[1.0, 10.0, 2.0]))
+            .with_per_endpoint_ceiling(5.0);
+
+        for _ in 0..6 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            assert_ne!(balancer.chosen_key(), Some(&1));
+            Service::call(&mut balancer, ());
+        }
+    }
+
+    #[test]
+    fn all_endpoints_over_ceiling_sheds() {
+        let mut balancer = Balance::round_robin(constant_loads(&[6.0, 10.0]))
+            .with_per_endpoint_ceiling(5.0);
+
+        match balancer.poll_ready() {
+            Err(Error::Overloaded) => {}
+            _ => panic!("requests should be shed"),
+        }
+    }
+
+    #[test]
+    fn spare_tier_used_only_under_saturation() {
+        let mut balancer = Balance::round_robin(constant_loads(&[1.0, 1.0, 0.0]))
+            .with_spare_tier(|key: &usize| *key == 2, 5.0);
+
+        let dispatch = |balancer: &mut Balance<_, _>| {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            let key = *balancer.chosen_key().unwrap();
+            Service::call(balancer, ());
+            key
+        };
+
+        for _ in 0..6 {
+            assert_ne!(dispatch(&mut balancer), 2);
+        }
+
+        // The primary tier is saturated.
+        balancer.seed_loads::<f64>(vec![(0, 6.0), (1, 7.0)].into_iter().collect());
+        for _ in 0..6 {
+            assert_eq!(dispatch(&mut balancer), 2);
+        }
+
+        // Load on the primary tier subsides.
+        balancer.seed_loads::<f64>(vec![(0, 2.0)].into_iter().collect());
+        for _ in 0..6 {
+            assert_ne!(dispatch(&mut balancer), 2);
+        }
+    }
+
+    #[test]
+    fn selection_is_cached_for_a_number_of_calls() {
+        let mut balancer = Balance::round_robin(constant_loads(&[1.0, 1.0, 1.0]))
+            .with_selection_cache(3);
+
+        let chosen = (0..9)
+            .map(|_| {
+                assert!(balancer.poll_ready().unwrap().is_ready());
+                let key = *balancer.chosen_key().unwrap();
+                Service::call(&mut balancer, ());
+                key
+            })
+            .collect::<Vec<_>>();
+
+        let windows = chosen.chunks(3).collect::<Vec<_>>();
+        for window in &windows {
+            assert!(window.iter().all(|&key| key == window[0]), "{:?}", chosen);
+        }
+        assert_ne!(windows[0][0], windows[1][0]);
+        assert_ne!(windows[1][0], windows[2][0]);
+    }
+
+    /// Yields a fixed set of changes, then ends, counting each poll.
+    struct Finite(VecDeque<Change<usize, ReluctantService>>, Rc<Cell<usize>>);
+
+    impl Discover for Finite {
+        type Key = usize;
+        type Service = ReluctantService;
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+            self.1.set(self.1.get() + 1);
+            Ok(self.0.pop_front().map(Async::Ready).unwrap_or(Async::NotReady))
+        }
+
+        fn is_done(&self) -> bool {
+            self.0.is_empty()
+        }
+    }
+
+    #[test]
+    fn finished_discover_is_not_polled() {
+        let polls = Rc::new(Cell::new(0));
+        let changes = (0..2)
+            .map(|i| Change::Insert(i, ReluctantService { polls_until_ready: 0 }))
+            .collect();
+        let mut balancer = Balance::round_robin(Finite(changes, polls.clone()));
+
+        assert!(balancer.poll_ready().unwrap().is_ready());
+        Service::call(&mut balancer, ());
+        let polled = polls.get();
+
+        for _ in 0..4 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            Service::call(&mut balancer, ());
+        }
+        assert_eq!(polls.get(), polled);
+        assert_eq!(balancer.num_ready(), 2);
+    }
+
+    #[test]
+    fn seeded_loads_apply_on_discovery() {
+        let mut balancer = Balance::p2c(constant_loads(&[1.0, 1.0]));
+        let snapshot = vec![(0, 10.0), (1, 2.0)].into_iter().collect();
+        balancer.seed_loads::<f64>(snapshot);
+
+        for _ in 0..6 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            assert_eq!(balancer.chosen_key(), Some(&1));
+        }
+    }
+
+    #[test]
+    fn seeded_loads_apply_to_known_endpoints() {
+        let mut balancer = Balance::p2c(constant_loads(&[1.0, 1.0]));
+        assert!(balancer.poll_ready().unwrap().is_ready());
+
+        let snapshot = vec![(1, 10.0)].into_iter().collect();
+        balancer.seed_loads::<f64>(snapshot);
+
+        for _ in 0..6 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            assert_eq!(balancer.chosen_key(), Some(&0));
+        }
+    }
+
+    /// Fails the test if it is ever asked to choose.
+    struct Unreachable;
+
+    impl<K, N> Choose<K, N> for Unreachable {
+        fn choose(&mut self, _: choose::Replicas<K, N>) -> usize {
+            panic!("a single endpoint must not be chosen between");
+        }
+    }
+
+    #[test]
+    fn single_endpoint_skips_selection() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let endpoints = vec![Change::Insert(7, Tracked(7, calls.clone()))];
+        let mut balancer = Balance::new(Fixed::new(endpoints), Unreachable);
+
+        for _ in 0..1_000 {
+            assert!(balancer.poll_ready().unwrap().is_ready());
+            assert_eq!(balancer.chosen_key(), Some(&7));
+            Service::call(&mut balancer, ());
+        }
+        assert_eq!(calls.borrow().len(), 1_000);
+        assert!(calls.borrow().iter().all(|&(i, _)| i == 7));
+    }
+
     quickcheck! {
         /// Creates a random number of services, each of which must be polled a random
         /// number of times before becoming ready. As the balancer is polled, ensure that
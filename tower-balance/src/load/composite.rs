@@ -0,0 +1,112 @@
+use futures::Poll;
+use tower_service::Service;
+
+use super::{Load, Metric};
+
+/// Combines the load of two sources into a single weighted metric.
+///
+/// Requests are dispatched to the `primary` service. The load of `primary` and
+/// `secondary` are each converted to a scalar via `Metric` and combined as
+/// `primary_weight * primary + secondary_weight * secondary`. For instance, a
+/// latency estimate and an in-flight count may be blended as
+/// `0.7 * latency + 0.3 * in_flight`.
+///
+/// Because the two metrics are added directly, weights should account for any
+/// difference in the magnitude of their units.
+#[derive(Debug)]
+pub struct CompositeLoad<A, B> {
+    primary: A,
+    secondary: B,
+    primary_weight: f64,
+    secondary_weight: f64,
+}
+
+/// The weighted sum of two load metrics.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct Weighted(f64);
+
+// ===== impl CompositeLoad =====
+
+impl<A, B> CompositeLoad<A, B>
+where
+    A: Load,
+    B: Load,
+    A::Metric: Metric,
+    B::Metric: Metric,
+{
+    pub fn new(primary: A, secondary: B, primary_weight: f64, secondary_weight: f64) -> Self {
+        Self {
+            primary,
+            secondary,
+            primary_weight,
+            secondary_weight,
+        }
+    }
+}
+
+impl<A, B> Load for CompositeLoad<A, B>
+where
+    A: Load,
+    B: Load,
+    A::Metric: Metric,
+    B::Metric: Metric,
+{
+    type Metric = Weighted;
+
+    fn load(&self) -> Weighted {
+        let a = self.primary.load().to_f64();
+        let b = self.secondary.load().to_f64();
+        Weighted(self.primary_weight * a + self.secondary_weight * b)
+    }
+}
+
+impl<A, B, Request> Service<Request> for CompositeLoad<A, B>
+where
+    A: Service<Request>,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = A::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.primary.poll_ready()
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        self.primary.call(req)
+    }
+}
+
+// ===== impl Weighted =====
+
+impl Metric for Weighted {
+    fn to_f64(&self) -> f64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use load::Constant;
+    use super::*;
+
+    fn composite(latency: f64, in_flight: usize) -> CompositeLoad<Constant<(), f64>, Constant<(), usize>> {
+        CompositeLoad::new(Constant::new((), latency), Constant::new((), in_flight), 0.75, 0.25)
+    }
+
+    #[test]
+    fn weighted_sum() {
+        assert_eq!(composite(10.0, 0).load(), Weighted(7.5));
+        assert_eq!(composite(0.0, 10).load(), Weighted(2.5));
+        assert_eq!(composite(10.0, 10).load(), Weighted(10.0));
+    }
+
+    #[test]
+    fn ordering_follows_weights() {
+        // Latency is weighted more heavily than the in-flight count, so a
+        // lower latency wins even with more requests in flight.
+        assert!(composite(2.0, 4).load() < composite(4.0, 2).load());
+        // 0.75 * 10 + 0.25 * 0 > 0.75 * 0 + 0.25 * 20
+        assert!(composite(10.0, 0).load() > composite(0.0, 20).load());
+    }
+}
@@ -2,6 +2,7 @@ use futures::{Async, Poll};
 use tower_discover::{Change, Discover};
 use tower_service::Service;
 
+use super::Seed;
 use Load;
 
 /// Wraps a type so that `Load::load` returns a constant value.
@@ -29,6 +30,12 @@ impl<T, M: Copy> Load for Constant<T, M> {
     }
 }
 
+impl<T, M: Copy + From<f64>> Seed for Constant<T, M> {
+    fn seed(&mut self, load: f64) {
+        self.load = M::from(load);
+    }
+}
+
 impl<S, M, Request> Service<Request> for Constant<S, M>
 where
     S: Service<Request>,
@@ -1,12 +1,16 @@
+mod composite;
 mod instrument;
 mod constant;
 pub mod peak_ewma;
 pub mod pending_requests;
+mod with_load;
 
+pub use self::composite::{CompositeLoad, Weighted};
 pub use self::instrument::{Instrument, InstrumentFuture, NoInstrument};
 pub use self::constant::Constant;
 pub use self::peak_ewma::{PeakEwma, WithPeakEwma};
 pub use self::pending_requests::{PendingRequests, WithPendingRequests};
+pub use self::with_load::{DiscoverExt, WithLoad};
 
 /// Exposes a load metric.
 ///
@@ -17,3 +21,34 @@ pub trait Load {
 
     fn load(&self) -> Self::Metric;
 }
+
+/// Expresses a load metric as a scalar so that it may be combined with others.
+///
+/// Implementations must preserve ordering: if `a < b`, then
+/// `a.to_f64() < b.to_f64()`.
+pub trait Metric {
+    fn to_f64(&self) -> f64;
+}
+
+macro_rules! impl_metric {
+    ($($t:ty),*) => {
+        $(
+            impl Metric for $t {
+                fn to_f64(&self) -> f64 {
+                    *self as f64
+                }
+            }
+        )*
+    }
+}
+
+/// Initializes a load metric from a previously observed value.
+///
+/// This allows a balancer to be warm-started from a snapshot of its endpoints' loads,
+/// e.g. one taken before a restart, rather than treating every endpoint as unloaded.
+/// The observed value is expressed as a scalar, as by `Metric::to_f64`.
+pub trait Seed {
+    fn seed(&mut self, load: f64);
+}
+
+impl_metric!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
@@ -5,7 +5,7 @@ use tokio_timer::clock;
 use tower_discover::{Change, Discover};
 use tower_service::Service;
 
-use super::{Instrument, InstrumentFuture, NoInstrument};
+use super::{Instrument, InstrumentFuture, Metric, NoInstrument, Seed};
 
 use Load;
 
@@ -181,6 +181,20 @@ impl<S, I> Load for PeakEwma<S, I> {
     }
 }
 
+impl<S, I> Seed for PeakEwma<S, I> {
+    /// Sets the RTT estimate so that the current cost equals `load`.
+    ///
+    /// The seeded estimate decays like any other.
+    fn seed(&mut self, load: f64) {
+        let pending = Arc::strong_count(&self.rtt_estimate) as u32 - 1;
+        let rtt_ns = load / f64::from(pending + 1);
+        if rtt_ns > 0.0 {
+            let mut rtt = self.rtt_estimate.lock().expect("peak ewma prior_estimate");
+            *rtt = RttEstimate::new(rtt_ns);
+        }
+    }
+}
+
 impl<S, I> PeakEwma<S, I> {
     fn update_estimate(&self) -> f64 {
         let mut rtt = self.rtt_estimate.lock().expect("peak ewma prior_estimate");
@@ -188,6 +202,14 @@ impl<S, I> PeakEwma<S, I> {
     }
 }
 
+// ===== impl Cost =====
+
+impl Metric for Cost {
+    fn to_f64(&self) -> f64 {
+        self.0
+    }
+}
+
 // ===== impl RttEstimate =====
 
 impl RttEstimate {
@@ -4,6 +4,7 @@ use tower_discover::{Change, Discover};
 use tower_service::Service;
 
 use Load;
+use super::Metric;
 use super::{Instrument, InstrumentFuture, NoInstrument};
 
 /// Expresses load based on the number of currently-pending requests.
@@ -36,7 +37,7 @@ pub struct Handle(RefCount);
 // ===== impl PendingRequests =====
 
 impl<S, I> PendingRequests<S, I> {
-    fn new(service: S, instrument: I) -> Self {
+    pub fn new(service: S, instrument: I) -> Self {
         Self {
             service,
             instrument,
@@ -76,6 +77,14 @@ where
     }
 }
 
+// ===== impl Count =====
+
+impl Metric for Count {
+    fn to_f64(&self) -> f64 {
+        self.0 as f64
+    }
+}
+
 // ===== impl WithPendingRequests =====
 
 impl<D, I> WithPendingRequests<D, I> {
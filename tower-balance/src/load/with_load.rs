@@ -0,0 +1,119 @@
+use futures::{Async, Poll};
+use tower_discover::{Change, Discover};
+
+use Load;
+
+/// Wraps each service yielded by `D` with a load metric, via `F`.
+///
+/// This is created by the `DiscoverExt::with_load` method.
+#[derive(Debug)]
+pub struct WithLoad<D, F> {
+    discover: D,
+    make_load: F,
+}
+
+/// An extension trait for attaching load metrics to discovered services.
+pub trait DiscoverExt: Discover {
+    /// Wraps every inserted service with `make_load`, so that each is `Load`
+    /// and may be balanced.
+    ///
+    /// ```ignore
+    /// let discover = discover.with_load(|svc| PendingRequests::new(svc, NoInstrument));
+    /// let balance = Balance::p2c(discover);
+    /// ```
+    fn with_load<F, L>(self, make_load: F) -> WithLoad<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Self::Service) -> L,
+        L: Load,
+    {
+        WithLoad::new(self, make_load)
+    }
+}
+
+impl<D: Discover> DiscoverExt for D {}
+
+// ===== impl WithLoad =====
+
+impl<D, F> WithLoad<D, F> {
+    pub fn new<L>(discover: D, make_load: F) -> Self
+    where
+        D: Discover,
+        F: FnMut(D::Service) -> L,
+        L: Load,
+    {
+        Self { discover, make_load }
+    }
+}
+
+impl<D, F, L> Discover for WithLoad<D, F>
+where
+    D: Discover,
+    F: FnMut(D::Service) -> L,
+    L: Load,
+{
+    type Key = D::Key;
+    type Service = L;
+    type Error = D::Error;
+
+    /// Yields the next discovery change set.
+    fn poll(&mut self) -> Poll<Change<D::Key, L>, D::Error> {
+        use self::Change::*;
+
+        let change = match try_ready!(self.discover.poll()) {
+            Insert(k, svc) => Insert(k, (self.make_load)(svc)),
+            Remove(k) => Remove(k),
+        };
+
+        Ok(Async::Ready(change))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{future, Future};
+    use tower_discover::test_util::Fixed;
+    use tower_service::Service;
+
+    use super::*;
+    use load::{Metric, NoInstrument, PendingRequests};
+
+    struct Svc;
+
+    impl Service<()> for Svc {
+        type Response = ();
+        type Error = ();
+        type Future = future::FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, (): ()) -> Self::Future {
+            future::ok(())
+        }
+    }
+
+    #[test]
+    fn wraps_inserted_services() {
+        let changes = vec![Change::Insert(0, Svc), Change::Remove(0)];
+        let mut discover = Fixed::new(changes)
+            .with_load(|svc| PendingRequests::new(svc, NoInstrument));
+
+        let mut svc = match discover.poll() {
+            Ok(Async::Ready(Change::Insert(0, svc))) => svc,
+            _ => panic!("expected insertion of 0"),
+        };
+        assert_eq!(svc.load().to_f64(), 0.0);
+
+        let rsp = svc.call(());
+        assert_eq!(svc.load().to_f64(), 1.0);
+        rsp.wait().unwrap();
+        assert_eq!(svc.load().to_f64(), 0.0);
+
+        match discover.poll() {
+            Ok(Async::Ready(Change::Remove(0))) => {}
+            _ => panic!("expected removal of 0"),
+        }
+    }
+}
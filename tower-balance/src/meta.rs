@@ -0,0 +1,76 @@
+use futures::Poll;
+use tower_service::Service;
+
+use load::Seed;
+use Load;
+
+/// Exposes metadata describing an endpoint, such as its zone or version.
+///
+/// Metadata is available to selection strategies through `Replicas` and to
+/// callers through `Balance::endpoint_meta`.
+pub trait EndpointMeta {
+    type Meta;
+
+    fn meta(&self) -> &Self::Meta;
+}
+
+/// Attaches `M`-typed metadata to an `S`-typed service.
+///
+/// Requests and load are passed through to the inner service.
+#[derive(Clone, Debug)]
+pub struct WithMeta<S, M> {
+    inner: S,
+    meta: M,
+}
+
+// ===== impl WithMeta =====
+
+impl<S, M> WithMeta<S, M> {
+    pub fn new(inner: S, meta: M) -> Self {
+        Self { inner, meta }
+    }
+
+    /// Returns a reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S, M> EndpointMeta for WithMeta<S, M> {
+    type Meta = M;
+
+    fn meta(&self) -> &M {
+        &self.meta
+    }
+}
+
+impl<S: Load, M> Load for WithMeta<S, M> {
+    type Metric = S::Metric;
+
+    fn load(&self) -> S::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S: Seed, M> Seed for WithMeta<S, M> {
+    fn seed(&mut self, load: f64) {
+        self.inner.seed(load)
+    }
+}
+
+impl<S, M, Request> Service<Request> for WithMeta<S, M>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        self.inner.call(req)
+    }
+}
@@ -0,0 +1,355 @@
+use futures::{Async, Future, Poll};
+use rand::{self, rngs::SmallRng, FromEntropy, Rng, SeedableRng};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use tower_service::Service;
+
+/// Mirrors a fraction of the requests dispatched to a primary service to a shadow
+/// service, e.g. a new deployment under test.
+///
+/// Mirrored requests are clones, and their responses are discarded: only the primary
+/// service's responses and errors are returned. The shadow service never delays the
+/// primary: a request is only mirrored if the shadow service is ready when the
+/// request is dispatched.
+///
+/// A mirrored response is driven along with the primary response to the same
+/// request. If it is still incomplete once that future is dropped, it is driven
+/// by later calls to `poll_ready`, so it may not complete if no further requests
+/// are dispatched.
+///
+/// This is created by `Balance::with_shadow`.
+pub struct Shadow<P, S, Request>
+where
+    S: Service<Request>,
+{
+    primary: P,
+    shadow: S,
+    fraction: f64,
+    rng: SmallRng,
+    /// Whether `shadow` was ready when last polled.
+    shadow_ready: bool,
+    /// Mirrored responses that outlived their primary responses, driven to
+    /// completion and discarded.
+    mirrored: Arc<Mutex<Vec<S::Future>>>,
+    _p: PhantomData<fn(Request)>,
+}
+
+/// `Shadow` response future
+pub struct ShadowFuture<F, M> {
+    primary: F,
+    /// The response to the mirrored request, if any, until it completes.
+    mirrored: Option<M>,
+    /// Where an incomplete mirrored response is left once this is dropped.
+    pending: Arc<Mutex<Vec<M>>>,
+}
+
+// ===== impl Shadow =====
+
+impl<P, S, Request> Shadow<P, S, Request>
+where
+    P: Service<Request>,
+    S: Service<Request>,
+    Request: Clone,
+{
+    /// Mirrors `fraction` of the requests dispatched to `primary` to `shadow`.
+    pub fn new(primary: P, shadow: S, fraction: f64) -> Self {
+        assert!(fraction >= 0.0 && fraction <= 1.0, "fraction must be within [0, 1]");
+        Self {
+            primary,
+            shadow,
+            fraction,
+            rng: SmallRng::from_entropy(),
+            shadow_ready: false,
+            mirrored: Arc::new(Mutex::new(Vec::new())),
+            _p: PhantomData,
+        }
+    }
+
+    /// Samples the requests to mirror from the provided randomization source.
+    pub fn with_rng<R: rand::Rng>(self, rng: &mut R) -> Result<Self, rand::Error> {
+        let rng = SmallRng::from_rng(rng)?;
+        Ok(Self { rng, ..self })
+    }
+
+    /// Returns a reference to the primary service.
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// Returns a reference to the shadow service.
+    pub fn shadow(&self) -> &S {
+        &self.shadow
+    }
+
+    /// Drives mirrored responses, discarding those that have completed.
+    fn poll_mirrored(&mut self) {
+        let mut mirrored = self.mirrored.lock().expect("mirrored lock");
+        let mut idx = 0;
+        while idx < mirrored.len() {
+            if is_complete(&mut mirrored[idx]) {
+                mirrored.swap_remove(idx);
+            } else {
+                idx += 1;
+            }
+        }
+    }
+}
+
+/// Drives a mirrored response, returning true once it has completed.
+fn is_complete<F: Future>(mirrored: &mut F) -> bool {
+    match mirrored.poll() {
+        Ok(Async::NotReady) => false,
+        Ok(Async::Ready(_)) => true,
+        Err(_) => {
+            debug!("mirrored request failed");
+            true
+        }
+    }
+}
+
+impl<P, S, Request> Service<Request> for Shadow<P, S, Request>
+where
+    P: Service<Request>,
+    S: Service<Request>,
+    Request: Clone,
+{
+    type Response = P::Response;
+    type Error = P::Error;
+    type Future = ShadowFuture<P::Future, S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.poll_mirrored();
+
+        self.shadow_ready = match self.shadow.poll_ready() {
+            Ok(ready) => ready.is_ready(),
+            Err(_) => {
+                debug!("shadow service failed to become ready");
+                false
+            }
+        };
+
+        self.primary.poll_ready()
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let mut mirrored = None;
+        if self.shadow_ready && self.rng.gen::<f64>() < self.fraction {
+            trace!("mirroring request");
+            self.shadow_ready = false;
+            mirrored = Some(self.shadow.call(request.clone()));
+        }
+
+        ShadowFuture {
+            primary: self.primary.call(request),
+            mirrored,
+            pending: self.mirrored.clone(),
+        }
+    }
+}
+
+impl<P, S, Request> fmt::Debug for Shadow<P, S, Request>
+where
+    P: fmt::Debug,
+    S: Service<Request> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Shadow")
+            .field("primary", &self.primary)
+            .field("shadow", &self.shadow)
+            .field("fraction", &self.fraction)
+            .field("mirrored", &self.mirrored.lock().map(|m| m.len()).unwrap_or(0))
+            .finish()
+    }
+}
+
+// ===== impl ShadowFuture =====
+
+impl<F, M> Future for ShadowFuture<F, M>
+where
+    F: Future,
+    M: Future,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        if self.mirrored.as_mut().map(is_complete).unwrap_or(false) {
+            self.mirrored = None;
+        }
+
+        self.primary.poll()
+    }
+}
+
+impl<F, M> Drop for ShadowFuture<F, M> {
+    fn drop(&mut self) {
+        if let Some(mirrored) = self.mirrored.take() {
+            self.pending.lock().expect("mirrored lock").push(mirrored);
+        }
+    }
+}
+
+impl<F, M> fmt::Debug for ShadowFuture<F, M>
+where
+    F: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShadowFuture")
+            .field("primary", &self.primary)
+            .field("mirrored", &self.mirrored.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use tower_discover::test_util::Fixed;
+    use tower_discover::Change;
+
+    use super::*;
+    use {choose, Balance};
+
+    /// Responds with its name, counting the requests it serves.
+    struct Named(&'static str, Rc<Cell<usize>>);
+
+    impl Service<usize> for Named {
+        type Response = &'static str;
+        type Error = ();
+        type Future = future::FutureResult<&'static str, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: usize) -> Self::Future {
+            self.1.set(self.1.get() + 1);
+            future::ok(self.0)
+        }
+    }
+
+    type Disco = Fixed<usize, Named>;
+
+    fn endpoints(name: &'static str, served: &Rc<Cell<usize>>) -> Disco {
+        let changes = (0..2).map(|i| Change::Insert(i, Named(name, served.clone())));
+        Fixed::new(changes)
+    }
+
+    type Mirrored = Shadow<Balance<Disco, choose::RoundRobin>, Balance<Disco, choose::RoundRobin>, usize>;
+
+    fn send(svc: &mut Mirrored, requests: usize) {
+        for i in 0..requests {
+            assert!(svc.poll_ready().unwrap().is_ready());
+            assert_eq!(svc.call(i).wait().unwrap(), "primary");
+        }
+    }
+
+    #[test]
+    fn fraction_of_requests_reach_shadow() {
+        let primary = Rc::new(Cell::new(0));
+        let shadow = Rc::new(Cell::new(0));
+        let mut svc = Balance::round_robin(endpoints("primary", &primary))
+            .with_shadow(endpoints("shadow", &shadow), 0.25)
+            .with_rng(&mut SmallRng::from_seed([7; 16]))
+            .unwrap();
+
+        send(&mut svc, 1000);
+        assert_eq!(primary.get(), 1000);
+        assert!(shadow.get() > 150 && shadow.get() < 350, "mirrored {}", shadow.get());
+    }
+
+    #[test]
+    fn all_or_no_requests_reach_shadow() {
+        let primary = Rc::new(Cell::new(0));
+        let shadow = Rc::new(Cell::new(0));
+
+        let mut svc = Balance::round_robin(endpoints("primary", &primary))
+            .with_shadow(endpoints("shadow", &shadow), 1.0);
+        send(&mut svc, 10);
+        assert_eq!(shadow.get(), 10);
+
+        let mut svc = Balance::round_robin(endpoints("primary", &primary))
+            .with_shadow(endpoints("shadow", &shadow), 0.0);
+        send(&mut svc, 10);
+        assert_eq!(shadow.get(), 10);
+        assert_eq!(primary.get(), 20);
+    }
+
+    /// Responds once its gate is opened, counting completed responses.
+    struct Gated(Rc<Cell<bool>>, Rc<Cell<usize>>);
+
+    struct GatedFuture(Rc<Cell<bool>>, Rc<Cell<usize>>);
+
+    impl Service<usize> for Gated {
+        type Response = ();
+        type Error = ();
+        type Future = GatedFuture;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: usize) -> Self::Future {
+            GatedFuture(self.0.clone(), self.1.clone())
+        }
+    }
+
+    impl Future for GatedFuture {
+        type Item = ();
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<(), ()> {
+            if !self.0.get() {
+                return Ok(Async::NotReady);
+            }
+            self.1.set(self.1.get() + 1);
+            Ok(Async::Ready(()))
+        }
+    }
+
+    fn gated(gate: &Rc<Cell<bool>>, completed: &Rc<Cell<usize>>) -> Fixed<usize, Gated> {
+        Fixed::new(vec![Change::Insert(0, Gated(gate.clone(), completed.clone()))])
+    }
+
+    #[test]
+    fn mirrored_response_is_driven_by_primary_response() {
+        let (primary_gate, primary_done) = (Rc::new(Cell::new(false)), Rc::new(Cell::new(0)));
+        let (shadow_gate, shadow_done) = (Rc::new(Cell::new(false)), Rc::new(Cell::new(0)));
+        let mut svc = Balance::round_robin(gated(&primary_gate, &primary_done))
+            .with_shadow(gated(&shadow_gate, &shadow_done), 1.0);
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        let mut rsp = svc.call(0);
+        assert!(rsp.poll().unwrap().is_not_ready());
+
+        // The mirrored response completes without the balancer being polled.
+        shadow_gate.set(true);
+        assert!(rsp.poll().unwrap().is_not_ready());
+        assert_eq!(shadow_done.get(), 1);
+
+        primary_gate.set(true);
+        assert!(rsp.poll().unwrap().is_ready());
+        assert_eq!(primary_done.get(), 1);
+    }
+
+    #[test]
+    fn mirrored_response_outliving_primary_is_driven_by_poll_ready() {
+        let (primary_gate, primary_done) = (Rc::new(Cell::new(true)), Rc::new(Cell::new(0)));
+        let (shadow_gate, shadow_done) = (Rc::new(Cell::new(false)), Rc::new(Cell::new(0)));
+        let mut svc = Balance::round_robin(gated(&primary_gate, &primary_done))
+            .with_shadow(gated(&shadow_gate, &shadow_done), 1.0);
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert!(svc.call(0).wait().is_ok());
+        assert_eq!(shadow_done.get(), 0);
+
+        // Until `poll_ready` is called again, the mirrored response is not driven.
+        shadow_gate.set(true);
+        assert_eq!(shadow_done.get(), 0);
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(shadow_done.get(), 1);
+    }
+}
@@ -0,0 +1,293 @@
+use futures::{Async, Future, Poll};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_timer::timer;
+use tower_discover::{Change, Discover};
+use tower_service::Service;
+use tower_timeout::{self, Timeout};
+
+use load::Seed;
+use {EndpointMeta, Load};
+
+/// Counts the responses of an endpoint that timed out in a row.
+///
+/// A balancer configured with `Balance::with_timeout_ejection` ejects endpoints whose
+/// responses repeatedly time out.
+pub trait Timeouts {
+    /// Returns the number of consecutive responses that timed out.
+    fn consecutive_timeouts(&self) -> usize;
+
+    /// Forgets prior timeouts, e.g. once the endpoint has been ejected.
+    fn reset_timeouts(&self);
+}
+
+/// Fails responses of an `S`-typed endpoint that do not complete within a timeout,
+/// counting consecutive timeouts.
+///
+/// Load, seeds and metadata are passed through to the inner service.
+#[derive(Debug)]
+pub struct TimedEndpoint<S> {
+    inner: Timeout<S>,
+    timeouts: Arc<AtomicUsize>,
+}
+
+/// Wraps `discover`'s services with `TimedEndpoint`.
+#[derive(Debug)]
+pub struct WithEndpointTimeout<D> {
+    discover: D,
+    timeout: Duration,
+    /// Drives timeouts, if not the default timer.
+    timer: Option<timer::Handle>,
+}
+
+/// `TimedEndpoint` response future
+#[derive(Debug)]
+pub struct TimedEndpointFuture<F> {
+    inner: tower_timeout::ResponseFuture<F>,
+    timeouts: Arc<AtomicUsize>,
+}
+
+// ===== impl TimedEndpoint =====
+
+impl<S> TimedEndpoint<S> {
+    pub fn new(inner: S, timeout: Duration) -> Self {
+        Self {
+            inner: Timeout::new(inner, timeout),
+            timeouts: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Uses `timer` to drive timeouts, rather than the default timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        Self {
+            inner: self.inner.with_timer(timer),
+            ..self
+        }
+    }
+
+    /// Returns a reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+}
+
+impl<S> Timeouts for TimedEndpoint<S> {
+    fn consecutive_timeouts(&self) -> usize {
+        self.timeouts.load(Ordering::SeqCst)
+    }
+
+    fn reset_timeouts(&self) {
+        self.timeouts.store(0, Ordering::SeqCst);
+    }
+}
+
+impl<S: Load> Load for TimedEndpoint<S> {
+    type Metric = S::Metric;
+
+    fn load(&self) -> S::Metric {
+        self.inner.get_ref().load()
+    }
+}
+
+impl<S: Seed> Seed for TimedEndpoint<S> {
+    fn seed(&mut self, load: f64) {
+        self.inner.get_mut().seed(load)
+    }
+}
+
+impl<S: EndpointMeta> EndpointMeta for TimedEndpoint<S> {
+    type Meta = S::Meta;
+
+    fn meta(&self) -> &S::Meta {
+        self.inner.get_ref().meta()
+    }
+}
+
+impl<S, Request> Service<Request> for TimedEndpoint<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = tower_timeout::Error<S::Error>;
+    type Future = TimedEndpointFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        TimedEndpointFuture {
+            inner: self.inner.call(req),
+            timeouts: self.timeouts.clone(),
+        }
+    }
+}
+
+// ===== impl WithEndpointTimeout =====
+
+impl<D> WithEndpointTimeout<D> {
+    /// Fails responses of each of `discover`'s endpoints that do not complete within
+    /// `timeout`.
+    pub fn new(discover: D, timeout: Duration) -> Self {
+        Self {
+            discover,
+            timeout,
+            timer: None,
+        }
+    }
+
+    /// Uses `timer` to drive timeouts, rather than the default timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        Self {
+            timer: Some(timer),
+            ..self
+        }
+    }
+}
+
+impl<D: Discover> Discover for WithEndpointTimeout<D> {
+    type Key = D::Key;
+    type Service = TimedEndpoint<D::Service>;
+    type Error = D::Error;
+
+    /// Yields the next discovery change set.
+    fn poll(&mut self) -> Poll<Change<D::Key, Self::Service>, D::Error> {
+        use self::Change::*;
+
+        let change = match try_ready!(self.discover.poll()) {
+            Insert(k, svc) => {
+                let svc = TimedEndpoint::new(svc, self.timeout);
+                match self.timer {
+                    Some(ref timer) => Insert(k, svc.with_timer(timer.clone())),
+                    None => Insert(k, svc),
+                }
+            }
+            Remove(k) => Remove(k),
+        };
+
+        Ok(Async::Ready(change))
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+// ===== impl TimedEndpointFuture =====
+
+impl<F: Future> Future for TimedEndpointFuture<F> {
+    type Item = F::Item;
+    type Error = tower_timeout::Error<F::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.inner.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(tower_timeout::Error::Timeout) => {
+                self.timeouts.fetch_add(1, Ordering::SeqCst);
+                Err(tower_timeout::Error::Timeout)
+            }
+            // Any other outcome shows that the endpoint is responding.
+            done => {
+                self.timeouts.store(0, Ordering::SeqCst);
+                done
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use tower_discover::test_util::Fixed;
+    use tower_timeout::test_util::{with_mock_clock, MockPark};
+
+    use super::*;
+    use {Balance, Error};
+
+    /// Responds immediately, or never if slow.
+    struct Endpoint {
+        slow: bool,
+    }
+
+    impl Service<()> for Endpoint {
+        type Response = ();
+        type Error = ();
+        type Future = future::Either<future::FutureResult<(), ()>, future::Empty<(), ()>>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            if self.slow {
+                future::Either::B(future::empty())
+            } else {
+                future::Either::A(future::ok(()))
+            }
+        }
+    }
+
+    #[test]
+    fn slow_endpoint_times_out_and_is_ejected() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let endpoints = vec![
+                Change::Insert(0, Endpoint { slow: true }),
+                Change::Insert(1, Endpoint { slow: false }),
+            ];
+            let discover = Fixed::new(endpoints);
+            let discover = WithEndpointTimeout::new(discover, Duration::from_secs(1))
+                .with_timer(timer.handle());
+            let mut balancer = Balance::round_robin(discover).with_timeout_ejection(2);
+
+            let poll_ready = |balancer: &mut Balance<_, _>| {
+                future::lazy(|| Ok::<_, ()>(Service::<()>::poll_ready(balancer))).wait().unwrap()
+            };
+
+            let mut in_flight = Vec::new();
+            for _ in 0..4 {
+                assert!(poll_ready(&mut balancer).unwrap().is_ready());
+                in_flight.push(Service::call(&mut balancer, ()));
+            }
+
+            // Poll each response once, so that the slow ones wait on the timer.
+            let mut responses = in_flight
+                .into_iter()
+                .map(|mut rsp| {
+                    let poll = future::lazy(|| Ok::<_, ()>(rsp.poll())).wait().unwrap();
+                    (rsp, poll.ok().map(|ready| ready.is_ready()))
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(responses.iter().filter(|&&(_, ref poll)| *poll == Some(true)).count(), 2);
+            responses.retain(|&(_, ref poll)| *poll == Some(false));
+
+            *time.as_mut() += Duration::from_secs(1);
+            timer.turn(None).unwrap();
+            for (rsp, _) in responses {
+                match rsp.wait() {
+                    Err(Error::Inner(tower_timeout::Error::Timeout)) => {}
+                    _ => panic!("slow response should have timed out"),
+                }
+            }
+
+            // The slow endpoint is ejected, so that only the fast one is used.
+            assert!(poll_ready(&mut balancer).unwrap().is_ready());
+            assert_eq!(balancer.ejected_count(), 1);
+            assert_eq!(balancer.num_ready(), 1);
+            for _ in 0..2 {
+                assert!(poll_ready(&mut balancer).unwrap().is_ready());
+                assert!(Service::call(&mut balancer, ()).wait().is_ok());
+            }
+
+            // Draining the fast endpoint does not eject it.
+            assert!(balancer.drain_endpoint(&1));
+            assert_eq!(balancer.num_drained(), 2);
+            assert_eq!(balancer.ejected_count(), 1);
+
+            // Once reinstated, the slow endpoint is no longer counted as ejected.
+            assert!(balancer.undrain_endpoint(&0));
+            assert_eq!(balancer.ejected_count(), 0);
+        });
+    }
+}
@@ -0,0 +1,193 @@
+use futures::{task, Async, Future, IntoFuture, Poll};
+
+use std::collections::HashMap;
+
+use {Change, Discover};
+
+/// Removes discovered services while a health check reports them as failing,
+/// and inserts them again once they recover.
+///
+/// Each service is checked continually: `F` is called with its key and the
+/// service, and returns a check that yields whether the service is healthy. A
+/// failed check counts as unhealthy. Once a check completes, the next is
+/// started, so `F` should wait between checks, e.g. with a `Delay`.
+///
+/// When an inserted service is found unhealthy, a `Remove` is yielded, even
+/// though the underlying `Discover` still lists it. When it is found healthy
+/// again, a clone of it is re-inserted. The removal of an evicted service by
+/// the underlying `Discover` is not yielded again.
+pub struct AutoEvict<D, F, R>
+where
+    D: Discover,
+    R: IntoFuture,
+{
+    discover: D,
+    check: F,
+    endpoints: HashMap<D::Key, Endpoint<D::Service, R::Future>>,
+}
+
+/// A service being checked, along with its check, if one is in progress.
+struct Endpoint<S, C> {
+    service: S,
+    evicted: bool,
+    check: Option<C>,
+}
+
+// ===== impl AutoEvict =====
+
+impl<D, F, R> AutoEvict<D, F, R>
+where
+    D: Discover,
+    D::Key: Clone,
+    D::Service: Clone,
+    F: FnMut(&D::Key, &D::Service) -> R,
+    R: IntoFuture<Item = bool>,
+{
+    pub fn new(discover: D, check: F) -> Self {
+        AutoEvict {
+            discover,
+            check,
+            endpoints: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of services currently evicted.
+    pub fn num_evicted(&self) -> usize {
+        self.endpoints.values().filter(|ep| ep.evicted).count()
+    }
+}
+
+impl<D, F, R> Discover for AutoEvict<D, F, R>
+where
+    D: Discover,
+    D::Key: Clone,
+    D::Service: Clone,
+    F: FnMut(&D::Key, &D::Service) -> R,
+    R: IntoFuture<Item = bool>,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        while let Async::Ready(change) = self.discover.poll()? {
+            match change {
+                Change::Insert(key, svc) => {
+                    // A replacement starts out healthy, with a fresh check.
+                    let endpoint = Endpoint {
+                        service: svc.clone(),
+                        evicted: false,
+                        check: None,
+                    };
+                    self.endpoints.insert(key.clone(), endpoint);
+                    return Ok(Async::Ready(Change::Insert(key, svc)));
+                }
+                Change::Remove(key) => {
+                    if let Some(endpoint) = self.endpoints.remove(&key) {
+                        if !endpoint.evicted {
+                            return Ok(Async::Ready(Change::Remove(key)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut completed = false;
+        for (key, endpoint) in self.endpoints.iter_mut() {
+            if endpoint.check.is_none() {
+                let check = (self.check)(key, &endpoint.service).into_future();
+                endpoint.check = Some(check);
+            }
+
+            let healthy = match endpoint.check.as_mut().expect("check").poll() {
+                Ok(Async::NotReady) => continue,
+                Ok(Async::Ready(healthy)) => healthy,
+                Err(_) => false,
+            };
+            endpoint.check = None;
+            completed = true;
+
+            if healthy == endpoint.evicted {
+                endpoint.evicted = !healthy;
+                let change = if healthy {
+                    Change::Insert(key.clone(), endpoint.service.clone())
+                } else {
+                    Change::Remove(key.clone())
+                };
+                return Ok(Async::Ready(change));
+            }
+        }
+
+        // Completed checks are started again on the next poll.
+        if completed {
+            task::current().notify();
+        }
+
+        Ok(Async::NotReady)
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    use super::*;
+    use test_util::{drain, Fixed};
+
+    fn keys(changes: &[Change<usize, &'static str>]) -> Vec<(&'static str, usize)> {
+        changes
+            .iter()
+            .map(|c| match *c {
+                Change::Insert(k, _) => ("insert", k),
+                Change::Remove(k) => ("remove", k),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn failing_endpoint_is_evicted_until_it_recovers() {
+        let failing = Rc::new(RefCell::new(HashSet::new()));
+        let check = {
+            let failing = failing.clone();
+            move |key: &usize, _: &&'static str| -> FutureResult<bool, ()> {
+                future::ok(!failing.borrow().contains(key))
+            }
+        };
+        let changes = vec![Change::Insert(0, "a"), Change::Insert(1, "b")];
+        let mut d = AutoEvict::new(Fixed::new(changes), check);
+        assert_eq!(keys(&drain(&mut d)), vec![("insert", 0), ("insert", 1)]);
+
+        failing.borrow_mut().insert(1);
+        assert_eq!(keys(&drain(&mut d)), vec![("remove", 1)]);
+        assert_eq!(d.num_evicted(), 1);
+        assert!(drain(&mut d).is_empty());
+
+        failing.borrow_mut().remove(&1);
+        let changes = drain(&mut d);
+        assert_eq!(keys(&changes), vec![("insert", 1)]);
+        match changes[0] {
+            Change::Insert(_, svc) => assert_eq!(svc, "b"),
+            Change::Remove(_) => unreachable!(),
+        }
+        assert_eq!(d.num_evicted(), 0);
+    }
+
+    #[test]
+    fn removal_of_evicted_endpoint_is_not_repeated() {
+        let check = |key: &usize, _: &&'static str| -> FutureResult<bool, ()> { future::ok(*key != 1) };
+        let changes = vec![Change::Insert(0, "a"), Change::Insert(1, "b")];
+        let mut d = AutoEvict::new(Fixed::new(changes), check);
+        assert_eq!(keys(&drain(&mut d)), vec![("insert", 0), ("insert", 1), ("remove", 1)]);
+
+        d.discover.push(Change::Remove(1));
+        assert!(drain(&mut d).is_empty());
+        assert_eq!(d.num_evicted(), 0);
+    }
+}
@@ -0,0 +1,137 @@
+use futures::sync::mpsc;
+use futures::{Async, Poll, Stream};
+
+use std::hash::Hash;
+
+use {Change, Discover};
+
+/// Dynamic service discovery fed by a bounded channel.
+///
+/// Changes are pushed by a source through the `mpsc::Sender` returned by
+/// `bounded`. Once the channel is full, the source must wait for the consumer,
+/// e.g. a balancer, to poll for changes before it may push more. This keeps
+/// a slow consumer from being overwhelmed, and bounds the memory held by
+/// changes that have not yet been applied.
+///
+/// Once every sender has been dropped and each change has been yielded,
+/// `is_done` returns true.
+pub struct FromBoundedStream<K, S> {
+    rx: mpsc::Receiver<Change<K, S>>,
+    /// Whether the channel has closed.
+    done: bool,
+}
+
+/// Creates a `FromBoundedStream` and the `mpsc::Sender` that feeds it.
+///
+/// As with `mpsc::channel`, the channel holds `buffer` changes plus one for
+/// each sender.
+pub fn bounded<K, S>(buffer: usize) -> (mpsc::Sender<Change<K, S>>, FromBoundedStream<K, S>)
+where
+    K: Hash + Eq,
+{
+    let (tx, rx) = mpsc::channel(buffer);
+    (tx, FromBoundedStream { rx, done: false })
+}
+
+// ===== impl FromBoundedStream =====
+
+impl<K, S> Discover for FromBoundedStream<K, S>
+where
+    K: Hash + Eq,
+{
+    type Key = K;
+    type Service = S;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        match try_ready!(self.rx.poll()) {
+            Some(change) => Ok(Async::Ready(change)),
+            // Every sender has been dropped, so there are no more changes.
+            None => {
+                self.done = true;
+                Ok(Async::NotReady)
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::{self, Notify};
+    use futures::{future, Future};
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Records whether a blocked task has been notified.
+    struct Notified(AtomicBool);
+
+    impl Notify for Notified {
+        fn notify(&self, _: usize) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn poll_change(discover: &mut FromBoundedStream<usize, ()>) -> Option<usize> {
+        future::lazy(|| match discover.poll() {
+            Ok(Async::Ready(Change::Insert(key, ()))) => Ok::<_, ()>(Some(key)),
+            Ok(Async::NotReady) => Ok(None),
+            _ => panic!("unexpected change"),
+        }).wait().unwrap()
+    }
+
+    #[test]
+    fn full_channel_blocks_producer() {
+        let (mut tx, mut discover) = bounded(0);
+
+        assert!(tx.try_send(Change::Insert(0, ())).is_ok());
+        match tx.try_send(Change::Insert(1, ())) {
+            Err(ref e) if e.is_full() => {}
+            _ => panic!("channel should be full"),
+        }
+
+        // Once the consumer catches up, the producer may continue.
+        assert_eq!(poll_change(&mut discover), Some(0));
+        assert!(tx.try_send(Change::Insert(1, ())).is_ok());
+        assert_eq!(poll_change(&mut discover), Some(1));
+        assert_eq!(poll_change(&mut discover), None);
+    }
+
+    #[test]
+    fn blocked_sender_resumes_after_poll() {
+        let (tx, mut discover) = bounded(0);
+        let mut tx = executor::spawn(tx);
+        let notified = Arc::new(Notified(AtomicBool::new(false)));
+
+        let mut start_send = |key| tx.start_send_notify(Change::Insert(key, ()), &notified, 0).unwrap();
+
+        // The first change fills the channel, so the second must wait.
+        assert!(start_send(0).is_ready());
+        assert!(!start_send(1).is_ready());
+        assert!(!notified.0.load(Ordering::SeqCst));
+
+        // Polling for a change notifies the blocked sender, which may continue.
+        assert_eq!(poll_change(&mut discover), Some(0));
+        assert!(notified.0.load(Ordering::SeqCst));
+        assert!(start_send(1).is_ready());
+        assert_eq!(poll_change(&mut discover), Some(1));
+    }
+
+    #[test]
+    fn done_once_senders_are_dropped() {
+        let (mut tx, mut discover) = bounded(1);
+        assert!(tx.try_send(Change::Insert(0, ())).is_ok());
+        drop(tx);
+
+        assert!(!discover.is_done());
+        assert_eq!(poll_change(&mut discover), Some(0));
+        assert_eq!(poll_change(&mut discover), None);
+        assert!(discover.is_done());
+    }
+}
@@ -0,0 +1,188 @@
+use futures::stream::Fuse;
+use futures::{Async, Poll, Stream};
+use tokio_timer::{clock, Interval};
+
+use std::time::Duration;
+
+use {Change, Discover};
+
+/// Tolerates transient errors from a `Discover`.
+///
+/// Up to `tolerance` errors are swallowed in each window, which ends each time
+/// the `R`-typed window stream produces an item. While errors are swallowed,
+/// the consumer continues with the endpoints it has already discovered. Once
+/// more than `tolerance` errors occur within a window, each further error is
+/// surfaced until the window ends.
+///
+/// If the window stream ends, the current window never ends. If it fails, the
+/// failure is treated as the end of a window.
+pub struct ErrorTolerant<D, R = Interval> {
+    discover: D,
+    windows: Fuse<R>,
+    tolerance: usize,
+    /// The number of errors that have occurred in the current window.
+    errors: usize,
+}
+
+// ===== impl ErrorTolerant =====
+
+impl<D: Discover> ErrorTolerant<D> {
+    /// Swallows up to `tolerance` errors every `window`.
+    pub fn new(discover: D, tolerance: usize, window: Duration) -> Self {
+        let windows = Interval::new(clock::now() + window, window);
+        Self::with_windows(discover, tolerance, windows)
+    }
+}
+
+impl<D, R> ErrorTolerant<D, R>
+where
+    D: Discover,
+    R: Stream,
+{
+    /// Swallows up to `tolerance` errors between each item yielded by `windows`.
+    pub fn with_windows(discover: D, tolerance: usize, windows: R) -> Self {
+        ErrorTolerant {
+            discover,
+            windows: windows.fuse(),
+            tolerance,
+            errors: 0,
+        }
+    }
+
+    /// Returns the number of errors that have occurred in the current window.
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+}
+
+impl<D, R> Discover for ErrorTolerant<D, R>
+where
+    D: Discover,
+    R: Stream,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        loop {
+            match self.windows.poll() {
+                Ok(Async::Ready(Some(_))) | Err(_) => self.errors = 0,
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => break,
+            }
+        }
+
+        loop {
+            match self.discover.poll() {
+                Ok(ready) => return Ok(ready),
+                Err(e) => {
+                    self.errors += 1;
+                    if self.errors > self.tolerance {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use test_util::Fixed;
+
+    /// Ends a window each time the shared count is incremented.
+    struct Windows(Rc<Cell<usize>>);
+
+    impl Stream for Windows {
+        type Item = ();
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<Option<()>, ()> {
+            match self.0.get() {
+                0 => Ok(Async::NotReady),
+                n => {
+                    self.0.set(n - 1);
+                    Ok(Async::Ready(Some(())))
+                }
+            }
+        }
+    }
+
+    fn is_insert(poll: Poll<Change<usize, ()>, &'static str>, key: usize) -> bool {
+        match poll {
+            Ok(Async::Ready(Change::Insert(k, ()))) => k == key,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn errors_past_tolerance_are_surfaced() {
+        let changes = vec![
+            Ok(Change::Insert(0, ())),
+            Err("a"),
+            Err("b"),
+            Err("c"),
+            Ok(Change::Insert(1, ())),
+        ];
+        let windows = Rc::new(Cell::new(0));
+        let mut discover = ErrorTolerant::with_windows(
+            Fixed::with_results(changes),
+            2,
+            Windows(windows.clone()),
+        );
+
+        assert!(is_insert(discover.poll(), 0));
+        // The first two errors are swallowed; the third is surfaced.
+        assert_eq!(discover.poll().err(), Some("c"));
+        assert_eq!(discover.errors(), 3);
+        assert!(is_insert(discover.poll(), 1));
+    }
+
+    #[test]
+    fn errors_are_forgotten_each_window() {
+        let changes = vec![
+            Err("a"),
+            Err("b"),
+            Ok(Change::Insert(0, ())),
+            Err("c"),
+            Err("d"),
+            Ok(Change::Insert(1, ())),
+        ];
+        let windows = Rc::new(Cell::new(0));
+        let mut discover = ErrorTolerant::with_windows(
+            Fixed::with_results(changes),
+            2,
+            Windows(windows.clone()),
+        );
+
+        assert!(is_insert(discover.poll(), 0));
+        assert_eq!(discover.errors(), 2);
+
+        windows.set(1);
+        assert!(is_insert(discover.poll(), 1));
+        assert_eq!(discover.errors(), 2);
+    }
+
+    #[test]
+    fn done_once_inner_is_done() {
+        let windows = Rc::new(Cell::new(0));
+        let mut discover = ErrorTolerant::with_windows(
+            Fixed::<usize, (), &'static str>::with_results(vec![Err("a")]),
+            1,
+            Windows(windows.clone()),
+        );
+        discover.discover.finish();
+        assert!(!discover.is_done());
+
+        assert!(discover.poll().unwrap().is_not_ready());
+        assert!(discover.is_done());
+    }
+}
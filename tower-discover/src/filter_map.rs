@@ -0,0 +1,159 @@
+use futures::{Async, Poll};
+
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use {Change, Discover};
+
+/// Transforms discovered services, optionally dropping them.
+///
+/// Each inserted service is passed to `F` along with its key. If `F` returns
+/// `None`, the insert is suppressed, as is any later removal of that key. If a
+/// key that was previously accepted is re-inserted and dropped, a `Remove` is
+/// yielded in place of the insert so that the stale service is discarded.
+pub struct FilterMapService<D, F, R>
+where
+    D: Discover,
+{
+    discover: D,
+    f: F,
+    /// Keys of services that have been yielded and not yet removed.
+    accepted: HashSet<D::Key>,
+    _p: PhantomData<fn() -> R>,
+}
+
+// ===== impl FilterMapService =====
+
+impl<D, F, R> FilterMapService<D, F, R>
+where
+    D: Discover,
+    D::Key: Clone,
+    F: FnMut(&D::Key, D::Service) -> Option<R>,
+{
+    pub fn new(discover: D, f: F) -> Self {
+        FilterMapService {
+            discover,
+            f,
+            accepted: HashSet::new(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<D, F, R> Discover for FilterMapService<D, F, R>
+where
+    D: Discover,
+    D::Key: Clone,
+    F: FnMut(&D::Key, D::Service) -> Option<R>,
+{
+    type Key = D::Key;
+    type Service = R;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        loop {
+            let change = match try_ready!(self.discover.poll()) {
+                Change::Insert(key, svc) => match (self.f)(&key, svc) {
+                    Some(svc) => {
+                        self.accepted.insert(key.clone());
+                        Change::Insert(key, svc)
+                    }
+                    None => {
+                        if !self.accepted.remove(&key) {
+                            continue;
+                        }
+                        Change::Remove(key)
+                    }
+                },
+                Change::Remove(key) => {
+                    if !self.accepted.remove(&key) {
+                        continue;
+                    }
+                    Change::Remove(key)
+                }
+            };
+
+            return Ok(Async::Ready(change));
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::Fixed;
+
+    type Evens = FilterMapService<Fixed<usize, usize>, fn(&usize, usize) -> Option<String>, String>;
+
+    /// Drops odd endpoints and wraps even ones.
+    fn evens(changes: Vec<Change<usize, usize>>) -> Evens {
+        fn f(_: &usize, svc: usize) -> Option<String> {
+            if svc % 2 == 0 {
+                Some(format!("svc-{}", svc))
+            } else {
+                None
+            }
+        }
+        FilterMapService::new(Fixed::new(changes), f as fn(&usize, usize) -> _)
+    }
+
+    fn next(d: &mut Evens) -> Option<Change<usize, String>> {
+        match d.poll().unwrap() {
+            Async::Ready(c) => Some(c),
+            Async::NotReady => None,
+        }
+    }
+
+    #[test]
+    fn drops_every_other_endpoint() {
+        let mut inserts = (0..4).map(|i| Change::Insert(i, i)).collect::<Vec<_>>();
+        inserts.extend((0..4).map(Change::Remove));
+        let mut d = evens(inserts);
+
+        match next(&mut d) {
+            Some(Change::Insert(0, ref s)) if s == "svc-0" => {}
+            _ => panic!("expected insert of 0"),
+        }
+        match next(&mut d) {
+            Some(Change::Insert(2, ref s)) if s == "svc-2" => {}
+            _ => panic!("expected insert of 2"),
+        }
+        match next(&mut d) {
+            Some(Change::Remove(0)) => {}
+            _ => panic!("expected removal of 0"),
+        }
+        match next(&mut d) {
+            Some(Change::Remove(2)) => {}
+            _ => panic!("expected removal of 2"),
+        }
+        assert!(next(&mut d).is_none());
+    }
+
+    #[test]
+    fn dropped_reinsert_removes_stale_service() {
+        let mut d = evens(vec![Change::Insert(0, 0), Change::Insert(0, 1)]);
+
+        match next(&mut d) {
+            Some(Change::Insert(0, _)) => {}
+            _ => panic!("expected insert of 0"),
+        }
+        match next(&mut d) {
+            Some(Change::Remove(0)) => {}
+            _ => panic!("expected removal of 0"),
+        }
+        assert!(next(&mut d).is_none());
+    }
+
+    #[test]
+    fn done_once_inner_is_done() {
+        let mut d = evens(vec![Change::Insert(0, 0)]);
+        d.discover.finish();
+        assert!(!d.is_done());
+        assert!(next(&mut d).is_some());
+        assert!(d.is_done());
+    }
+}
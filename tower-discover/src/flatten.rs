@@ -0,0 +1,187 @@
+use futures::{Async, Poll};
+
+use std::collections::{HashSet, VecDeque};
+
+use {Change, Discover};
+
+/// Flattens a `Discover` of `Discover`s into a single `Discover`.
+///
+/// This supports hierarchical discovery, e.g. a set of regions that each
+/// discover a set of hosts. Each inner key is namespaced by the key of the
+/// `Discover` that yielded it, so that hosts in different regions may share
+/// keys without colliding.
+///
+/// When an outer `Discover` removes (or replaces) a region, a `Remove` is
+/// yielded for every endpoint that region had inserted.
+pub struct Flatten<D>
+where
+    D: Discover,
+    D::Service: Discover,
+{
+    discover: D,
+    regions: Vec<Region<D::Key, D::Service>>,
+    removals: VecDeque<(D::Key, <D::Service as Discover>::Key)>,
+}
+
+struct Region<K, D: Discover> {
+    key: K,
+    discover: D,
+    keys: HashSet<D::Key>,
+}
+
+// ===== impl Flatten =====
+
+impl<D> Flatten<D>
+where
+    D: Discover,
+    D::Key: Clone,
+    D::Service: Discover<Error = D::Error>,
+    <D::Service as Discover>::Key: Clone,
+{
+    pub fn new(discover: D) -> Self {
+        Flatten {
+            discover,
+            regions: Vec::new(),
+            removals: VecDeque::new(),
+        }
+    }
+
+    /// Drops the region identified by `key`, enqueuing a removal for each of
+    /// its endpoints.
+    fn remove_region(&mut self, key: &D::Key) {
+        if let Some(idx) = self.regions.iter().position(|r| r.key == *key) {
+            let region = self.regions.swap_remove(idx);
+            for k in region.keys {
+                self.removals.push_back((region.key.clone(), k));
+            }
+        }
+    }
+}
+
+impl<D> Discover for Flatten<D>
+where
+    D: Discover,
+    D::Key: Clone,
+    D::Service: Discover<Error = D::Error>,
+    <D::Service as Discover>::Key: Clone,
+{
+    type Key = (D::Key, <D::Service as Discover>::Key);
+    type Service = <D::Service as Discover>::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        while let Async::Ready(change) = self.discover.poll()? {
+            match change {
+                Change::Insert(key, discover) => {
+                    // A replaced region must not leak its endpoints.
+                    self.remove_region(&key);
+                    self.regions.push(Region {
+                        key,
+                        discover,
+                        keys: HashSet::new(),
+                    });
+                }
+                Change::Remove(key) => self.remove_region(&key),
+            }
+        }
+
+        if let Some(key) = self.removals.pop_front() {
+            return Ok(Async::Ready(Change::Remove(key)));
+        }
+
+        for region in &mut self.regions {
+            if let Async::Ready(change) = region.discover.poll()? {
+                let change = match change {
+                    Change::Insert(k, svc) => {
+                        region.keys.insert(k.clone());
+                        Change::Insert((region.key.clone(), k), svc)
+                    }
+                    Change::Remove(k) => {
+                        region.keys.remove(&k);
+                        Change::Remove((region.key.clone(), k))
+                    }
+                };
+                return Ok(Async::Ready(change));
+            }
+        }
+
+        Ok(Async::NotReady)
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+            && self.removals.is_empty()
+            && self.regions.iter().all(|r| r.discover.is_done())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::{drain, Fixed};
+
+    type Hosts = Fixed<&'static str, &'static str>;
+
+    fn hosts(names: &[&'static str]) -> Hosts {
+        Fixed::new(names.iter().map(|n| Change::Insert(*n, *n)))
+    }
+
+    #[test]
+    fn namespaces_keys() {
+        let regions = vec![
+            Change::Insert("east", hosts(&["a", "b"])),
+            Change::Insert("west", hosts(&["a", "b"])),
+        ];
+        let mut flat = Flatten::new(Fixed::new(regions));
+
+        let mut inserted = drain(&mut flat)
+            .into_iter()
+            .map(|c| match c {
+                Change::Insert(k, _) => k,
+                Change::Remove(_) => panic!("unexpected remove"),
+            })
+            .collect::<Vec<_>>();
+        inserted.sort();
+
+        assert_eq!(
+            inserted,
+            vec![("east", "a"), ("east", "b"), ("west", "a"), ("west", "b")]
+        );
+    }
+
+    #[test]
+    fn region_removal_cascades() {
+        let regions = vec![
+            Change::Insert("east", hosts(&["a", "b"])),
+            Change::Insert("west", hosts(&["a", "b"])),
+        ];
+        let mut flat = Flatten::new(Fixed::new(regions));
+        assert_eq!(drain(&mut flat).len(), 4);
+
+        flat.discover.push(Change::Remove("east"));
+
+        let mut removed = drain(&mut flat)
+            .into_iter()
+            .map(|c| match c {
+                Change::Remove(k) => k,
+                Change::Insert(..) => panic!("unexpected insert"),
+            })
+            .collect::<Vec<_>>();
+        removed.sort();
+
+        assert_eq!(removed, vec![("east", "a"), ("east", "b")]);
+        assert_eq!(flat.regions.len(), 1);
+    }
+
+    #[test]
+    fn done_once_every_region_is_done() {
+        let regions = vec![Change::Insert("east", hosts(&["a"]))];
+        let mut flat = Flatten::new(Fixed::new(regions));
+        flat.discover.finish();
+        assert_eq!(drain(&mut flat).len(), 1);
+        assert!(!flat.is_done());
+
+        flat.regions[0].discover.finish();
+        assert!(flat.is_done());
+    }
+}
@@ -0,0 +1,195 @@
+use futures::{Async, Future, IntoFuture, Poll};
+
+use std::collections::HashSet;
+
+use {Change, Discover};
+
+/// Withholds discovered services until they pass a health probe.
+///
+/// Each inserted service is passed to `F` along with its key. `F` returns a
+/// probe, which yields the service back once it is healthy; only then is the
+/// service inserted. If the probe fails, the service is dropped and never
+/// inserted. If a key that was previously inserted is re-inserted and the
+/// replacement fails its probe, a `Remove` is yielded so that the stale
+/// service is discarded.
+///
+/// A service that is removed before its probe completes is never inserted.
+pub struct HealthGate<D, F, R>
+where
+    D: Discover,
+    R: IntoFuture,
+{
+    discover: D,
+    probe: F,
+    /// Services awaiting their probes.
+    probing: Vec<(D::Key, R::Future)>,
+    /// Keys of services that have been yielded and not yet removed.
+    healthy: HashSet<D::Key>,
+}
+
+// ===== impl HealthGate =====
+
+impl<D, F, R> HealthGate<D, F, R>
+where
+    D: Discover,
+    D::Key: Clone,
+    F: FnMut(&D::Key, D::Service) -> R,
+    R: IntoFuture<Item = D::Service>,
+{
+    pub fn new(discover: D, probe: F) -> Self {
+        HealthGate {
+            discover,
+            probe,
+            probing: Vec::new(),
+            healthy: HashSet::new(),
+        }
+    }
+
+    /// Returns the number of services awaiting their probes.
+    pub fn num_probing(&self) -> usize {
+        self.probing.len()
+    }
+
+    /// Stops probing the service with the given key, if there is one.
+    fn cancel_probe(&mut self, key: &D::Key) {
+        self.probing.retain(|&(ref k, _)| k != key);
+    }
+}
+
+impl<D, F, R> Discover for HealthGate<D, F, R>
+where
+    D: Discover,
+    D::Key: Clone,
+    F: FnMut(&D::Key, D::Service) -> R,
+    R: IntoFuture<Item = D::Service>,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        while let Async::Ready(change) = self.discover.poll()? {
+            match change {
+                Change::Insert(key, svc) => {
+                    self.cancel_probe(&key);
+                    let probe = (self.probe)(&key, svc).into_future();
+                    self.probing.push((key, probe));
+                }
+                Change::Remove(key) => {
+                    self.cancel_probe(&key);
+                    if self.healthy.remove(&key) {
+                        return Ok(Async::Ready(Change::Remove(key)));
+                    }
+                }
+            }
+        }
+
+        let mut idx = 0;
+        while idx < self.probing.len() {
+            let result = self.probing[idx].1.poll();
+            match result {
+                Ok(Async::NotReady) => {
+                    idx += 1;
+                }
+                Ok(Async::Ready(svc)) => {
+                    let (key, _) = self.probing.swap_remove(idx);
+                    self.healthy.insert(key.clone());
+                    return Ok(Async::Ready(Change::Insert(key, svc)));
+                }
+                Err(_) => {
+                    let (key, _) = self.probing.swap_remove(idx);
+                    if self.healthy.remove(&key) {
+                        return Ok(Async::Ready(Change::Remove(key)));
+                    }
+                }
+            }
+        }
+
+        Ok(Async::NotReady)
+    }
+
+    fn is_done(&self) -> bool {
+        self.probing.is_empty() && self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use super::*;
+    use test_util::{drain, Fixed};
+
+    type Probe = fn(&usize, &'static str) -> FutureResult<&'static str, ()>;
+    type Gate = HealthGate<Fixed<usize, &'static str>, Probe, FutureResult<&'static str, ()>>;
+
+    /// Fails services named "sick".
+    fn gate(changes: Vec<Change<usize, &'static str>>) -> Gate {
+        fn probe(_: &usize, svc: &'static str) -> FutureResult<&'static str, ()> {
+            if svc == "sick" {
+                future::err(())
+            } else {
+                future::ok(svc)
+            }
+        }
+        HealthGate::new(Fixed::new(changes), probe as Probe)
+    }
+
+    fn inserted(changes: &[Change<usize, &'static str>]) -> Vec<usize> {
+        let mut keys = changes
+            .iter()
+            .filter_map(|c| match *c {
+                Change::Insert(k, _) => Some(k),
+                Change::Remove(_) => None,
+            })
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn failed_probe_is_never_inserted() {
+        let mut d = gate(vec![
+            Change::Insert(0, "ok"),
+            Change::Insert(1, "sick"),
+            Change::Insert(2, "ok"),
+        ]);
+
+        let changes = drain(&mut d);
+        assert_eq!(inserted(&changes), vec![0, 2]);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(d.num_probing(), 0);
+
+        // The removal of a service that was never inserted is suppressed.
+        d.discover.push(Change::Remove(1));
+        assert!(drain(&mut d).is_empty());
+    }
+
+    #[test]
+    fn failed_replacement_removes_stale_service() {
+        let mut d = gate(vec![Change::Insert(0, "ok")]);
+        assert_eq!(inserted(&drain(&mut d)), vec![0]);
+
+        d.discover.push(Change::Insert(0, "sick"));
+        let changes = drain(&mut d);
+        assert_eq!(changes.len(), 1);
+        match changes[0] {
+            Change::Remove(0) => {}
+            _ => panic!("expected removal of 0"),
+        }
+    }
+
+    #[test]
+    fn not_done_while_probing() {
+        let changes = vec![Change::Insert(0, "slow")];
+        let mut d = HealthGate::new(Fixed::new(changes), |_: &usize, _| future::empty::<&'static str, ()>());
+        d.discover.finish();
+
+        assert!(drain(&mut d).is_empty());
+        assert_eq!(d.num_probing(), 1);
+        assert!(!d.is_done());
+
+        d.discover.push(Change::Remove(0));
+        assert!(drain(&mut d).is_empty());
+        assert!(d.is_done());
+    }
+}
@@ -7,6 +7,9 @@
 
 #[macro_use]
 extern crate futures;
+#[macro_use]
+extern crate log;
+extern crate tokio_timer;
 extern crate tower_service;
 
 use futures::{Async, Poll, Stream};
@@ -16,6 +19,38 @@ use std::hash::Hash;
 use std::iter::{Enumerate, IntoIterator};
 use std::marker::PhantomData;
 
+mod auto_evict;
+mod bounded;
+mod error_tolerant;
+mod filter_map;
+mod flatten;
+mod health_gate;
+mod logged;
+mod map_error;
+mod namespaced;
+mod replay;
+mod throttle;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod timeout;
+pub mod virtual_nodes;
+pub mod with_extra;
+
+pub use auto_evict::AutoEvict;
+pub use bounded::{bounded, FromBoundedStream};
+pub use error_tolerant::ErrorTolerant;
+pub use filter_map::FilterMapService;
+pub use flatten::Flatten;
+pub use health_gate::HealthGate;
+pub use logged::Logged;
+pub use map_error::MapDiscoverError;
+pub use namespaced::Namespaced;
+pub use replay::Replay;
+pub use throttle::Throttle;
+pub use timeout::Timeout;
+pub use virtual_nodes::VirtualNodes;
+pub use with_extra::WithExtra;
+
 /// Provide a uniform set of services able to satisfy a request.
 ///
 /// This set of services may be updated over time. On each change to the set, a
@@ -33,6 +68,44 @@ pub trait Discover {
 
     /// Yields the next discovery change set.
     fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error>;
+
+    /// Returns true once this `Discover` will never yield another change, e.g.
+    /// because the stream backing it has ended.
+    ///
+    /// Consumers may then stop polling it, while continuing to use the services
+    /// it has already yielded. `poll` must still return `NotReady` once done.
+    /// By default, a `Discover` is never done.
+    fn is_done(&self) -> bool {
+        false
+    }
+}
+
+impl<'a, D: Discover + ?Sized> Discover for &'a mut D {
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        (**self).poll()
+    }
+
+    fn is_done(&self) -> bool {
+        (**self).is_done()
+    }
+}
+
+impl<D: Discover + ?Sized> Discover for Box<D> {
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        (**self).poll()
+    }
+
+    fn is_done(&self) -> bool {
+        (**self).is_done()
+    }
 }
 
 /// A change in the service set
@@ -44,12 +117,15 @@ pub enum Change<K, V> {
 /// Static service discovery based on a predetermined list of services.
 ///
 /// `List` is created with an initial list of services. The discovery process
-/// will yield this list once and do nothing after.
+/// will yield this list once and do nothing after: once the list has been
+/// yielded, `is_done` returns true.
 pub struct List<T>
 where
     T: IntoIterator,
 {
     inner: Enumerate<T::IntoIter>,
+    /// Whether every service has been yielded.
+    done: bool,
 }
 
 // ===== impl List =====
@@ -64,6 +140,7 @@ where
     {
         List {
             inner: services.into_iter().enumerate(),
+            done: false,
         }
     }
 }
@@ -79,14 +156,83 @@ where
     fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
         match self.inner.next() {
             Some((i, service)) => Ok(Change::Insert(i, service).into()),
-            None => Ok(Async::NotReady),
+            None => {
+                self.done = true;
+                Ok(Async::NotReady)
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// Static service discovery based on a predetermined list of keyed services.
+///
+/// Unlike `List`, which keys services by their position, `KeyedList` is
+/// created with a list of `(key, service)` pairs. This keeps each service's key
+/// stable when the list is rebuilt, even if the order of the services changes.
+/// The discovery process will yield this list once and do nothing after: once
+/// the list has been yielded, `is_done` returns true.
+pub struct KeyedList<T>
+where
+    T: IntoIterator,
+{
+    inner: T::IntoIter,
+    /// Whether every service has been yielded.
+    done: bool,
+}
+
+// ===== impl KeyedList =====
+
+impl<T, K, U> KeyedList<T>
+where
+    T: IntoIterator<Item = (K, U)>,
+    K: Hash + Eq,
+{
+    pub fn new<Request>(services: T) -> KeyedList<T>
+    where
+        U: Service<Request>,
+    {
+        KeyedList {
+            inner: services.into_iter(),
+            done: false,
+        }
+    }
+}
+
+impl<T, K, U> Discover for KeyedList<T>
+where
+    T: IntoIterator<Item = (K, U)>,
+    K: Hash + Eq,
+{
+    type Key = K;
+    type Service = U;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        match self.inner.next() {
+            Some((key, service)) => Ok(Change::Insert(key, service).into()),
+            None => {
+                self.done = true;
+                Ok(Async::NotReady)
+            }
         }
     }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
 }
 
 /// Dynamic service discovery based on a stream of service changes.
+///
+/// Once the stream ends, `is_done` returns true.
 pub struct Services<S, K, Svc> {
     inner: futures::stream::Fuse<S>,
+    /// Whether the stream has ended.
+    done: bool,
     _marker_k: PhantomData<K>,
     _marker_v: PhantomData<Svc>,
 }
@@ -103,6 +249,7 @@ where
     {
         Services {
             inner: services.fuse(),
+            done: false,
             _marker_k: PhantomData,
             _marker_v: PhantomData,
         }
@@ -123,10 +270,15 @@ where
             Some(c) => Ok(Async::Ready(c)),
             None => {
                 // there are no more service changes coming
+                self.done = true;
                 Ok(Async::NotReady)
             }
         }
     }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
 }
 
 // check that List can be directly over collections
@@ -137,3 +289,83 @@ type ListVecTest<T> = List<Vec<T>>;
 #[cfg(test)]
 #[allow(dead_code)]
 type ListVecIterTest<T> = List<::std::vec::IntoIter<T>>;
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Svc(&'static str);
+
+    impl Service<()> for Svc {
+        type Response = ();
+        type Error = ();
+        type Future = FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(())
+        }
+    }
+
+    fn discover_all<D>(mut discover: D) -> HashMap<D::Key, D::Service>
+    where
+        D: Discover<Error = ()>,
+    {
+        let mut services = HashMap::new();
+        while let Async::Ready(change) = discover.poll().unwrap() {
+            match change {
+                Change::Insert(key, svc) => services.insert(key, svc),
+                Change::Remove(key) => services.remove(&key),
+            };
+        }
+        services
+    }
+
+    fn addrs(order: &[&'static str]) -> Vec<(&'static str, Svc)> {
+        order.iter().map(|&addr| (addr, Svc(addr))).collect()
+    }
+
+    #[test]
+    fn keyed_list_keys_are_stable_across_rebuilds() {
+        let first = discover_all(KeyedList::new(addrs(&["a", "b", "c"])));
+        let second = discover_all(KeyedList::new(addrs(&["c", "a", "b"])));
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, second);
+        for (key, svc) in &second {
+            assert_eq!(*key, svc.0);
+        }
+    }
+
+    #[test]
+    fn list_keys_follow_order() {
+        let list = |order: &[&'static str]| List::new(addrs(order).into_iter().map(|(_, svc)| svc));
+
+        let first = discover_all(list(&["a", "b"]));
+        let second = discover_all(list(&["b", "a"]));
+        assert_eq!(first[&0], Svc("a"));
+        assert_eq!(second[&0], Svc("b"));
+    }
+
+    #[test]
+    fn lists_are_done_once_yielded() {
+        let mut list = List::new(vec![Svc("a")]);
+        assert!(!list.is_done());
+        assert!(list.poll().unwrap().is_ready());
+        assert!(list.poll().unwrap().is_not_ready());
+        assert!(list.is_done());
+
+        let mut keyed = KeyedList::new(addrs(&["a"]));
+        assert!(keyed.poll().unwrap().is_ready());
+        assert!(!keyed.is_done());
+        assert!(keyed.poll().unwrap().is_not_ready());
+        assert!(keyed.is_done());
+    }
+}
@@ -0,0 +1,138 @@
+use futures::{Async, Poll};
+
+use std::fmt;
+
+use {Change, Discover};
+
+/// Logs every change and error yielded by a `Discover`, e.g. to debug churn in
+/// the service set.
+///
+/// Inserts, removals and errors are logged at `debug` level, prefixed with
+/// `name` to tell discovery sources apart. Polls that yield nothing are logged
+/// at `trace` level.
+pub struct Logged<D, R> {
+    discover: D,
+    name: R,
+}
+
+// ===== impl Logged =====
+
+impl<D, R> Logged<D, R>
+where
+    D: Discover,
+    D::Key: fmt::Debug,
+    D::Error: fmt::Debug,
+    R: fmt::Display,
+{
+    pub fn new(discover: D, name: R) -> Self {
+        Logged { discover, name }
+    }
+}
+
+impl<D, R> Discover for Logged<D, R>
+where
+    D: Discover,
+    D::Key: fmt::Debug,
+    D::Error: fmt::Debug,
+    R: fmt::Display,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        match self.discover.poll() {
+            Ok(Async::Ready(change)) => {
+                match change {
+                    Change::Insert(ref key, _) => debug!("{}: insert {:?}", self.name, key),
+                    Change::Remove(ref key) => debug!("{}: remove {:?}", self.name, key),
+                }
+                Ok(Async::Ready(change))
+            }
+            Ok(Async::NotReady) => {
+                trace!("{}: not ready", self.name);
+                Ok(Async::NotReady)
+            }
+            Err(e) => {
+                debug!("{}: error {:?}", self.name, e);
+                Err(e)
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{self, Level, LevelFilter, Log, Metadata, Record};
+
+    use std::cell::RefCell;
+    use std::sync::{Once, ONCE_INIT};
+
+    use super::*;
+    use test_util::Fixed;
+
+    thread_local! {
+        static CAPTURED: RefCell<Vec<(Level, String)>> = RefCell::new(Vec::new());
+    }
+
+    /// Captures this crate's log lines for the logging thread, so that tests
+    /// running in parallel do not see each other's lines.
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target().starts_with("tower_discover")
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                let line = (record.level(), record.args().to_string());
+                CAPTURED.with(|captured| captured.borrow_mut().push(line));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture;
+    static INIT: Once = ONCE_INIT;
+
+    fn captured() -> Vec<(Level, String)> {
+        INIT.call_once(|| {
+            log::set_logger(&CAPTURE).expect("set logger");
+            log::set_max_level(LevelFilter::Trace);
+        });
+        CAPTURED.with(|captured| captured.borrow_mut().drain(..).collect())
+    }
+
+    #[test]
+    fn logs_each_change() {
+        captured();
+
+        let script = vec![
+            Ok(Change::Insert("a", ())),
+            Ok(Change::Insert("b", ())),
+            Ok(Change::Remove("a")),
+            Err("registry unavailable"),
+        ];
+        let mut logged = Logged::new(Fixed::with_results(script), "dns");
+        for _ in 0..5 {
+            let _ = logged.poll();
+        }
+
+        assert_eq!(
+            captured(),
+            vec![
+                (Level::Debug, "dns: insert \"a\"".to_string()),
+                (Level::Debug, "dns: insert \"b\"".to_string()),
+                (Level::Debug, "dns: remove \"a\"".to_string()),
+                (Level::Debug, "dns: error \"registry unavailable\"".to_string()),
+                (Level::Trace, "dns: not ready".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use futures::Poll;
+
+use std::marker::PhantomData;
+
+use {Change, Discover};
+
+/// Converts the errors of a `Discover` with `F`.
+///
+/// This mirrors `map_err` for services, and is useful to give discovery sources
+/// with different error types a common one before they are combined.
+pub struct MapDiscoverError<D, F, E> {
+    discover: D,
+    f: F,
+    _p: PhantomData<fn() -> E>,
+}
+
+// ===== impl MapDiscoverError =====
+
+impl<D, F, E> MapDiscoverError<D, F, E>
+where
+    D: Discover,
+    F: Fn(D::Error) -> E,
+{
+    pub fn new(discover: D, f: F) -> Self {
+        MapDiscoverError {
+            discover,
+            f,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<D, F, E> Discover for MapDiscoverError<D, F, E>
+where
+    D: Discover,
+    F: Fn(D::Error) -> E,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        self.discover.poll().map_err(&self.f)
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::Async;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum SourceError {
+        Dns(&'static str),
+        Registry(u16),
+    }
+
+    /// Fails every poll with a fixed error.
+    struct Failing<E>(E);
+
+    impl<E: Copy> Discover for Failing<E> {
+        type Key = usize;
+        type Service = ();
+        type Error = E;
+
+        fn poll(&mut self) -> Poll<Change<usize, ()>, E> {
+            Err(self.0)
+        }
+    }
+
+    /// Yields nothing.
+    struct Idle;
+
+    impl Discover for Idle {
+        type Key = usize;
+        type Service = ();
+        type Error = &'static str;
+
+        fn poll(&mut self) -> Poll<Change<usize, ()>, &'static str> {
+            Ok(Async::NotReady)
+        }
+    }
+
+    #[test]
+    fn errors_are_converted() {
+        let mut dns = MapDiscoverError::new(Failing("nxdomain"), SourceError::Dns);
+        let mut registry = MapDiscoverError::new(Failing(503u16), SourceError::Registry);
+
+        match dns.poll() {
+            Err(e) => assert_eq!(e, SourceError::Dns("nxdomain")),
+            Ok(_) => panic!("expected an error"),
+        }
+        match registry.poll() {
+            Err(e) => assert_eq!(e, SourceError::Registry(503)),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn changes_pass_through() {
+        let mut idle = MapDiscoverError::new(Idle, SourceError::Dns);
+        assert!(idle.poll().unwrap().is_not_ready());
+    }
+}
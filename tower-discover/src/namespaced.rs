@@ -0,0 +1,104 @@
+use futures::{Async, Poll};
+
+use std::hash::Hash;
+
+use {Change, Discover};
+
+/// Prefixes each key of a `Discover` with a namespace, as `(prefix, key)`.
+///
+/// This keeps keys from different discovery sources distinct, and readable,
+/// when their services are combined, e.g. `("us-east", 3)` and
+/// `("us-west", 3)`.
+#[derive(Debug)]
+pub struct Namespaced<D, P> {
+    discover: D,
+    prefix: P,
+}
+
+// ===== impl Namespaced =====
+
+impl<D, P> Namespaced<D, P>
+where
+    D: Discover,
+    P: Clone + Hash + Eq,
+{
+    pub fn new(discover: D, prefix: P) -> Self {
+        Namespaced { discover, prefix }
+    }
+
+    /// Returns the prefix applied to each key.
+    pub fn prefix(&self) -> &P {
+        &self.prefix
+    }
+}
+
+impl<D, P> Discover for Namespaced<D, P>
+where
+    D: Discover,
+    P: Clone + Hash + Eq,
+{
+    type Key = (P, D::Key);
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        let change = match try_ready!(self.discover.poll()) {
+            Change::Insert(key, svc) => Change::Insert((self.prefix.clone(), key), svc),
+            Change::Remove(key) => Change::Remove((self.prefix.clone(), key)),
+        };
+
+        Ok(Async::Ready(change))
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::Fixed;
+
+    fn next(discover: &mut Namespaced<Fixed<usize, &'static str>, &'static str>) -> Change<(&'static str, usize), &'static str> {
+        match discover.poll() {
+            Ok(Async::Ready(change)) => change,
+            _ => panic!("expected a change"),
+        }
+    }
+
+    #[test]
+    fn keys_are_prefixed() {
+        let changes = vec![Change::Insert(0, "a"), Change::Insert(1, "b"), Change::Remove(0)];
+        let mut east = Namespaced::new(Fixed::new(changes), "us-east");
+
+        match next(&mut east) {
+            Change::Insert(key, svc) => assert_eq!((key, svc), (("us-east", 0), "a")),
+            Change::Remove(_) => panic!("expected an insert"),
+        }
+        match next(&mut east) {
+            Change::Insert(key, svc) => assert_eq!((key, svc), (("us-east", 1), "b")),
+            Change::Remove(_) => panic!("expected an insert"),
+        }
+        match next(&mut east) {
+            Change::Remove(key) => assert_eq!(key, ("us-east", 0)),
+            Change::Insert(..) => panic!("expected a removal"),
+        }
+        assert!(east.poll().unwrap().is_not_ready());
+    }
+
+    #[test]
+    fn equal_keys_in_different_namespaces_are_distinct() {
+        let mut east = Namespaced::new(Fixed::new(vec![Change::Insert(0, "a")]), "us-east");
+        let mut west = Namespaced::new(Fixed::new(vec![Change::Insert(0, "b")]), "us-west");
+
+        let keys = [next(&mut east), next(&mut west)]
+            .iter()
+            .map(|change| match *change {
+                Change::Insert(key, _) => key,
+                Change::Remove(key) => key,
+            })
+            .collect::<::std::collections::HashSet<_>>();
+        assert_eq!(keys.len(), 2);
+    }
+}
@@ -0,0 +1,273 @@
+use futures::{Async, Future, Poll};
+use tokio_timer::{clock, Delay};
+
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use {Change, Discover};
+
+/// Records the set of live keys, so that it may be replayed after a restart.
+///
+/// `snapshot` returns the keys that are currently inserted. When a `Replay` is
+/// created from a prior snapshot with `with_snapshot`, each key is first
+/// yielded as an insert, with a service built by `F`, before changes from the
+/// live `Discover` are yielded. This warms a consumer, e.g. a balancer, before
+/// live discovery has caught up.
+///
+/// Services that should be rebuilt from more than their key alone may be
+/// described by the key itself, e.g. by keying them by an address that `F`
+/// connects to. Live discovery takes over from the snapshot as it yields
+/// changes: a live insert replaces a replayed service, and a live removal
+/// removes it.
+///
+/// A replayed key that live discovery never inserts is stale, e.g. because the
+/// endpoint went away while the consumer was down. Once the live `Discover` is
+/// done, or once the deadline set by `with_confirm_deadline` has passed, each
+/// replayed key that has not been confirmed by a live insert is removed.
+pub struct Replay<D, F>
+where
+    D: Discover,
+{
+    discover: D,
+    make_service: F,
+    /// Keys from a prior snapshot that have not yet been replayed.
+    replay: VecDeque<D::Key>,
+    /// Keys that have been inserted and not yet removed.
+    live: HashSet<D::Key>,
+    /// Replayed keys that live discovery has not yet inserted.
+    unconfirmed: HashSet<D::Key>,
+    /// Fires when unconfirmed keys are considered stale, if ever.
+    deadline: Option<Delay>,
+    /// Whether unconfirmed keys are considered stale.
+    synced: bool,
+}
+
+// ===== impl Replay =====
+
+impl<D, F> Replay<D, F>
+where
+    D: Discover,
+    D::Key: Clone,
+    F: FnMut(&D::Key) -> D::Service,
+{
+    /// Records the keys yielded by `discover`, without replaying any.
+    pub fn new(discover: D, make_service: F) -> Self {
+        Self::with_snapshot(discover, Vec::new(), make_service)
+    }
+
+    /// Replays each key in `snapshot` as an insert, building its service with
+    /// `make_service`, before yielding changes from `discover`.
+    pub fn with_snapshot<I>(discover: D, snapshot: I, make_service: F) -> Self
+    where
+        I: IntoIterator<Item = D::Key>,
+    {
+        Replay {
+            discover,
+            make_service,
+            replay: snapshot.into_iter().collect(),
+            live: HashSet::new(),
+            unconfirmed: HashSet::new(),
+            deadline: None,
+            synced: false,
+        }
+    }
+
+    /// Removes replayed keys that live discovery has not inserted within
+    /// `deadline`, even if it is not yet done.
+    pub fn with_confirm_deadline(self, deadline: Duration) -> Self {
+        Replay {
+            deadline: Some(Delay::new(clock::now() + deadline)),
+            ..self
+        }
+    }
+
+    /// Returns the keys that are currently inserted, in no particular order.
+    ///
+    /// Keys from a prior snapshot that have not yet been replayed are included.
+    pub fn snapshot(&self) -> Vec<D::Key> {
+        self.live
+            .iter()
+            .chain(self.replay.iter().filter(|k| !self.live.contains(k)))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns true once live discovery has caught up, or the deadline for
+    /// confirming replayed keys has passed.
+    fn poll_synced(&mut self) -> bool {
+        if !self.synced {
+            let expired = match self.deadline.as_mut().map(Future::poll) {
+                None | Some(Ok(Async::NotReady)) => false,
+                Some(Ok(Async::Ready(()))) => true,
+                Some(Err(_)) => {
+                    debug!("confirm deadline failed; removing unconfirmed keys");
+                    true
+                }
+            };
+            self.synced = expired || self.discover.is_done();
+        }
+        self.synced
+    }
+}
+
+impl<D, F> Discover for Replay<D, F>
+where
+    D: Discover,
+    D::Key: Clone,
+    F: FnMut(&D::Key) -> D::Service,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        while let Some(key) = self.replay.pop_front() {
+            if self.live.insert(key.clone()) {
+                self.unconfirmed.insert(key.clone());
+                let svc = (self.make_service)(&key);
+                return Ok(Async::Ready(Change::Insert(key, svc)));
+            }
+        }
+
+        if let Async::Ready(change) = self.discover.poll()? {
+            match change {
+                Change::Insert(ref key, _) => {
+                    self.live.insert(key.clone());
+                    self.unconfirmed.remove(key);
+                }
+                Change::Remove(ref key) => {
+                    self.live.remove(key);
+                    self.unconfirmed.remove(key);
+                }
+            }
+            return Ok(Async::Ready(change));
+        }
+
+        if self.unconfirmed.is_empty() || !self.poll_synced() {
+            return Ok(Async::NotReady);
+        }
+
+        let key = self.unconfirmed.iter().next().cloned().expect("unconfirmed key");
+        trace!("removing unconfirmed replayed key");
+        self.unconfirmed.remove(&key);
+        self.live.remove(&key);
+        Ok(Async::Ready(Change::Remove(key)))
+    }
+
+    fn is_done(&self) -> bool {
+        self.replay.is_empty() && self.unconfirmed.is_empty() && self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tower_timeout;
+
+    use self::tower_timeout::test_util::with_mock_timer;
+    use super::*;
+    use test_util::{drain, Fixed};
+
+    fn connect(addr: &&'static str) -> String {
+        format!("replayed {}", addr)
+    }
+
+    fn removed<K, V>(changes: Vec<Change<K, V>>) -> Vec<K> {
+        changes
+            .into_iter()
+            .map(|c| match c {
+                Change::Remove(k) => k,
+                Change::Insert(..) => panic!("unexpected insertion"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_is_replayed_as_inserts() {
+        let live = vec![
+            Change::Insert("a", "live a".to_owned()),
+            Change::Insert("b", "live b".to_owned()),
+            Change::Insert("c", "live c".to_owned()),
+        ];
+        let mut d = Replay::new(Fixed::new(live), connect);
+        assert_eq!(drain(&mut d).len(), 3);
+
+        let mut snapshot = d.snapshot();
+        snapshot.sort();
+        assert_eq!(snapshot, vec!["a", "b", "c"]);
+
+        // After a restart, before live discovery has yielded anything.
+        let mut d = Replay::with_snapshot(Fixed::new(vec![]), snapshot, connect);
+        let replayed = drain(&mut d)
+            .into_iter()
+            .map(|c| match c {
+                Change::Insert(k, svc) => (k, svc),
+                Change::Remove(_) => panic!("unexpected removal"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            replayed,
+            vec![
+                ("a", "replayed a".to_owned()),
+                ("b", "replayed b".to_owned()),
+                ("c", "replayed c".to_owned()),
+            ]
+        );
+
+        // Live discovery takes over.
+        d.discover.push(Change::Remove("b"));
+        assert_eq!(drain(&mut d).len(), 1);
+        let mut snapshot = d.snapshot();
+        snapshot.sort();
+        assert_eq!(snapshot, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn unconfirmed_keys_are_removed_once_synced() {
+        let live = vec![Change::Insert("a", "live a".to_owned())];
+        let mut d = Replay::with_snapshot(Fixed::new(live), vec!["a", "b"], connect);
+        assert_eq!(drain(&mut d).len(), 3);
+
+        // Live discovery may not have caught up yet.
+        assert!(drain(&mut d).is_empty());
+        assert_eq!(d.snapshot().len(), 2);
+
+        d.discover.finish();
+        assert_eq!(removed(drain(&mut d)), vec!["b"]);
+        assert_eq!(d.snapshot(), vec!["a"]);
+    }
+
+    #[test]
+    fn unconfirmed_keys_are_removed_after_deadline() {
+        with_mock_timer(|time, timer| {
+            let live = vec![Change::Insert("a", "live a".to_owned())];
+            let mut d = Replay::with_snapshot(Fixed::new(live), vec!["a", "b"], connect)
+                .with_confirm_deadline(Duration::from_secs(1));
+            assert_eq!(drain(&mut d).len(), 3);
+
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(drain(&mut d).is_empty());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            assert_eq!(removed(drain(&mut d)), vec!["b"]);
+            assert_eq!(d.snapshot(), vec!["a"]);
+
+            // A later live insert of the removed key is yielded as usual.
+            d.discover.push(Change::Insert("b", "live b".to_owned()));
+            assert_eq!(drain(&mut d).len(), 1);
+            assert_eq!(d.snapshot().len(), 2);
+        });
+    }
+
+    #[test]
+    fn done_once_snapshot_is_settled() {
+        let mut d = Replay::with_snapshot(Fixed::new(vec![]), vec!["a"], connect);
+        d.discover.finish();
+        assert!(!d.is_done());
+
+        // The replayed key is inserted, then removed as it is never confirmed.
+        assert_eq!(drain(&mut d).len(), 2);
+        assert!(d.is_done());
+    }
+}
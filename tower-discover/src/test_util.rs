@@ -0,0 +1,93 @@
+//! Utilities for testing middleware that is composed with `Discover`.
+//!
+//! This module is only available when the `test-util` feature is enabled.
+
+use futures::{future, Async, Future, Poll};
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+
+use {Change, Discover};
+
+/// A `Discover` that yields a fixed sequence of changes and errors, and is then
+/// not ready.
+///
+/// Further changes may be pushed at any time. Once `finish` is called, it is
+/// done as soon as every pushed change has been yielded.
+#[derive(Debug)]
+pub struct Fixed<K, V, E = ()> {
+    changes: VecDeque<Result<Change<K, V>, E>>,
+    finished: bool,
+}
+
+/// Polls `discover` until it is not ready, returning the changes it yielded.
+///
+/// `discover` is polled within a task, so that it may register interest in
+/// being notified.
+pub fn drain<D>(discover: &mut D) -> Vec<Change<D::Key, D::Service>>
+where
+    D: Discover,
+    D::Error: fmt::Debug,
+{
+    future::lazy(|| {
+        let mut changes = Vec::new();
+        while let Async::Ready(change) = discover.poll().unwrap() {
+            changes.push(change);
+        }
+        Ok::<_, ()>(changes)
+    }).wait()
+        .unwrap()
+}
+
+// ===== impl Fixed =====
+
+impl<K, V> Fixed<K, V> {
+    /// Yields each of `changes`, in order.
+    pub fn new<I>(changes: I) -> Self
+    where
+        I: IntoIterator<Item = Change<K, V>>,
+    {
+        Self::with_results(changes.into_iter().map(Ok))
+    }
+}
+
+impl<K, V, E> Fixed<K, V, E> {
+    /// Yields each of `results`, in order, as a change or an error.
+    pub fn with_results<I>(results: I) -> Self
+    where
+        I: IntoIterator<Item = Result<Change<K, V>, E>>,
+    {
+        Fixed {
+            changes: results.into_iter().collect(),
+            finished: false,
+        }
+    }
+
+    /// Yields `change` once the changes before it have been yielded.
+    pub fn push(&mut self, change: Change<K, V>) {
+        self.changes.push_back(Ok(change));
+    }
+
+    /// Yields no changes after those already pushed.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+}
+
+impl<K: Hash + Eq, V, E> Discover for Fixed<K, V, E> {
+    type Key = K;
+    type Service = V;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<Change<K, V>, E> {
+        match self.changes.pop_front() {
+            Some(Ok(change)) => Ok(Async::Ready(change)),
+            Some(Err(e)) => Err(e),
+            None => Ok(Async::NotReady),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.finished && self.changes.is_empty()
+    }
+}
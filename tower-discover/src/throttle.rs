@@ -0,0 +1,175 @@
+use futures::{Async, Poll, Stream};
+use tokio_timer::{clock, Interval};
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use {Change, Discover};
+
+/// Limits how quickly changes from a `Discover` are released.
+///
+/// At most `per_tick` changes are yielded each time the `R`-typed tick stream
+/// produces an item; the rest are buffered in order. This lets a consumer
+/// apply a large burst of updates gradually rather than all at once.
+///
+/// If the tick stream ends, changes are no longer throttled. If it fails, the
+/// failure is treated as a tick, so that discovery is never stalled
+/// indefinitely.
+pub struct Throttle<D, R = Interval>
+where
+    D: Discover,
+{
+    discover: D,
+    ticks: R,
+    per_tick: usize,
+    /// The number of changes that may be released before the next tick.
+    remaining: usize,
+    buffer: VecDeque<Change<D::Key, D::Service>>,
+}
+
+// ===== impl Throttle =====
+
+impl<D: Discover> Throttle<D> {
+    /// Releases at most `per_tick` changes every `period`.
+    pub fn new(discover: D, per_tick: usize, period: Duration) -> Self {
+        let ticks = Interval::new(clock::now() + period, period);
+        Self::with_ticks(discover, per_tick, ticks)
+    }
+}
+
+impl<D, R> Throttle<D, R>
+where
+    D: Discover,
+    R: Stream,
+{
+    /// Releases at most `per_tick` changes each time `ticks` yields an item.
+    ///
+    /// The first `per_tick` changes are released immediately.
+    pub fn with_ticks(discover: D, per_tick: usize, ticks: R) -> Self {
+        assert!(per_tick > 0, "at least one change must be released per tick");
+
+        Throttle {
+            discover,
+            ticks,
+            per_tick,
+            remaining: per_tick,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Returns the number of changes that have been buffered.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+impl<D, R> Discover for Throttle<D, R>
+where
+    D: Discover,
+    R: Stream,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        while let Async::Ready(change) = self.discover.poll()? {
+            self.buffer.push_back(change);
+        }
+
+        loop {
+            if self.buffer.is_empty() {
+                return Ok(Async::NotReady);
+            }
+
+            if self.remaining > 0 {
+                self.remaining -= 1;
+                let change = self.buffer.pop_front().expect("buffer must not be empty");
+                return Ok(Async::Ready(change));
+            }
+
+            self.remaining = match self.ticks.poll() {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::Ready(Some(_))) | Err(_) => self.per_tick,
+                Ok(Async::Ready(None)) => ::std::usize::MAX,
+            };
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.buffer.is_empty() && self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use test_util::{drain, Fixed};
+
+    /// Yields a tick each time the shared count is incremented.
+    struct Ticks(Rc<Cell<usize>>);
+
+    impl Stream for Ticks {
+        type Item = ();
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<Option<()>, ()> {
+            match self.0.get() {
+                0 => Ok(Async::NotReady),
+                n => {
+                    self.0.set(n - 1);
+                    Ok(Async::Ready(Some(())))
+                }
+            }
+        }
+    }
+
+    fn keys(changes: Vec<Change<usize, ()>>) -> Vec<(bool, usize)> {
+        changes
+            .into_iter()
+            .map(|c| match c {
+                Change::Insert(k, ()) => (true, k),
+                Change::Remove(k) => (false, k),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn releases_burst_over_ticks() {
+        let mut burst = (0..5).map(|i| Change::Insert(i, ())).collect::<VecDeque<_>>();
+        burst.push_back(Change::Remove(0));
+        burst.push_back(Change::Remove(1));
+
+        let ticks = Rc::new(Cell::new(0));
+        let mut throttle = Throttle::with_ticks(Fixed::new(burst), 3, Ticks(ticks.clone()));
+
+        assert_eq!(keys(drain(&mut throttle)), vec![(true, 0), (true, 1), (true, 2)]);
+        assert_eq!(throttle.buffered(), 4);
+
+        ticks.set(1);
+        assert_eq!(keys(drain(&mut throttle)), vec![(true, 3), (true, 4), (false, 0)]);
+        assert_eq!(throttle.buffered(), 1);
+
+        ticks.set(1);
+        assert_eq!(keys(drain(&mut throttle)), vec![(false, 1)]);
+        assert_eq!(throttle.buffered(), 0);
+    }
+
+    #[test]
+    fn done_once_buffer_is_released() {
+        let burst = vec![Change::Insert(0, ()), Change::Insert(1, ())];
+        let ticks = Rc::new(Cell::new(0));
+        let mut throttle = Throttle::with_ticks(Fixed::new(burst), 1, Ticks(ticks.clone()));
+        throttle.discover.finish();
+
+        assert_eq!(keys(drain(&mut throttle)), vec![(true, 0)]);
+        assert!(!throttle.is_done());
+
+        ticks.set(1);
+        assert_eq!(keys(drain(&mut throttle)), vec![(true, 1)]);
+        assert!(throttle.is_done());
+    }
+}
@@ -0,0 +1,211 @@
+//! Detects discovery sources that stop making progress.
+
+use futures::stream::Fuse;
+use futures::{Async, Poll, Stream};
+use tokio_timer::{clock, Interval};
+
+use std::time::Duration;
+use std::{error, fmt};
+
+use {Change, Discover};
+
+/// Fails discovery when a `Discover` makes no progress within a window.
+///
+/// A window ends each time the `R`-typed watchdog stream produces an item. If
+/// the inner `Discover` yielded no change during a window, it is considered
+/// stalled and `Error::Stalled` is returned, so that a consumer such as a
+/// balancer is not left waiting on it indefinitely.
+///
+/// This is only suitable for sources that are expected to yield changes
+/// regularly, e.g. ones that periodically refresh their endpoints.
+///
+/// If the watchdog stream ends, the source is never considered stalled. If it
+/// fails, the failure is treated as the end of a window.
+pub struct Timeout<D, R = Interval> {
+    discover: D,
+    watchdog: Fuse<R>,
+    /// Whether `discover` has yielded a change in the current window.
+    progressed: bool,
+}
+
+/// Errors produced by `Timeout`.
+#[derive(Debug)]
+pub enum Error<T> {
+    /// The inner `Discover` failed.
+    Inner(T),
+
+    /// The inner `Discover` made no progress within a window.
+    Stalled,
+}
+
+// ===== impl Timeout =====
+
+impl<D: Discover> Timeout<D> {
+    /// Fails discovery if `discover` yields no change within any `window`.
+    pub fn new(discover: D, window: Duration) -> Self {
+        let watchdog = Interval::new(clock::now() + window, window);
+        Self::with_watchdog(discover, watchdog)
+    }
+}
+
+impl<D, R> Timeout<D, R>
+where
+    D: Discover,
+    R: Stream,
+{
+    /// Fails discovery if `discover` yields no change between items yielded
+    /// by `watchdog`.
+    pub fn with_watchdog(discover: D, watchdog: R) -> Self {
+        Timeout {
+            discover,
+            watchdog: watchdog.fuse(),
+            progressed: false,
+        }
+    }
+}
+
+impl<D, R> Discover for Timeout<D, R>
+where
+    D: Discover,
+    R: Stream,
+{
+    type Key = D::Key;
+    type Service = D::Service;
+    type Error = Error<D::Error>;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        if let Async::Ready(change) = self.discover.poll().map_err(Error::Inner)? {
+            self.progressed = true;
+            return Ok(Async::Ready(change));
+        }
+
+        // A source that has ended is not stalled.
+        if self.discover.is_done() {
+            return Ok(Async::NotReady);
+        }
+
+        loop {
+            match self.watchdog.poll() {
+                Ok(Async::Ready(Some(_))) | Err(_) => {
+                    if !self.progressed {
+                        return Err(Error::Stalled);
+                    }
+                    self.progressed = false;
+                }
+                Ok(Async::Ready(None)) | Ok(Async::NotReady) => return Ok(Async::NotReady),
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+// ===== impl Error =====
+
+impl<T> fmt::Display for Error<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Inner(ref why) => fmt::Display::fmt(why, f),
+            Error::Stalled => f.pad("discovery stalled"),
+        }
+    }
+}
+
+impl<T> error::Error for Error<T>
+where
+    T: error::Error,
+{
+    fn cause(&self) -> Option<&error::Error> {
+        if let Error::Inner(ref why) = *self {
+            Some(why)
+        } else {
+            None
+        }
+    }
+
+    fn description(&self) -> &str {
+        match *self {
+            Error::Inner(_) => "discovery failed",
+            Error::Stalled => "discovery stalled",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use test_util::Fixed;
+
+    /// Ends a window each time the shared count is incremented.
+    struct Watchdog(Rc<Cell<usize>>);
+
+    impl Stream for Watchdog {
+        type Item = ();
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<Option<()>, ()> {
+            match self.0.get() {
+                0 => Ok(Async::NotReady),
+                n => {
+                    self.0.set(n - 1);
+                    Ok(Async::Ready(Some(())))
+                }
+            }
+        }
+    }
+
+    fn is_stalled<T>(poll: Poll<T, Error<()>>) -> bool {
+        match poll {
+            Err(Error::Stalled) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn source_without_progress_stalls() {
+        let windows = Rc::new(Cell::new(0));
+        let mut discover = Timeout::with_watchdog(Fixed::<usize, ()>::new(vec![]), Watchdog(windows.clone()));
+
+        assert!(discover.poll().unwrap().is_not_ready());
+        assert!(discover.poll().unwrap().is_not_ready());
+
+        windows.set(1);
+        assert!(is_stalled(discover.poll()));
+    }
+
+    #[test]
+    fn progress_within_window_is_not_stalled() {
+        let windows = Rc::new(Cell::new(0));
+        let changes = vec![Change::Insert(0, ())];
+        let mut discover = Timeout::with_watchdog(Fixed::new(changes), Watchdog(windows.clone()));
+
+        assert!(discover.poll().unwrap().is_ready());
+        assert!(discover.poll().unwrap().is_not_ready());
+
+        windows.set(1);
+        assert!(discover.poll().unwrap().is_not_ready());
+
+        // No change was yielded in the second window.
+        windows.set(1);
+        assert!(is_stalled(discover.poll()));
+    }
+
+    #[test]
+    fn finished_source_is_done_and_never_stalls() {
+        let windows = Rc::new(Cell::new(0));
+        let mut discover = Timeout::with_watchdog(Fixed::<usize, ()>::new(vec![]), Watchdog(windows.clone()));
+        discover.discover.finish();
+        assert!(discover.is_done());
+
+        windows.set(2);
+        assert!(discover.poll().unwrap().is_not_ready());
+    }
+}
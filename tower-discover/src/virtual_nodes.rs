@@ -0,0 +1,140 @@
+use futures::{Async, Poll};
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use {Change, Discover};
+
+/// Maps each endpoint to several virtual nodes, e.g. for consistent hashing.
+///
+/// Each endpoint inserted by the inner `Discover` is yielded `replicas` times,
+/// keyed by a hash of its key and the replica's index, so that it occupies
+/// several points on a hash ring and keys are spread more evenly between
+/// endpoints. When the endpoint is removed, all of its virtual nodes are
+/// removed.
+pub struct VirtualNodes<D>
+where
+    D: Discover,
+{
+    discover: D,
+    replicas: usize,
+    endpoints: HashSet<D::Key>,
+    pending: VecDeque<Change<u64, D::Service>>,
+}
+
+/// Returns the key of the `replica`th virtual node of the endpoint `key`.
+pub fn node_key<K: Hash>(key: &K, replica: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    replica.hash(&mut hasher);
+    hasher.finish()
+}
+
+// ===== impl VirtualNodes =====
+
+impl<D> VirtualNodes<D>
+where
+    D: Discover,
+    D::Service: Clone,
+{
+    /// Yields `replicas` virtual nodes for each endpoint of `discover`.
+    ///
+    /// # Panics
+    ///
+    /// If `replicas` is zero.
+    pub fn new(discover: D, replicas: usize) -> Self {
+        assert!(replicas > 0, "each endpoint needs at least one virtual node");
+        VirtualNodes {
+            discover,
+            replicas,
+            endpoints: HashSet::new(),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<D> Discover for VirtualNodes<D>
+where
+    D: Discover,
+    D::Service: Clone,
+{
+    type Key = u64;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Ok(Async::Ready(change));
+            }
+
+            match try_ready!(self.discover.poll()) {
+                Change::Insert(key, svc) => {
+                    for replica in 0..self.replicas {
+                        let node = node_key(&key, replica);
+                        self.pending.push_back(Change::Insert(node, svc.clone()));
+                    }
+                    self.endpoints.insert(key);
+                }
+                Change::Remove(key) => {
+                    // Only endpoints that were inserted have nodes to remove.
+                    if self.endpoints.remove(&key) {
+                        for replica in 0..self.replicas {
+                            self.pending.push_back(Change::Remove(node_key(&key, replica)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.pending.is_empty() && self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::{drain, Fixed};
+
+    #[test]
+    fn yields_nodes_per_endpoint() {
+        let changes = vec![Change::Insert("a", "a"), Change::Insert("b", "b")];
+        let mut nodes = VirtualNodes::new(Fixed::new(changes), 3);
+
+        let mut inserted = HashSet::new();
+        for change in drain(&mut nodes) {
+            match change {
+                Change::Insert(node, svc) => {
+                    assert!((0..3).any(|i| node == node_key(&svc, i)));
+                    assert!(inserted.insert(node), "duplicate node key");
+                }
+                Change::Remove(_) => panic!("unexpected removal"),
+            }
+        }
+        assert_eq!(inserted.len(), 6);
+    }
+
+    #[test]
+    fn removal_cascades_to_nodes() {
+        let changes = vec![
+            Change::Insert("a", "a"),
+            Change::Insert("b", "b"),
+            Change::Remove("a"),
+            Change::Remove("c"),
+        ];
+        let mut nodes = VirtualNodes::new(Fixed::new(changes), 3);
+
+        let removed = drain(&mut nodes)
+            .into_iter()
+            .filter_map(|change| match change {
+                Change::Remove(node) => Some(node),
+                Change::Insert(..) => None,
+            })
+            .collect::<HashSet<_>>();
+        let expected = (0..3).map(|i| node_key(&"a", i)).collect::<HashSet<_>>();
+        assert_eq!(removed, expected);
+    }
+}
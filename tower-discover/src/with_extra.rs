@@ -0,0 +1,198 @@
+use futures::task::{self, Task};
+use futures::{Async, Poll};
+
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+
+use {Change, Discover};
+
+/// Adds synthetic services, e.g. a canary, to those from a `Discover`.
+///
+/// The synthetic services are yielded first, followed by changes from the
+/// wrapped `Discover`. Keys are namespaced by `Key`, so that a synthetic key
+/// never collides with a discovered one. Synthetic services may later be
+/// removed with `remove_extra`, or more added with `insert_extra`.
+pub struct WithExtra<D, E>
+where
+    D: Discover,
+{
+    discover: D,
+    /// Synthetic changes that have not yet been yielded.
+    extra: VecDeque<Change<Key<D::Key, E>, D::Service>>,
+    /// Synthetic keys that have been inserted and not yet removed.
+    inserted: HashSet<E>,
+    /// The task last left waiting on a change, if any.
+    task: Option<Task>,
+}
+
+/// Identifies a service yielded by `WithExtra`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key<K, E> {
+    /// A service from the wrapped `Discover`.
+    Discovered(K),
+    /// A synthetic service.
+    Extra(E),
+}
+
+// ===== impl WithExtra =====
+
+impl<D, E> WithExtra<D, E>
+where
+    D: Discover,
+    E: Hash + Eq + Clone,
+{
+    /// Yields each of `extra` before changes from `discover`.
+    pub fn new<I>(discover: D, extra: I) -> Self
+    where
+        I: IntoIterator<Item = (E, D::Service)>,
+    {
+        let mut with_extra = WithExtra {
+            discover,
+            extra: VecDeque::new(),
+            inserted: HashSet::new(),
+            task: None,
+        };
+        for (key, svc) in extra {
+            with_extra.insert_extra(key, svc);
+        }
+        with_extra
+    }
+
+    /// Adds a synthetic service, replacing any with the same key.
+    pub fn insert_extra(&mut self, key: E, svc: D::Service) {
+        self.inserted.insert(key.clone());
+        self.extra.push_back(Change::Insert(Key::Extra(key), svc));
+        self.notify();
+    }
+
+    /// Removes a synthetic service. Returns false if there is no such service.
+    pub fn remove_extra(&mut self, key: &E) -> bool {
+        if !self.inserted.remove(key) {
+            return false;
+        }
+        self.extra.push_back(Change::Remove(Key::Extra(key.clone())));
+        self.notify();
+        true
+    }
+
+    /// Wakes a task waiting on a change, so that it polls for the new extra
+    /// change.
+    fn notify(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.notify();
+        }
+    }
+}
+
+impl<D, E> Discover for WithExtra<D, E>
+where
+    D: Discover,
+    E: Hash + Eq,
+{
+    type Key = Key<D::Key, E>;
+    type Service = D::Service;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        if let Some(change) = self.extra.pop_front() {
+            return Ok(Async::Ready(change));
+        }
+
+        let change = match self.discover.poll()? {
+            Async::Ready(Change::Insert(key, svc)) => Change::Insert(Key::Discovered(key), svc),
+            Async::Ready(Change::Remove(key)) => Change::Remove(Key::Discovered(key)),
+            Async::NotReady => {
+                self.task = Some(task::current());
+                return Ok(Async::NotReady);
+            }
+        };
+        Ok(Async::Ready(change))
+    }
+
+    /// Returns true once the inner `Discover` is done and every extra change
+    /// has been yielded.
+    ///
+    /// A consumer may stop polling once this is done, so extra services
+    /// inserted or removed afterwards may never be observed.
+    fn is_done(&self) -> bool {
+        self.extra.is_empty() && self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::{self, Notify};
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use test_util::{drain, Fixed};
+
+    /// Records whether a waiting task has been notified.
+    struct Notified(AtomicBool);
+
+    impl Notify for Notified {
+        fn notify(&self, _: usize) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn synthetic_endpoint_alongside_discovered() {
+        let live = vec![Change::Insert(0, "live 0"), Change::Insert(1, "live 1")];
+        let mut d = WithExtra::new(Fixed::new(live), vec![("canary", "canary")]);
+
+        let inserted = drain(&mut d)
+            .into_iter()
+            .map(|c| match c {
+                Change::Insert(k, svc) => (k, svc),
+                Change::Remove(_) => panic!("unexpected removal"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inserted,
+            vec![
+                (Key::Extra("canary"), "canary"),
+                (Key::Discovered(0), "live 0"),
+                (Key::Discovered(1), "live 1"),
+            ]
+        );
+
+        assert!(d.remove_extra(&"canary"));
+        assert!(!d.remove_extra(&"canary"));
+        let changes = drain(&mut d);
+        assert_eq!(changes.len(), 1);
+        match changes[0] {
+            Change::Remove(Key::Extra("canary")) => {}
+            _ => panic!("expected removal of the canary"),
+        }
+    }
+
+    #[test]
+    fn done_once_extra_changes_are_yielded() {
+        let mut d = WithExtra::new(Fixed::new(vec![Change::Insert(0, "live 0")]), vec![("canary", "canary")]);
+        d.discover.finish();
+        assert!(!d.is_done());
+
+        assert_eq!(drain(&mut d).len(), 2);
+        assert!(d.is_done());
+    }
+
+    #[test]
+    fn waiting_task_is_notified_of_extra_changes() {
+        let mut d = WithExtra::new(Fixed::new(vec![]), vec![("canary", "canary")]);
+        let notified = Arc::new(Notified(AtomicBool::new(false)));
+        let poll = |d: &mut WithExtra<Fixed<usize, &'static str>, &'static str>| {
+            executor::with_notify(&notified, 0, || d.poll().unwrap().is_ready())
+        };
+
+        assert!(poll(&mut d));
+        assert!(!poll(&mut d));
+        assert!(!notified.0.load(Ordering::SeqCst));
+
+        assert!(d.remove_extra(&"canary"));
+        assert!(notified.0.load(Ordering::SeqCst));
+        assert!(poll(&mut d));
+    }
+}
@@ -1,7 +1,9 @@
 //! Conditionally dispatch requests to the inner service based on the result of
 //! a predicate.
 
+#[macro_use]
 extern crate futures;
+extern crate tokio_timer;
 extern crate tower_service;
 
 use futures::{Future, IntoFuture, Poll, Async};
@@ -9,14 +11,21 @@ use futures::task::AtomicTask;
 use tower_service::Service;
 
 use std::{fmt, mem};
+use std::hash::Hash;
 use std::sync::Arc;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
+use std::time::Duration;
+
+pub mod predicate;
+
+pub use predicate::{And, Cached, Tagged};
 
 #[derive(Debug)]
-pub struct Filter<T, U> {
+pub struct Filter<T, U, W = Unweighted> {
     inner: T,
     predicate: U,
+    weight: W,
     // Tracks the number of in-flight requests
     counts: Arc<Counts>,
 }
@@ -37,6 +46,8 @@ where
     check: T,
     service: S,
     counts: Arc<Counts>,
+    /// The capacity consumed by the request.
+    weight: usize,
 }
 
 /// Errors produced by `Filter`
@@ -57,9 +68,78 @@ pub trait Predicate<Request> {
     type Error;
     type Future: Future<Item = (), Error = Self::Error>;
 
+    /// Returns `Ready` when the predicate is able to accept a request.
+    ///
+    /// This is consulted by `Filter::poll_ready`, so that a predicate that can
+    /// cheaply tell it would reject the next request (e.g. an exhausted quota)
+    /// may apply backpressure before `call`. An error is surfaced as
+    /// `Error::Rejected`.
+    ///
+    /// By default, the predicate is always ready.
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
     fn check(&mut self, request: &Request) -> Self::Future;
+
+    /// Tags each rejection from this predicate with `tag`, e.g. a variant of
+    /// an enum of rejection reasons.
+    ///
+    /// This allows a composed filter to report which predicate rejected a
+    /// request.
+    fn tagged<F, E>(self, tag: F) -> Tagged<Self, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Error) -> E + Clone,
+    {
+        Tagged::new(self, tag)
+    }
+
+    /// Accepts a request only if both this predicate and `other` accept it.
+    ///
+    /// `other` only checks requests that this predicate has accepted, so a
+    /// rejection is reported by the first predicate to reject a request.
+    fn and<P>(self, other: P) -> And<Self, P>
+    where
+        Self: Sized,
+        P: Predicate<Request, Error = Self::Error> + Clone,
+        Request: Clone,
+    {
+        And::new(self, other)
+    }
+
+    /// Accepts requests with the same cache key as a request this predicate
+    /// accepted within `ttl`, without checking them again.
+    ///
+    /// `key` maps each request to its cache key, e.g. its auth token.
+    fn cached<F, K>(self, ttl: Duration, key: F) -> Cached<Self, F, K>
+    where
+        Self: Sized,
+        F: Fn(&Request) -> K,
+        K: Hash + Eq,
+    {
+        Cached::new(self, ttl, key)
+    }
+}
+
+/// Determines how much of a `Filter`'s capacity a request consumes.
+pub trait Weight<Request> {
+    /// Returns the capacity consumed by `request`.
+    fn weight(&self, request: &Request) -> usize;
+
+    /// Returns the least capacity that any request may consume.
+    ///
+    /// `Filter::poll_ready` is not ready until at least this much capacity is
+    /// available. By default, this is 1.
+    fn min_weight(&self) -> usize {
+        1
+    }
 }
 
+/// Weighs every request equally, so that each consumes 1 unit of capacity.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Unweighted;
+
 #[derive(Debug)]
 struct Counts {
     /// Filter::poll_ready task
@@ -67,6 +147,9 @@ struct Counts {
 
     /// Remaining capacity
     rem: AtomicUsize,
+
+    /// The least capacity that a request may consume
+    min_weight: usize,
 }
 
 #[derive(Debug)]
@@ -84,23 +167,42 @@ impl<T, U> Filter<T, U> {
     where
         T: Service<Request> + Clone,
         U: Predicate<Request>,
+    {
+        Filter::with_weight(inner, predicate, buffer, Unweighted)
+    }
+}
+
+impl<T, U, W> Filter<T, U, W> {
+    /// Creates a `Filter` in which each request consumes `weight` of the
+    /// `capacity`, rather than 1.
+    ///
+    /// The weight of a request is restored once the request is dispatched to
+    /// the inner service.
+    pub fn with_weight<Request>(inner: T, predicate: U, capacity: usize, weight: W) -> Self
+    where
+        T: Service<Request> + Clone,
+        U: Predicate<Request>,
+        W: Weight<Request>,
     {
         let counts = Counts {
             task: AtomicTask::new(),
-            rem: AtomicUsize::new(buffer),
+            rem: AtomicUsize::new(capacity),
+            min_weight: weight.min_weight(),
         };
 
         Filter {
             inner,
             predicate,
+            weight,
             counts: Arc::new(counts),
         }
     }
 }
 
-impl<T, U, Request> Service<Request> for Filter<T, U>
+impl<T, U, W, Request> Service<Request> for Filter<T, U, W>
 where T: Service<Request> + Clone,
       U: Predicate<Request>,
+      W: Weight<Request>,
 {
     type Response = T::Response;
     type Error = Error<U::Error, T::Error>;
@@ -113,24 +215,26 @@ where T: Service<Request> + Clone,
 
         // TODO: Handle catching upstream closing
 
-        if rem == 0 {
-            Ok(Async::NotReady)
-        } else {
-            Ok(().into())
+        if rem < self.counts.min_weight {
+            return Ok(Async::NotReady);
         }
+
+        self.predicate.poll_ready()
+            .map_err(Error::Rejected)
     }
 
     fn call(&mut self, request: Request) -> Self::Future {
         let rem = self.counts.rem.load(SeqCst);
+        let weight = self.weight.weight(&request);
 
-        if rem == 0 {
+        if rem < weight {
             return ResponseFuture {
                 inner: None,
             };
         }
 
         // Decrement
-        self.counts.rem.fetch_sub(1, SeqCst);
+        self.counts.rem.fetch_sub(weight, SeqCst);
 
         // Check the request
         let check = self.predicate.check(&request);
@@ -147,6 +251,7 @@ where T: Service<Request> + Clone,
                 check,
                 service,
                 counts,
+                weight,
             }),
         }
     }
@@ -166,6 +271,22 @@ impl<F, T, U> Predicate<T> for F
     }
 }
 
+// ===== impl Weight =====
+
+impl<F, T> Weight<T> for F
+    where F: Fn(&T) -> usize,
+{
+    fn weight(&self, request: &T) -> usize {
+        self(request)
+    }
+}
+
+impl<T> Weight<T> for Unweighted {
+    fn weight(&self, _: &T) -> usize {
+        1
+    }
+}
+
 // ===== impl ResponseFuture =====
 
 impl<T, S, Request> Future for ResponseFuture<T, S, Request>
@@ -203,7 +324,8 @@ where T: Future,
       S: Service<Request>,
 {
     fn inc_rem(&self) {
-        if 0 == self.counts.rem.fetch_add(1, SeqCst) {
+        let prev = self.counts.rem.fetch_add(self.weight, SeqCst);
+        if prev < self.counts.min_weight && self.weight > 0 {
             self.counts.task.notify();
         }
     }
@@ -0,0 +1,276 @@
+//! Combinators for composing `Predicate`s.
+
+use futures::future::MapErr;
+use futures::{Async, Future, Poll};
+use tokio_timer::clock;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use Predicate;
+
+/// Tags each rejection from a predicate, e.g. with a variant of a reason enum.
+///
+/// Created by `Predicate::tagged`.
+#[derive(Clone, Debug)]
+pub struct Tagged<P, F> {
+    predicate: P,
+    tag: F,
+}
+
+/// Accepts a request only if both predicates accept it.
+///
+/// The second predicate only checks a request once the first has accepted it.
+/// The request and the second predicate are cloned for this, as the check may
+/// complete after `check` returns.
+///
+/// Created by `Predicate::and`.
+#[derive(Clone, Debug)]
+pub struct And<A, B> {
+    a: A,
+    b: B,
+}
+
+/// Completes once both predicates of an `And` accept a request.
+pub struct AndFuture<A, B, Request>
+where
+    B: Predicate<Request>,
+{
+    state: AndState<A, B, Request>,
+}
+
+enum AndState<A, B, Request>
+where
+    B: Predicate<Request>,
+{
+    /// Waiting for `a` to accept the request, which `b` then checks. The
+    /// request and `b` are taken once `a` has accepted it.
+    First(A, Option<(B, Request)>),
+    /// Waiting for `b` to accept the request.
+    Second(B::Future),
+}
+
+/// Accepts requests like one the inner predicate recently accepted, without
+/// checking them again.
+///
+/// Each request is mapped to a cache key by `F`, e.g. its auth token. Once the
+/// inner predicate accepts a request, requests with the same key are accepted
+/// without consulting it until `ttl` elapses, as measured by the default clock.
+/// Rejections are not cached. Expired entries are evicted when their key is
+/// next checked, or when any new acceptance is cached, so keys that are never
+/// seen again do not accumulate.
+///
+/// Created by `Predicate::cached`.
+#[derive(Clone, Debug)]
+pub struct Cached<P, F, K>
+where
+    K: Hash + Eq,
+{
+    predicate: P,
+    key: F,
+    ttl: Duration,
+    /// When the acceptance of each key expires.
+    accepted: Arc<Mutex<HashMap<K, Instant>>>,
+}
+
+/// Completes once a request is accepted, from the cache or by the inner
+/// predicate.
+#[derive(Debug)]
+pub struct CachedFuture<T, K>
+where
+    K: Hash + Eq,
+{
+    /// The inner check and the key it will cache, or `None` on a cache hit.
+    check: Option<(T, K)>,
+    ttl: Duration,
+    accepted: Arc<Mutex<HashMap<K, Instant>>>,
+}
+
+// ===== impl Tagged =====
+
+impl<P, F> Tagged<P, F> {
+    pub fn new(predicate: P, tag: F) -> Self {
+        Tagged { predicate, tag }
+    }
+}
+
+impl<P, F, E, Request> Predicate<Request> for Tagged<P, F>
+where
+    P: Predicate<Request>,
+    F: Fn(P::Error) -> E + Clone,
+{
+    type Error = E;
+    type Future = MapErr<P::Future, F>;
+
+    fn poll_ready(&mut self) -> Poll<(), E> {
+        self.predicate.poll_ready().map_err(&self.tag)
+    }
+
+    fn check(&mut self, request: &Request) -> Self::Future {
+        self.predicate.check(request).map_err(self.tag.clone())
+    }
+}
+
+// ===== impl And =====
+
+impl<A, B> And<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        And { a, b }
+    }
+}
+
+impl<A, B, Request> Predicate<Request> for And<A, B>
+where
+    A: Predicate<Request>,
+    B: Predicate<Request, Error = A::Error> + Clone,
+    Request: Clone,
+{
+    type Error = A::Error;
+    type Future = AndFuture<A::Future, B, Request>;
+
+    fn poll_ready(&mut self) -> Poll<(), A::Error> {
+        let a = self.a.poll_ready()?;
+        let b = self.b.poll_ready()?;
+        if a.is_ready() && b.is_ready() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    /// Checks `request` against `a`, and then against `b` once `a` accepts it.
+    fn check(&mut self, request: &Request) -> Self::Future {
+        let second = (self.b.clone(), request.clone());
+        AndFuture {
+            state: AndState::First(self.a.check(request), Some(second)),
+        }
+    }
+}
+
+// ===== impl AndFuture =====
+
+impl<A, B, Request> Future for AndFuture<A, B, Request>
+where
+    A: Future<Item = ()>,
+    B: Predicate<Request, Error = A::Error>,
+{
+    type Item = ();
+    type Error = A::Error;
+
+    fn poll(&mut self) -> Poll<(), A::Error> {
+        loop {
+            let (mut b, request) = match self.state {
+                AndState::First(ref mut a, ref mut second) => {
+                    try_ready!(a.poll());
+                    second.take().expect("polled after complete")
+                }
+                AndState::Second(ref mut b) => return b.poll(),
+            };
+            self.state = AndState::Second(b.check(&request));
+        }
+    }
+}
+
+impl<A, B, Request> fmt::Debug for AndFuture<A, B, Request>
+where
+    A: fmt::Debug,
+    B: Predicate<Request>,
+    B::Future: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.state {
+            AndState::First(ref a, _) => f.debug_tuple("AndFuture::First").field(a).finish(),
+            AndState::Second(ref b) => f.debug_tuple("AndFuture::Second").field(b).finish(),
+        }
+    }
+}
+
+// ===== impl Cached =====
+
+impl<P, F, K> Cached<P, F, K>
+where
+    K: Hash + Eq,
+{
+    pub fn new(predicate: P, ttl: Duration, key: F) -> Self {
+        Cached {
+            predicate,
+            key,
+            ttl,
+            accepted: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the number of cached keys, including any that have expired but
+    /// have not yet been evicted.
+    pub fn cached_keys(&self) -> usize {
+        self.accepted.lock().expect("cache poisoned").len()
+    }
+}
+
+impl<P, F, K, Request> Predicate<Request> for Cached<P, F, K>
+where
+    P: Predicate<Request>,
+    F: Fn(&Request) -> K,
+    K: Hash + Eq,
+{
+    type Error = P::Error;
+    type Future = CachedFuture<P::Future, K>;
+
+    fn poll_ready(&mut self) -> Poll<(), P::Error> {
+        self.predicate.poll_ready()
+    }
+
+    fn check(&mut self, request: &Request) -> Self::Future {
+        let key = (self.key)(request);
+        let now = clock::now();
+        let hit = {
+            let mut accepted = self.accepted.lock().expect("cache poisoned");
+            let fresh = accepted.get(&key).map(|expires| *expires > now);
+            if fresh == Some(false) {
+                accepted.remove(&key);
+            }
+            fresh == Some(true)
+        };
+
+        let check = if hit {
+            None
+        } else {
+            Some((self.predicate.check(request), key))
+        };
+
+        CachedFuture {
+            check,
+            ttl: self.ttl,
+            accepted: self.accepted.clone(),
+        }
+    }
+}
+
+// ===== impl CachedFuture =====
+
+impl<T, K> Future for CachedFuture<T, K>
+where
+    T: Future<Item = ()>,
+    K: Hash + Eq,
+{
+    type Item = ();
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<(), T::Error> {
+        if let Some((ref mut check, _)) = self.check {
+            try_ready!(check.poll());
+        }
+
+        if let Some((_, key)) = self.check.take() {
+            let now = clock::now();
+            let mut accepted = self.accepted.lock().expect("cache poisoned");
+            accepted.retain(|_, expires| *expires > now);
+            accepted.insert(key, now + self.ttl);
+        }
+
+        Ok(Async::Ready(()))
+    }
+}
@@ -2,6 +2,7 @@ extern crate futures;
 extern crate tower_mock;
 extern crate tower_filter;
 extern crate tower_service;
+extern crate tower_timeout;
 extern crate tower_util;
 
 use futures::*;
@@ -9,6 +10,8 @@ use tower_filter::*;
 use tower_service::*;
 use tower_util::ServiceExt;
 
+use std::cell::Cell;
+use std::rc::Rc;
 use std::thread;
 use std::sync::mpsc;
 
@@ -123,6 +126,246 @@ fn saturate() {
     th2.join().unwrap();
 }
 
+#[test]
+fn predicate_backpressure() {
+    let remaining = Rc::new(Cell::new(0));
+    let (service, _handle) = Mock::new();
+    let mut service = Filter::new(service, Quota(remaining.clone()), 10);
+
+    with_task(|| {
+        assert!(service.poll_ready().unwrap().is_not_ready());
+    });
+
+    remaining.set(1);
+
+    with_task(|| {
+        assert!(service.poll_ready().unwrap().is_ready());
+    });
+}
+
+#[test]
+fn predicate_poll_ready_error() {
+    let remaining = Rc::new(Cell::new(::std::usize::MAX));
+    let (service, _handle) = Mock::new();
+    let mut service = Filter::new(service, Quota(remaining), 10);
+
+    with_task(|| {
+        match service.poll_ready() {
+            Err(Error::Rejected(QuotaError)) => {}
+            _ => panic!("expected rejection"),
+        }
+    });
+}
+
+#[test]
+fn weighted_capacity() {
+    let (service, _handle) = Mock::new();
+    let weight = |request: &String| request.len();
+    let mut service = Filter::with_weight(service, |_: &String| Ok::<_, ()>(()), 5, weight);
+
+    let is_ready = |service: &mut Filter<Mock, _, _>| {
+        with_task(|| service.poll_ready().unwrap().is_ready())
+    };
+    let no_capacity = |rsp: &mut ResponseFuture<_, Mock, String>| {
+        match with_task(|| rsp.poll()) {
+            Err(Error::NoCapacity) => true,
+            _ => false,
+        }
+    };
+
+    assert!(is_ready(&mut service));
+    let mut r1 = service.call("abc".into());
+
+    // Only 2 units remain, which is too few for a 4-unit request.
+    assert!(no_capacity(&mut service.call("abcd".into())));
+    let mut r2 = service.call("ab".into());
+    assert!(!is_ready(&mut service));
+
+    // Dispatching the first request restores its 3 units.
+    assert!(with_task(|| r1.poll()).unwrap().is_not_ready());
+    assert!(is_ready(&mut service));
+    assert!(no_capacity(&mut service.call("abcd".into())));
+
+    // Dispatching the second request restores its 2 units.
+    assert!(with_task(|| r2.poll()).unwrap().is_not_ready());
+    assert!(!no_capacity(&mut service.call("abcd".into())));
+}
+
+#[test]
+fn composed_rejection_reports_predicate() {
+    #[derive(Debug, PartialEq)]
+    enum Reason {
+        Empty,
+        TooLong(usize),
+        Quota(QuotaError),
+    }
+
+    let not_empty = |request: &String| if request.is_empty() { Err(()) } else { Ok(()) };
+    let short = |request: &String| if request.len() > 3 { Err(request.len()) } else { Ok(()) };
+    let remaining = Rc::new(Cell::new(10));
+    let predicate = not_empty.tagged(|()| Reason::Empty)
+        .and(short.tagged(Reason::TooLong))
+        .and(Quota(remaining).tagged(Reason::Quota));
+
+    let (service, _handle) = Mock::new();
+    let mut service = Filter::new(service, predicate, 10);
+
+    let mut rejection = |request: &str| {
+        match with_task(|| service.call(request.into()).poll()) {
+            Err(Error::Rejected(reason)) => Some(reason),
+            Ok(Async::NotReady) => None,
+            _ => panic!("unexpected result"),
+        }
+    };
+    assert_eq!(rejection(""), Some(Reason::Empty));
+    assert_eq!(rejection("abcde"), Some(Reason::TooLong(5)));
+    assert_eq!(rejection("abc"), None);
+}
+
+#[test]
+fn cached_acceptance_skips_predicate() {
+    use std::time::Duration;
+
+    let checks = Rc::new(Cell::new(0));
+    let counting = {
+        let checks = checks.clone();
+        move |_: &String| {
+            checks.set(checks.get() + 1);
+            Ok::<_, ()>(())
+        }
+    };
+    let mut predicate = counting.cached(Duration::from_secs(60), String::clone);
+
+    predicate.check(&"token-a".to_string()).wait().unwrap();
+    predicate.check(&"token-a".to_string()).wait().unwrap();
+    assert_eq!(checks.get(), 1);
+
+    predicate.check(&"token-b".to_string()).wait().unwrap();
+    assert_eq!(checks.get(), 2);
+}
+
+#[test]
+fn expired_acceptance_is_checked_again() {
+    use std::time::Duration;
+
+    let checks = Rc::new(Cell::new(0));
+    let counting = {
+        let checks = checks.clone();
+        move |_: &String| {
+            checks.set(checks.get() + 1);
+            Ok::<_, ()>(())
+        }
+    };
+    let mut predicate = counting.cached(Duration::from_secs(0), String::clone);
+
+    predicate.check(&"token-a".to_string()).wait().unwrap();
+    predicate.check(&"token-a".to_string()).wait().unwrap();
+    assert_eq!(checks.get(), 2);
+}
+
+#[test]
+fn cached_acceptance_expires_after_ttl() {
+    use std::time::Duration;
+    use tower_timeout::test_util::with_mock_clock;
+
+    with_mock_clock(|time| {
+        let checks = Rc::new(Cell::new(0));
+        let counting = {
+            let checks = checks.clone();
+            move |_: &String| {
+                checks.set(checks.get() + 1);
+                Ok::<_, ()>(())
+            }
+        };
+        let mut predicate = counting.cached(Duration::from_secs(60), String::clone);
+
+        predicate.check(&"token-a".to_string()).wait().unwrap();
+        *time.as_mut() += Duration::from_secs(30);
+        predicate.check(&"token-b".to_string()).wait().unwrap();
+        assert_eq!(checks.get(), 2);
+
+        *time.as_mut() += Duration::from_secs(29);
+        predicate.check(&"token-a".to_string()).wait().unwrap();
+        assert_eq!(checks.get(), 2);
+
+        // Each key expires on its own schedule.
+        *time.as_mut() += Duration::from_secs(1);
+        predicate.check(&"token-a".to_string()).wait().unwrap();
+        predicate.check(&"token-b".to_string()).wait().unwrap();
+        assert_eq!(checks.get(), 3);
+
+        // Once checked again, the acceptance is cached anew.
+        *time.as_mut() += Duration::from_secs(59);
+        predicate.check(&"token-a".to_string()).wait().unwrap();
+        assert_eq!(checks.get(), 3);
+    });
+}
+
+#[test]
+fn expired_keys_are_evicted_without_being_checked_again() {
+    use std::time::Duration;
+    use tower_timeout::test_util::with_mock_clock;
+
+    with_mock_clock(|time| {
+        let accept = |_: &String| Ok::<_, ()>(());
+        let mut predicate = accept.cached(Duration::from_secs(60), String::clone);
+
+        predicate.check(&"token-a".to_string()).wait().unwrap();
+        predicate.check(&"token-b".to_string()).wait().unwrap();
+        assert_eq!(predicate.cached_keys(), 2);
+
+        // Neither key is checked again, but caching another sweeps them.
+        *time.as_mut() += Duration::from_secs(60);
+        predicate.check(&"token-c".to_string()).wait().unwrap();
+        assert_eq!(predicate.cached_keys(), 1);
+    });
+}
+
+#[test]
+fn and_checks_second_only_after_first_accepts() {
+    let checks = Rc::new(Cell::new(0));
+    let not_empty = |request: &String| if request.is_empty() { Err(()) } else { Ok(()) };
+    let counting = {
+        let checks = checks.clone();
+        move |_: &String| {
+            checks.set(checks.get() + 1);
+            Ok::<_, ()>(())
+        }
+    };
+    let mut predicate = not_empty.and(counting);
+
+    assert!(predicate.check(&"".to_string()).wait().is_err());
+    assert_eq!(checks.get(), 0);
+
+    predicate.check(&"abc".to_string()).wait().unwrap();
+    assert_eq!(checks.get(), 1);
+}
+
+/// Admits a fixed number of requests. A quota of `usize::MAX` is invalid.
+#[derive(Clone)]
+struct Quota(Rc<Cell<usize>>);
+
+#[derive(Debug, PartialEq)]
+struct QuotaError;
+
+impl Predicate<String> for Quota {
+    type Error = QuotaError;
+    type Future = future::FutureResult<(), QuotaError>;
+
+    fn poll_ready(&mut self) -> Poll<(), QuotaError> {
+        match self.0.get() {
+            0 => Ok(Async::NotReady),
+            ::std::usize::MAX => Err(QuotaError),
+            _ => Ok(Async::Ready(())),
+        }
+    }
+
+    fn check(&mut self, _: &String) -> Self::Future {
+        self.0.set(self.0.get() - 1);
+        future::ok(())
+    }
+}
+
 type Mock = tower_mock::Mock<String, String, ()>;
 type Handle = tower_mock::Handle<String, String, ()>;
 
@@ -66,6 +66,12 @@ impl<T> InFlightLimit<T> {
         }
     }
 
+    /// Returns the number of requests in flight, including capacity reserved
+    /// by `poll_ready` for a request that has not yet been dispatched.
+    pub fn in_flight(&self) -> usize {
+        self.state.shared.curr.load(SeqCst)
+    }
+
     /// Get a reference to the inner service
     pub fn get_ref(&self) -> &T {
         &self.inner
@@ -0,0 +1,157 @@
+//! Delays between failed connection attempts.
+
+use rand::{self, rngs::SmallRng, FromEntropy, Rng, SeedableRng};
+
+use std::cmp;
+use std::time::Duration;
+
+/// Exponential backoff with jitter.
+///
+/// Each consecutive failure doubles the base delay, starting at `min` and
+/// capped at `max`. The delay actually used is chosen uniformly at random from
+/// `[base * (1 - jitter), base]`, so that many clients reconnecting to the
+/// same recovered backend do not retry in lockstep.
+#[derive(Debug)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    jitter: f64,
+    /// The number of consecutive failures since the last reset.
+    attempts: u32,
+    rng: SmallRng,
+}
+
+// ===== impl Backoff =====
+
+impl Backoff {
+    /// Creates a backoff that draws jitter from `rng`.
+    ///
+    /// `jitter` is the fraction of each delay that may be randomly shaved
+    /// off, and must be in `[0, 1]`. A `jitter` of `0` produces a purely
+    /// exponential backoff.
+    pub fn new(min: Duration, max: Duration, jitter: f64, rng: SmallRng) -> Self {
+        assert!(min <= max, "backoff minimum must not exceed its maximum");
+        assert!(jitter >= 0.0 && jitter <= 1.0, "jitter must be in [0, 1]");
+
+        Backoff {
+            min,
+            max,
+            jitter,
+            attempts: 0,
+            rng,
+        }
+    }
+
+    /// Creates a backoff seeded from the provided randomization source.
+    ///
+    /// This may be used to produce deterministic delays in tests.
+    pub fn with_rng<R: Rng>(
+        min: Duration,
+        max: Duration,
+        jitter: f64,
+        rng: &mut R,
+    ) -> Result<Self, rand::Error> {
+        let rng = SmallRng::from_rng(rng)?;
+        Ok(Self::new(min, max, jitter, rng))
+    }
+
+    /// Creates a backoff seeded from system entropy.
+    pub fn from_entropy(min: Duration, max: Duration, jitter: f64) -> Self {
+        Self::new(min, max, jitter, SmallRng::from_entropy())
+    }
+
+    /// Returns the delay to wait before the next attempt, and records a failure.
+    pub fn next_delay(&mut self) -> Duration {
+        let base = self.base();
+        self.attempts = self.attempts.saturating_add(1);
+
+        let scale = 1.0 - self.jitter * self.rng.gen::<f64>();
+        let nanos = (nanos(base) as f64 * scale) as u64;
+        Duration::new(nanos / NANOS_PER_SEC, (nanos % NANOS_PER_SEC) as u32)
+    }
+
+    /// Clears the record of failures, e.g. after a successful connection.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// The un-jittered delay for the current attempt.
+    fn base(&self) -> Duration {
+        // Past 2^31 the delay has certainly saturated at `max`.
+        let factor = 1u32 << cmp::min(self.attempts, 31);
+        match self.min.checked_mul(factor) {
+            Some(d) => cmp::min(d, self.max),
+            None => self.max,
+        }
+    }
+}
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+fn nanos(d: Duration) -> u64 {
+    d.as_secs()
+        .saturating_mul(NANOS_PER_SEC)
+        .saturating_add(d.subsec_nanos() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(jitter: f64) -> Backoff {
+        let rng = SmallRng::from_seed([7; 16]);
+        Backoff::new(Duration::from_millis(100), Duration::from_secs(2), jitter, rng)
+    }
+
+    #[test]
+    fn delays_within_jittered_bounds() {
+        let mut backoff = seeded(0.5);
+        let bases = [100, 200, 400, 800, 1600, 2000, 2000];
+
+        for base in bases.iter().map(|ms| Duration::from_millis(*ms)) {
+            let delay = backoff.next_delay();
+            assert!(delay <= base, "{:?} > {:?}", delay, base);
+            assert!(delay >= base / 2, "{:?} < {:?}", delay, base / 2);
+        }
+    }
+
+    #[test]
+    fn no_jitter() {
+        let mut backoff = seeded(0.0);
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(400));
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn same_seed_same_delays() {
+        let mut a = seeded(1.0);
+        let mut b = seeded(1.0);
+        for _ in 0..10 {
+            assert_eq!(a.next_delay(), b.next_delay());
+        }
+    }
+
+    #[test]
+    fn jitter_spreads_clients() {
+        let mut a = Backoff::new(
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            1.0,
+            SmallRng::from_seed([1; 16]),
+        );
+        let mut b = Backoff::new(
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            1.0,
+            SmallRng::from_seed([2; 16]),
+        );
+        assert_ne!(
+            (0..10).map(|_| a.next_delay()).collect::<Vec<_>>(),
+            (0..10).map(|_| b.next_delay()).collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,28 +1,57 @@
 extern crate futures;
 #[macro_use]
 extern crate log;
+extern crate rand;
+extern crate tokio_timer;
 extern crate tower_service;
 extern crate tower_util;
 
-use futures::{Future, Async, Poll};
+use futures::{future, Future, Async, Poll};
+use tokio_timer::{clock, timer, Delay};
 use tower_service::Service;
-use tower_util::MakeService;
+use tower_util::{MakeService, ServiceExt};
+use tower_util::ext::ReadyOneshot;
 
 use std::{error, fmt, marker::PhantomData};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-pub struct Reconnect<M, Target>
+pub mod backoff;
+
+pub use backoff::Backoff;
+
+pub struct Reconnect<M, Target, W = NoWarmup>
 where
     M: Service<Target>,
+    W: Warmup<M::Response>,
 {
     mk_service: M,
-    state: State<M::Future, M::Response>,
+    state: State<M::Future, M::Response, W::Future>,
     target: Target,
+    backoff: Option<Backoff>,
+    /// Drives backoff delays, if not the default timer.
+    timer: Option<timer::Handle>,
+    warmup: W,
+    counters: Counters,
+}
+
+/// A snapshot of the connection attempts made by a `Reconnect`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReconnectStats {
+    /// The number of connection attempts that have been started.
+    pub attempts: usize,
+    /// The number of attempts that established a connection, and warmed it up
+    /// if a warmup is configured.
+    pub successes: usize,
+    /// The number of attempts that failed to connect or to warm up.
+    pub failures: usize,
 }
 
 #[derive(Debug)]
 pub enum Error<T, U> {
     Service(T),
     Connect(U),
+    /// The warmup request issued after connecting failed.
+    Warmup,
     NotReady,
 }
 
@@ -31,11 +60,53 @@ pub struct ResponseFuture<F, E> {
     _connect_error_marker: PhantomData<fn() -> E>,
 }
 
+/// Prepares a newly established connection before it serves requests.
+///
+/// See `Reconnect::with_warmup`.
+pub trait Warmup<S> {
+    /// Yields the connection once it has been warmed up.
+    type Future: Future<Item = S, Error = ()>;
+
+    /// Starts warming up `service`, or returns it if it needs no warmup.
+    fn warm_up(&self, service: S) -> Result<Self::Future, S>;
+}
+
+/// Serves new connections without warming them up.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoWarmup;
+
+/// Warms up each new connection by dispatching a request to it.
+///
+/// This is created by `Reconnect::with_warmup`.
+pub struct WarmupRequest<F, Request> {
+    make_request: F,
+    _p: PhantomData<fn() -> Request>,
+}
+
+/// A pending warmup request, yielding the connection once it succeeds.
+pub struct Warming<S, Request>
+where
+    S: Service<Request>,
+{
+    inner: ReadyOneshot<S, Request>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    attempts: AtomicUsize,
+    successes: AtomicUsize,
+    failures: AtomicUsize,
+}
+
 #[derive(Debug)]
-enum State<F, S> {
+enum State<F, S, W> {
     Idle,
     Connecting(F),
+    /// Waiting for the warmup to complete.
+    WarmingUp(W),
     Connected(S),
+    /// Waiting before reconnecting after a failed attempt.
+    Backoff(Delay),
 }
 
 // ===== impl Reconnect =====
@@ -50,14 +121,108 @@ where
             mk_service,
             state: State::Idle,
             target,
+            backoff: None,
+            timer: None,
+            warmup: NoWarmup,
+            counters: Counters::default(),
+        }
+    }
+
+    /// Creates a `Reconnect` that waits between failed connection attempts.
+    ///
+    /// After each failure, `backoff` determines how long to wait before the next
+    /// attempt. The backoff is reset once a connection is established.
+    pub fn with_backoff(mk_service: M, target: Target, backoff: Backoff) -> Self {
+        Reconnect {
+            mk_service,
+            state: State::Idle,
+            target,
+            backoff: Some(backoff),
+            timer: None,
+            warmup: NoWarmup,
+            counters: Counters::default(),
+        }
+    }
+
+    /// Issues a warmup request on each new connection before serving.
+    ///
+    /// Once a connection is established, the request produced by
+    /// `make_warmup_request` is dispatched to it, and the `Reconnect` only
+    /// becomes ready once that request succeeds. If the warmup request fails,
+    /// the connection is dropped, `Error::Warmup` is returned, and a new
+    /// connection is established on the next call to `poll_ready`.
+    pub fn with_warmup<F, Request>(
+        self,
+        make_warmup_request: F,
+    ) -> Reconnect<M, Target, WarmupRequest<F, Request>>
+    where
+        F: Fn() -> Request,
+        M::Response: Service<Request>,
+    {
+        let state = match self.state {
+            State::Idle => State::Idle,
+            State::Connecting(f) => State::Connecting(f),
+            State::WarmingUp(_) => unreachable!("connections are not warmed up without a warmup"),
+            State::Connected(service) => State::Connected(service),
+            State::Backoff(delay) => State::Backoff(delay),
+        };
+
+        Reconnect {
+            mk_service: self.mk_service,
+            state,
+            target: self.target,
+            backoff: self.backoff,
+            timer: self.timer,
+            warmup: WarmupRequest {
+                make_request: make_warmup_request,
+                _p: PhantomData,
+            },
+            counters: self.counters,
+        }
+    }
+}
+
+impl<M, Target, W> Reconnect<M, Target, W>
+where
+    M: Service<Target>,
+    W: Warmup<M::Response>,
+{
+    /// Uses `timer` to drive backoff delays, rather than the default timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        Reconnect {
+            timer: Some(timer),
+            ..self
+        }
+    }
+
+    /// Replaces the `MakeService` used to establish connections.
+    ///
+    /// The replacement takes effect on the next connection attempt. An
+    /// established connection continues to serve requests until it fails, and
+    /// an attempt that is already in progress is allowed to complete.
+    pub fn set_new_service(&mut self, mk_service: M) {
+        self.mk_service = mk_service;
+    }
+
+    /// Returns the number of connection attempts, successes, and failures
+    /// since this `Reconnect` was created.
+    ///
+    /// Attempts that are still in progress, including those that are warming
+    /// up, are counted as neither successes nor failures.
+    pub fn stats(&self) -> ReconnectStats {
+        ReconnectStats {
+            attempts: self.counters.attempts.load(Ordering::Relaxed),
+            successes: self.counters.successes.load(Ordering::Relaxed),
+            failures: self.counters.failures.load(Ordering::Relaxed),
         }
     }
 }
 
-impl<M, Target, S, Request> Service<Request> for Reconnect<M, Target>
+impl<M, Target, W, S, Request> Service<Request> for Reconnect<M, Target, W>
 where
     M: Service<Target, Response=S>,
     S: Service<Request>,
+    W: Warmup<S>,
     Target: Clone,
 {
     type Response = S::Response;
@@ -85,6 +250,7 @@ where
                     }
 
                     let fut = self.mk_service.make_service(self.target.clone());
+                    self.counters.attempts.fetch_add(1, Ordering::Relaxed);
                     self.state = State::Connecting(fut);
                     continue;
                 }
@@ -92,7 +258,17 @@ where
                     trace!("poll_ready; connecting");
                     match f.poll() {
                         Ok(Async::Ready(service)) => {
-                            state = State::Connected(service);
+                            state = match self.warmup.warm_up(service) {
+                                Ok(warming) => {
+                                    trace!("poll_ready; warming up");
+                                    State::WarmingUp(warming)
+                                }
+                                Err(service) => {
+                                    self.counters.successes.fetch_add(1, Ordering::Relaxed);
+                                    reset(&mut self.backoff);
+                                    State::Connected(service)
+                                }
+                            };
                         }
                         Ok(Async::NotReady) => {
                             trace!("poll_ready; not ready");
@@ -100,12 +276,44 @@ where
                         }
                         Err(e) => {
                             trace!("poll_ready; error");
-                            state = State::Idle;
+                            self.counters.failures.fetch_add(1, Ordering::Relaxed);
+                            state = after_failure(&mut self.backoff, &self.timer);
                             ret = Err(Error::Connect(e));
                             break;
                         }
                     }
                 }
+                State::WarmingUp(ref mut warming) => {
+                    trace!("poll_ready; warming up");
+                    match warming.poll() {
+                        Ok(Async::Ready(service)) => {
+                            self.counters.successes.fetch_add(1, Ordering::Relaxed);
+                            reset(&mut self.backoff);
+                            state = State::Connected(service);
+                        }
+                        Ok(Async::NotReady) => {
+                            trace!("poll_ready; warmup not ready");
+                            return Ok(Async::NotReady);
+                        }
+                        Err(()) => {
+                            trace!("poll_ready; warmup failed");
+                            self.counters.failures.fetch_add(1, Ordering::Relaxed);
+                            state = after_failure(&mut self.backoff, &self.timer);
+                            ret = Err(Error::Warmup);
+                            break;
+                        }
+                    }
+                }
+                State::Backoff(ref mut delay) => {
+                    trace!("poll_ready; backoff");
+                    match delay.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        // A timer error is treated as an elapsed delay.
+                        Ok(Async::Ready(())) | Err(_) => {
+                            state = State::Idle;
+                        }
+                    }
+                }
                 State::Connected(ref mut inner) => {
                     trace!("poll_ready; connected");
                     match inner.poll_ready() {
@@ -143,22 +351,113 @@ where
     }
 }
 
-impl<M, Target> fmt::Debug for Reconnect<M, Target>
+impl<M, Target, W> fmt::Debug for Reconnect<M, Target, W>
 where
     M: Service<Target> + fmt::Debug,
     M::Future: fmt::Debug,
     M::Response: fmt::Debug,
     Target: fmt::Debug,
+    W: Warmup<M::Response> + fmt::Debug,
+    W::Future: fmt::Debug,
 {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("Reconnect")
             .field("mk_service", &self.mk_service)
             .field("state", &self.state)
             .field("target", &self.target)
+            .field("backoff", &self.backoff)
+            .field("timer", &self.timer)
+            .field("warmup", &self.warmup)
+            .field("stats", &self.stats())
             .finish()
     }
 }
 
+/// Returns the state to enter after a failed connection or warmup.
+fn after_failure<F, S, W>(
+    backoff: &mut Option<Backoff>,
+    timer: &Option<timer::Handle>,
+) -> State<F, S, W> {
+    match *backoff {
+        Some(ref mut backoff) => {
+            let delay = backoff.next_delay();
+            trace!("poll_ready; backing off for {:?}", delay);
+            let deadline = clock::now() + delay;
+            State::Backoff(match *timer {
+                Some(ref timer) => timer.delay(deadline),
+                None => Delay::new(deadline),
+            })
+        }
+        None => State::Idle,
+    }
+}
+
+fn reset(backoff: &mut Option<Backoff>) {
+    if let Some(ref mut backoff) = *backoff {
+        backoff.reset();
+    }
+}
+
+// ===== impl NoWarmup =====
+
+impl<S> Warmup<S> for NoWarmup {
+    type Future = future::Empty<S, ()>;
+
+    fn warm_up(&self, service: S) -> Result<Self::Future, S> {
+        Err(service)
+    }
+}
+
+// ===== impl WarmupRequest =====
+
+impl<F, Request, S> Warmup<S> for WarmupRequest<F, Request>
+where
+    F: Fn() -> Request,
+    S: Service<Request>,
+{
+    type Future = Warming<S, Request>;
+
+    fn warm_up(&self, service: S) -> Result<Self::Future, S> {
+        let request = (self.make_request)();
+        Ok(Warming {
+            inner: service.ready_oneshot(request),
+        })
+    }
+}
+
+impl<F, Request> fmt::Debug for WarmupRequest<F, Request> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("WarmupRequest").finish()
+    }
+}
+
+// ===== impl Warming =====
+
+impl<S, Request> Future for Warming<S, Request>
+where
+    S: Service<Request>,
+{
+    type Item = S;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<S, ()> {
+        match self.inner.poll() {
+            Ok(Async::Ready((_, service))) => Ok(Async::Ready(service)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => Err(()),
+        }
+    }
+}
+
+impl<S, Request> fmt::Debug for Warming<S, Request>
+where
+    S: Service<Request>,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Warming").finish()
+    }
+}
+
 // ===== impl ResponseFuture =====
 
 impl<F, E> ResponseFuture<F, E> {
@@ -199,6 +498,7 @@ where
         match *self {
             Error::Service(ref why) => fmt::Display::fmt(why, f),
             Error::Connect(ref why) => write!(f, "connection failed: {}", why),
+            Error::Warmup => f.pad("warmup request failed"),
             Error::NotReady => f.pad("not ready"),
         }
     }
@@ -213,7 +513,7 @@ where
         match *self {
             Error::Service(ref why) => Some(why),
             Error::Connect(ref why) => Some(why),
-            Error::NotReady => None,
+            Error::Warmup | Error::NotReady => None,
         }
     }
 
@@ -221,7 +521,311 @@ where
         match *self {
             Error::Service(_) => "inner service error",
             Error::Connect(_) => "connection failed",
+            Error::Warmup => "warmup request failed",
             Error::NotReady => "not ready",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate tower_timeout;
+
+    use futures::future::{self, FutureResult};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use self::tower_timeout::test_util::{with_mock_clock, MockPark};
+    use super::*;
+
+    struct Svc;
+
+    impl Service<()> for Svc {
+        type Response = ();
+        type Error = ();
+        type Future = FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(())
+        }
+    }
+
+    /// Fails to connect a fixed number of times before succeeding.
+    struct Flaky {
+        failures: usize,
+    }
+
+    impl Service<()> for Flaky {
+        type Response = Svc;
+        type Error = &'static str;
+        type Future = FutureResult<Svc, &'static str>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            if self.failures == 0 {
+                return future::ok(Svc);
+            }
+            self.failures -= 1;
+            future::err("connection refused")
+        }
+    }
+
+    #[test]
+    fn counts_attempts_successes_and_failures() {
+        let mut reconnect = Reconnect::new(Flaky { failures: 2 }, ());
+        assert_eq!(reconnect.stats(), ReconnectStats::default());
+
+        for failures in 1..3 {
+            match Service::<()>::poll_ready(&mut reconnect) {
+                Err(Error::Connect("connection refused")) => {}
+                _ => panic!("expected connect error"),
+            }
+            let stats = ReconnectStats {
+                attempts: failures,
+                successes: 0,
+                failures,
+            };
+            assert_eq!(reconnect.stats(), stats);
+        }
+
+        assert!(Service::<()>::poll_ready(&mut reconnect).unwrap().is_ready());
+        let stats = ReconnectStats {
+            attempts: 3,
+            successes: 1,
+            failures: 2,
+        };
+        assert_eq!(reconnect.stats(), stats);
+
+        // Once connected, no further attempts are made.
+        assert!(Service::<()>::poll_ready(&mut reconnect).unwrap().is_ready());
+        assert_eq!(reconnect.stats(), stats);
+    }
+
+    #[test]
+    fn backs_off_between_failed_attempts() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let backoff = Backoff::from_entropy(Duration::from_millis(100), Duration::from_secs(1), 0.0);
+            let mut reconnect = Reconnect::with_backoff(Flaky { failures: 2 }, (), backoff)
+                .with_timer(timer.handle());
+            let poll_ready = |reconnect: &mut Reconnect<Flaky, ()>| {
+                future::lazy(|| Ok::<_, ()>(Service::<()>::poll_ready(reconnect)))
+                    .wait()
+                    .unwrap()
+            };
+
+            match poll_ready(&mut reconnect) {
+                Err(Error::Connect("connection refused")) => {}
+                _ => panic!("expected connect error"),
+            }
+            assert!(poll_ready(&mut reconnect).unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(99);
+            timer.turn(None).unwrap();
+            assert!(poll_ready(&mut reconnect).unwrap().is_not_ready());
+
+            // The second attempt fails, doubling the delay.
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            match poll_ready(&mut reconnect) {
+                Err(Error::Connect("connection refused")) => {}
+                _ => panic!("expected connect error"),
+            }
+
+            *time.as_mut() += Duration::from_millis(199);
+            timer.turn(None).unwrap();
+            assert!(poll_ready(&mut reconnect).unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            assert!(poll_ready(&mut reconnect).unwrap().is_ready());
+            assert_eq!(reconnect.stats().attempts, 3);
+        });
+    }
+
+    /// Responds with its name, and fails once it is marked broken.
+    struct Conn(&'static str, Rc<Cell<bool>>);
+
+    impl Service<()> for Conn {
+        type Response = &'static str;
+        type Error = ();
+        type Future = FutureResult<&'static str, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            if self.1.get() {
+                return Err(());
+            }
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(self.0)
+        }
+    }
+
+    /// Connects to a named target.
+    struct Dialer(&'static str, Rc<Cell<bool>>);
+
+    impl Service<()> for Dialer {
+        type Response = Conn;
+        type Error = ();
+        type Future = FutureResult<Conn, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(Conn(self.0, self.1.clone()))
+        }
+    }
+
+    #[test]
+    fn swapped_new_service_used_on_reconnect() {
+        let blue = Rc::new(Cell::new(false));
+        let mut reconnect = Reconnect::new(Dialer("blue", blue.clone()), ());
+        assert!(Service::<()>::poll_ready(&mut reconnect).unwrap().is_ready());
+        assert_eq!(reconnect.call(()).wait().unwrap(), "blue");
+
+        // The established connection keeps serving after the swap.
+        reconnect.set_new_service(Dialer("green", Rc::new(Cell::new(false))));
+        assert!(Service::<()>::poll_ready(&mut reconnect).unwrap().is_ready());
+        assert_eq!(reconnect.call(()).wait().unwrap(), "blue");
+        assert_eq!(reconnect.stats().attempts, 1);
+
+        // Once it fails, the replacement is used to reconnect.
+        blue.set(true);
+        assert!(Service::<()>::poll_ready(&mut reconnect).unwrap().is_ready());
+        assert_eq!(reconnect.call(()).wait().unwrap(), "green");
+        assert_eq!(reconnect.stats().attempts, 2);
+    }
+
+    const PENDING: usize = 0;
+    const SUCCEEDED: usize = 1;
+    const FAILED: usize = 2;
+
+    /// Responds to "warmup" according to a shared outcome, and to any other
+    /// request immediately.
+    struct Session(Arc<AtomicUsize>);
+
+    impl Service<&'static str> for Session {
+        type Response = &'static str;
+        type Error = ();
+        type Future = Response;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, request: &'static str) -> Self::Future {
+            if request == "warmup" {
+                return Response(self.0.clone());
+            }
+            Response(Arc::new(AtomicUsize::new(SUCCEEDED)))
+        }
+    }
+
+    struct Response(Arc<AtomicUsize>);
+
+    impl Future for Response {
+        type Item = &'static str;
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<&'static str, ()> {
+            match self.0.load(Ordering::SeqCst) {
+                PENDING => Ok(Async::NotReady),
+                SUCCEEDED => Ok(Async::Ready("ok")),
+                _ => Err(()),
+            }
+        }
+    }
+
+    struct Connector(Arc<AtomicUsize>);
+
+    impl Service<()> for Connector {
+        type Response = Session;
+        type Error = ();
+        type Future = FutureResult<Session, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(Session(self.0.clone()))
+        }
+    }
+
+    #[test]
+    fn warmup_gates_readiness() {
+        let warmup = Arc::new(AtomicUsize::new(PENDING));
+        // The warmup need not be `Send`.
+        let issued = Rc::new(Cell::new(0));
+        let counter = issued.clone();
+        let mut reconnect = Reconnect::new(Connector(warmup.clone()), ())
+            .with_warmup(move || {
+                counter.set(counter.get() + 1);
+                "warmup"
+            });
+
+        let ready = Service::<&'static str>::poll_ready(&mut reconnect).unwrap();
+        assert!(ready.is_not_ready());
+        assert_eq!(issued.get(), 1);
+        // The connection does not count as established until it is warm.
+        let stats = ReconnectStats {
+            attempts: 1,
+            successes: 0,
+            failures: 0,
+        };
+        assert_eq!(reconnect.stats(), stats);
+
+        warmup.store(SUCCEEDED, Ordering::SeqCst);
+        let ready = Service::<&'static str>::poll_ready(&mut reconnect).unwrap();
+        assert!(ready.is_ready());
+        assert_eq!(reconnect.call("ping").wait().unwrap(), "ok");
+        let stats = ReconnectStats {
+            attempts: 1,
+            successes: 1,
+            failures: 0,
+        };
+        assert_eq!(reconnect.stats(), stats);
+        assert_eq!(issued.get(), 1);
+    }
+
+    #[test]
+    fn failed_warmup_reconnects() {
+        let warmup = Arc::new(AtomicUsize::new(FAILED));
+        let mut reconnect = Reconnect::new(Connector(warmup.clone()), ())
+            .with_warmup(|| "warmup");
+
+        match Service::<&'static str>::poll_ready(&mut reconnect) {
+            Err(Error::Warmup) => {}
+            _ => panic!("expected warmup error"),
+        }
+        let stats = ReconnectStats {
+            attempts: 1,
+            successes: 0,
+            failures: 1,
+        };
+        assert_eq!(reconnect.stats(), stats);
+
+        warmup.store(SUCCEEDED, Ordering::SeqCst);
+        let ready = Service::<&'static str>::poll_ready(&mut reconnect).unwrap();
+        assert!(ready.is_ready());
+        let stats = ReconnectStats {
+            attempts: 2,
+            successes: 1,
+            failures: 1,
+        };
+        assert_eq!(reconnect.stats(), stats);
+    }
+}
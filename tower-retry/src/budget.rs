@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{cmp, fmt, isize};
 use std::sync::{Mutex, atomic::{AtomicIsize, Ordering}};
 use std::time::{Duration, Instant};
 
@@ -93,7 +93,17 @@ impl Budget {
     }
 
     pub fn withdraw(&self) -> Result<(), Overdrawn> {
-        if self.bucket.try_get(self.withdraw_amount) {
+        self.withdraw_cost(1)
+    }
+
+    /// Withdraws `cost` retries at once, e.g. for an expensive request.
+    ///
+    /// Nothing is withdrawn unless the whole cost is affordable.
+    pub fn withdraw_cost(&self, cost: u32) -> Result<(), Overdrawn> {
+        // `cost` may not fit in an `isize` on 32-bit targets.
+        let cost = cmp::min(cost as u64, isize::MAX as u64) as isize;
+        let amount = self.withdraw_amount.saturating_mul(cost);
+        if self.bucket.try_get(amount) {
             Ok(())
         } else {
             Err(Overdrawn {
@@ -240,6 +250,33 @@ mod tests {
         });
     }
 
+    #[test]
+    fn cost() {
+        let time = MockNow(Arc::new(Mutex::new(Instant::now())));
+        let clock = clock::Clock::new_with_now(time.clone());
+        clock::with_default(&clock, &mut enter().unwrap(), |_| {
+            let bgt = Budget::new(Duration::from_secs(1), 5, 1.0);
+            bgt.withdraw_cost(3).unwrap();
+            bgt.withdraw_cost(3).unwrap_err();
+            bgt.withdraw_cost(2).unwrap();
+
+            bgt.withdraw().unwrap_err();
+        });
+    }
+
+    #[test]
+    fn max_cost_is_never_affordable() {
+        let time = MockNow(Arc::new(Mutex::new(Instant::now())));
+        let clock = clock::Clock::new_with_now(time.clone());
+        clock::with_default(&clock, &mut enter().unwrap(), |_| {
+            let bgt = Budget::new(Duration::from_secs(1), 5, 1.0);
+            bgt.withdraw_cost(::std::u32::MAX).unwrap_err();
+
+            // Nothing was withdrawn.
+            bgt.withdraw_cost(5).unwrap();
+        });
+    }
+
     #[test]
     fn reserve() {
         let time = MockNow(Arc::new(Mutex::new(Instant::now())));
@@ -0,0 +1,233 @@
+//! A circuit that disables retries during a widespread outage.
+//!
+//! Under a widespread outage, retries only add load. A `Circuit` tracks the
+//! rate of failures across all requests that share it, and a `WithCircuit`
+//! policy stops retrying while that rate exceeds a threshold.
+
+use futures::{Async, Future, Poll};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use {Outcome, Policy};
+
+/// Tracks the failure rate over the most recent results, opening once it
+/// exceeds a threshold.
+///
+/// The circuit is shared by every request whose policy holds it, so that a
+/// failure rate is observed across requests rather than within one.
+pub struct Circuit {
+    /// The failure rate above which the circuit opens.
+    threshold: f64,
+    /// The number of recent results over which the failure rate is measured.
+    window: usize,
+    /// Whether each recent result was a failure, oldest first.
+    results: Mutex<VecDeque<bool>>,
+}
+
+/// Wraps a `Policy`, giving up on failed requests while a `Circuit` is open.
+///
+/// Every result passed to the policy, whether a response or an error, is
+/// recorded in the circuit. Requests that cannot be cloned are never passed to
+/// the policy, so their results are not recorded.
+#[derive(Clone, Debug)]
+pub struct WithCircuit<P> {
+    policy: P,
+    circuit: Arc<Circuit>,
+}
+
+/// Yields a `WithCircuit` policy once the inner policy's future completes.
+#[derive(Debug)]
+pub struct WithCircuitFuture<T> {
+    inner: T,
+    circuit: Option<Arc<Circuit>>,
+}
+
+// ===== impl Circuit =====
+
+impl Circuit {
+    /// Opens once more than `threshold` of the last `window` results are
+    /// failures, and closes once the failure rate recovers.
+    ///
+    /// The circuit does not open until `window` results have been recorded.
+    pub fn new(threshold: f64, window: usize) -> Self {
+        assert!(threshold >= 0.0 && threshold <= 1.0);
+        assert!(window > 0);
+
+        Circuit {
+            threshold,
+            window,
+            results: Mutex::new(VecDeque::with_capacity(window)),
+        }
+    }
+
+    /// Records the result of an attempt.
+    pub fn record(&self, failed: bool) {
+        let mut results = self.results.lock().expect("circuit lock");
+        if results.len() == self.window {
+            results.pop_front();
+        }
+        results.push_back(failed);
+    }
+
+    /// Returns the fraction of recent results that were failures.
+    pub fn failure_rate(&self) -> f64 {
+        let results = self.results.lock().expect("circuit lock");
+        Self::rate(&results)
+    }
+
+    /// Returns whether retries are currently disabled.
+    pub fn is_open(&self) -> bool {
+        let results = self.results.lock().expect("circuit lock");
+        results.len() == self.window && Self::rate(&results) > self.threshold
+    }
+
+    fn rate(results: &VecDeque<bool>) -> f64 {
+        if results.is_empty() {
+            return 0.0;
+        }
+        let failures = results.iter().filter(|&&failed| failed).count();
+        failures as f64 / results.len() as f64
+    }
+}
+
+impl fmt::Debug for Circuit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Circuit")
+            .field("threshold", &self.threshold)
+            .field("window", &self.window)
+            .field("failure_rate", &self.failure_rate())
+            .finish()
+    }
+}
+
+// ===== impl WithCircuit =====
+
+impl<P> WithCircuit<P> {
+    pub fn new(policy: P, circuit: Arc<Circuit>) -> Self {
+        WithCircuit { policy, circuit }
+    }
+
+    /// Get a reference to the inner policy
+    pub fn get_ref(&self) -> &P {
+        &self.policy
+    }
+}
+
+impl<P, Req, Res, E> Policy<Req, Res, E> for WithCircuit<P>
+where
+    P: Policy<Req, Res, E>,
+{
+    type Future = WithCircuitFuture<P::Future>;
+
+    fn retry(&self, req: &Req, result: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, result).ok()
+    }
+
+    fn retry_or_give_up(&self, req: &Req, result: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        self.circuit.record(result.is_err());
+        if result.is_err() && self.circuit.is_open() {
+            return Err(Outcome::CircuitOpen);
+        }
+
+        self.policy.retry_or_give_up(req, result).map(|inner| WithCircuitFuture {
+            inner,
+            circuit: Some(self.circuit.clone()),
+        })
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        self.policy.clone_request(req)
+    }
+
+    fn size_hint(&self, req: &Req) -> Option<usize> {
+        self.policy.size_hint(req)
+    }
+
+    fn is_replayable(&self, req: &Req) -> bool {
+        self.policy.is_replayable(req)
+    }
+
+    fn retry_cost(&self, req: &Req) -> u32 {
+        self.policy.retry_cost(req)
+    }
+
+    fn on_future_error(
+        &self,
+        error: <Self::Future as Future>::Error,
+        result: Result<Res, E>,
+    ) -> Result<Res, E> {
+        self.policy.on_future_error(error, result)
+    }
+}
+
+// ===== impl WithCircuitFuture =====
+
+impl<T> Future for WithCircuitFuture<T>
+where
+    T: Future,
+{
+    type Item = WithCircuit<T::Item>;
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let policy = try_ready!(self.inner.poll());
+        Ok(Async::Ready(WithCircuit {
+            policy,
+            circuit: self.circuit.take().expect("polled after complete"),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct Always;
+
+    impl Policy<(), (), ()> for Always {
+        type Future = FutureResult<Self, ()>;
+
+        fn retry(&self, _: &(), result: Result<&(), &()>) -> Option<Self::Future> {
+            if result.is_err() {
+                Some(future::ok(Always))
+            } else {
+                None
+            }
+        }
+
+        fn clone_request(&self, _: &()) -> Option<()> {
+            Some(())
+        }
+    }
+
+    #[test]
+    fn open_circuit_disables_retries() {
+        let circuit = Arc::new(Circuit::new(0.5, 4));
+        let policy = WithCircuit::new(Always, circuit.clone());
+
+        // Two failures in four results do not exceed the threshold.
+        for &failed in &[true, false, true, false] {
+            circuit.record(failed);
+        }
+        assert!(!circuit.is_open());
+        assert!(policy.retry(&(), Err(&())).is_some());
+
+        // The failure just reported, and two more, open the circuit.
+        circuit.record(true);
+        circuit.record(true);
+        assert!(circuit.is_open());
+        assert_eq!(policy.retry_or_give_up(&(), Err(&())).err(), Some(Outcome::CircuitOpen));
+
+        // Successes bring the failure rate back down, closing the circuit.
+        for _ in 0..3 {
+            assert!(policy.retry(&(), Ok(&())).is_none());
+        }
+        assert!(!circuit.is_open());
+        assert!(policy.retry(&(), Err(&())).is_some());
+    }
+}
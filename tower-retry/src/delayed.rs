@@ -0,0 +1,41 @@
+//! Waits out the delay before a retry.
+
+use futures::{Async, Future, Poll};
+use tokio_timer::{self, clock, timer, Delay};
+
+use std::time::Duration;
+
+/// Yields a value, such as the policy for the next retry, once `wait` has
+/// elapsed.
+#[derive(Debug)]
+pub struct Delayed<T> {
+    delay: Delay,
+    value: Option<T>,
+}
+
+// ===== impl Delayed =====
+
+impl<T> Delayed<T> {
+    /// Waits on `timer`, if given, or else on the default timer.
+    pub fn new(wait: Duration, timer: Option<&timer::Handle>, value: T) -> Self {
+        let deadline = clock::now() + wait;
+        let delay = match timer {
+            Some(timer) => timer.delay(deadline),
+            None => Delay::new(deadline),
+        };
+        Delayed {
+            delay,
+            value: Some(value),
+        }
+    }
+}
+
+impl<T> Future for Delayed<T> {
+    type Item = T;
+    type Error = tokio_timer::Error;
+
+    fn poll(&mut self) -> Poll<T, tokio_timer::Error> {
+        try_ready!(self.delay.poll());
+        Ok(Async::Ready(self.value.take().expect("polled after complete")))
+    }
+}
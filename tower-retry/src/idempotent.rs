@@ -0,0 +1,133 @@
+//! A ready-made policy for idempotent requests.
+
+use futures::{Future, Poll};
+use tokio_timer::{self, timer};
+
+use std::cmp;
+use std::time::Duration;
+
+use delayed::Delayed;
+use {Outcome, Policy};
+
+/// Retries idempotent requests, such as GETs, that fail with transient errors.
+///
+/// By default, a request is retried up to 3 times, waiting 50ms before the
+/// first retry and doubling the wait before each further retry, up to 1s. Only
+/// requests that may safely be sent more than once should be retried this way.
+///
+/// This is created by the `idempotent` function.
+#[derive(Clone, Debug)]
+pub struct Idempotent<C, F> {
+    clone_request: C,
+    is_transient: F,
+    /// The number of retries made so far.
+    retries: usize,
+    max_retries: usize,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    /// Drives backoff, if not the default timer.
+    timer: Option<timer::Handle>,
+}
+
+/// Yields the policy for the next retry once its backoff has elapsed.
+#[derive(Debug)]
+pub struct IdempotentFuture<C, F> {
+    inner: Delayed<Idempotent<C, F>>,
+}
+
+/// Retries requests that fail with errors for which `is_transient` returns
+/// true, with exponential backoff and a cap on attempts.
+///
+/// `clone_request` clones each request so that it may be sent again, so that
+/// requests need not implement `Clone`.
+pub fn idempotent<C, F>(clone_request: C, is_transient: F) -> Idempotent<C, F> {
+    Idempotent {
+        clone_request,
+        is_transient,
+        retries: 0,
+        max_retries: 3,
+        base_backoff: Duration::from_millis(50),
+        max_backoff: Duration::from_secs(1),
+        timer: None,
+    }
+}
+
+// ===== impl Idempotent =====
+
+impl<C, F> Idempotent<C, F> {
+    /// Retries each request at most `max_retries` times.
+    pub fn max_retries(self, max_retries: usize) -> Self {
+        Idempotent { max_retries, ..self }
+    }
+
+    /// Waits `base` before the first retry, doubling the wait before each
+    /// further retry up to `max`.
+    pub fn backoff(self, base: Duration, max: Duration) -> Self {
+        Idempotent {
+            base_backoff: base,
+            max_backoff: max,
+            ..self
+        }
+    }
+
+    /// Uses `timer` to drive backoff, rather than the default timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        Idempotent {
+            timer: Some(timer),
+            ..self
+        }
+    }
+
+    fn backoff_for(&self, retries: usize) -> Duration {
+        // Doubling more than 31 times exceeds any reasonable maximum.
+        let factor = 1u32 << cmp::min(retries, 31) as u32;
+        match self.base_backoff.checked_mul(factor) {
+            Some(backoff) => cmp::min(backoff, self.max_backoff),
+            None => self.max_backoff,
+        }
+    }
+}
+
+impl<C, F, Req, Res, E> Policy<Req, Res, E> for Idempotent<C, F>
+where
+    C: Fn(&Req) -> Option<Req> + Clone,
+    F: Fn(&E) -> bool + Clone,
+{
+    type Future = IdempotentFuture<C, F>;
+
+    fn retry(&self, req: &Req, res: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, res).ok()
+    }
+
+    fn retry_or_give_up(&self, _: &Req, res: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        match res {
+            Ok(_) => Err(Outcome::Succeeded),
+            Err(e) if !(self.is_transient)(e) => Err(Outcome::NotRetryable),
+            Err(_) if self.retries >= self.max_retries => Err(Outcome::AttemptsExhausted),
+            Err(_) => {
+                let next = Idempotent {
+                    retries: self.retries + 1,
+                    ..self.clone()
+                };
+                Ok(IdempotentFuture {
+                    inner: Delayed::new(self.backoff_for(self.retries), self.timer.as_ref(), next),
+                })
+            }
+        }
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        (self.clone_request)(req)
+    }
+}
+
+// ===== impl IdempotentFuture =====
+
+impl<C, F> Future for IdempotentFuture<C, F> {
+    type Item = Idempotent<C, F>;
+    type Error = tokio_timer::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.inner.poll()
+    }
+}
@@ -4,16 +4,67 @@ extern crate tokio_timer;
 extern crate tower_service;
 
 use futures::{Async, Future, Poll};
+use tokio_timer::{clock, Delay};
 use tower_service::Service;
 
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
 pub mod budget;
+pub mod circuit;
+mod delayed;
+mod idempotent;
+mod observed;
+mod per_class;
+mod retry_after;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
+pub use idempotent::{idempotent, Idempotent, IdempotentFuture};
+pub use observed::{Observed, ObservedFuture};
+pub use per_class::{PerClass, PerClassFuture};
+pub use retry_after::{RetryAfter, RetryAfterFuture};
 
 #[derive(Clone, Debug)]
 pub struct Retry<P, S> {
     policy: P,
     service: S,
+    /// Requests with a larger `Policy::size_hint` are not cloned for retry.
+    clone_limit: Option<usize>,
+    /// Called with the outcome of each request once it is no longer retried.
+    on_outcome: Option<OnOutcome>,
+    /// How long a retry may wait for the service to become ready.
+    ready_timeout: Option<Duration>,
 }
 
+/// Describes how a request through `Retry` ultimately completed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The request succeeded.
+    Succeeded,
+    /// The policy gave up because the request had been attempted as many
+    /// times as it allows.
+    AttemptsExhausted,
+    /// The policy gave up because its retry budget was exhausted.
+    BudgetExhausted,
+    /// The request failed with an error that the policy does not retry.
+    NotRetryable,
+    /// The request failed, but could not be cloned or replayed for a retry.
+    NotReplayable,
+    /// The policy's future failed while deciding whether to retry.
+    PolicyFailed,
+    /// The request failed while a `circuit::Circuit` was open, so it was not
+    /// retried.
+    CircuitOpen,
+    /// The service did not become ready to retry the request within the
+    /// bound set by `Retry::with_ready_timeout`.
+    Backpressured,
+}
+
+#[derive(Clone)]
+struct OnOutcome(Arc<Fn(Outcome) + Send + Sync>);
+
 #[derive(Debug)]
 pub struct ResponseFuture<P, S, Request>
 where
@@ -21,6 +72,8 @@ where
     S: Service<Request>,
 {
     request: Option<Request>,
+    /// Whether `request` may still be replayed, per `Policy::is_replayable`.
+    replayable: bool,
     retry: Retry<P, S>,
     state: State<S::Future, P::Future, S::Response, S::Error>,
 }
@@ -32,13 +85,75 @@ enum State<F, P, R, E> {
     /// Polling the future from `Policy::retry`
     Checking(P, Option<Result<R, E>>),
     /// Polling `Service::poll_ready` after `Checking` was OK.
-    Retrying,
+    ///
+    /// With a ready timeout, the delay is started once the service is first
+    /// not ready, and the last result is held to be returned if it elapses.
+    Retrying(Option<Delay>, Option<Result<R, E>>),
 }
 
 pub trait Policy<Req, Res, E>: Sized {
-    type Future: Future<Item=Self, Error=()>;
+    type Future: Future<Item=Self>;
     fn retry(&self, req: &Req, res: Result<&Res, &E>) -> Option<Self::Future>;
     fn clone_request(&self, req: &Req) -> Option<Req>;
+
+    /// Decides whether to retry, as with `retry`, but reports why the request
+    /// is not retried.
+    ///
+    /// This is what `Retry` calls. By default, it defers to `retry`, and
+    /// reports that a response succeeded and that an error is not retryable.
+    /// Policies that limit attempts or withdraw from a budget should override
+    /// it to report `Outcome::AttemptsExhausted` or
+    /// `Outcome::BudgetExhausted`.
+    fn retry_or_give_up(&self, req: &Req, res: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        match self.retry(req, res) {
+            Some(future) => Ok(future),
+            None if res.is_ok() => Err(Outcome::Succeeded),
+            None => Err(Outcome::NotRetryable),
+        }
+    }
+
+    /// Decides the outcome of a request whose `Policy::Future` failed.
+    ///
+    /// `result` is the outcome of the last attempt. By default, it is returned
+    /// as-is, so that a failed policy future simply gives up. A policy may
+    /// instead, e.g., replace it with an error describing why it gave up.
+    fn on_future_error(
+        &self,
+        _error: <Self::Future as Future>::Error,
+        result: Result<Res, E>,
+    ) -> Result<Res, E> {
+        result
+    }
+
+    /// Returns an estimate of the memory held by `req`, e.g. its body length.
+    ///
+    /// This is compared against the limit set by `Retry::with_clone_limit` to
+    /// decide whether a request may be cloned. By default, no estimate is made
+    /// and requests are always eligible to be cloned.
+    fn size_hint(&self, _req: &Req) -> Option<usize> {
+        None
+    }
+
+    /// Returns whether `req` may safely be dispatched again.
+    ///
+    /// This is checked when a request is cloned, and again before each retry.
+    /// A request whose body has begun streaming to the inner service, for
+    /// example, should report that it is no longer replayable, so that a
+    /// partial body is never re-issued. By default, requests are always
+    /// replayable.
+    fn is_replayable(&self, _req: &Req) -> bool {
+        true
+    }
+
+    /// Returns the cost of retrying `req`, for policies that withdraw from a
+    /// `budget::Budget`.
+    ///
+    /// Expensive requests may report a higher cost, so that they exhaust a
+    /// shared budget sooner and are retried less. By default, each retry costs
+    /// one.
+    fn retry_cost(&self, _req: &Req) -> u32 {
+        1
+    }
 }
 
 
@@ -53,6 +168,105 @@ impl<P, S> Retry<P, S> {
         Retry {
             policy,
             service,
+            clone_limit: None,
+            on_outcome: None,
+            ready_timeout: None,
+        }
+    }
+
+    /// Does not clone requests whose `Policy::size_hint` exceeds `max_size`.
+    ///
+    /// Holding a clone of a large request for the duration of each call may
+    /// double its memory use. Requests larger than `max_size` are dispatched
+    /// once, without being retried.
+    pub fn with_clone_limit(self, max_size: usize) -> Self {
+        Retry {
+            clone_limit: Some(max_size),
+            ..self
+        }
+    }
+
+    /// Calls `f` with the `Outcome` of each request once it is no longer
+    /// retried, e.g. to categorize failures in metrics.
+    pub fn on_outcome<F>(self, f: F) -> Self
+    where
+        F: Fn(Outcome) + Send + Sync + 'static,
+    {
+        Retry {
+            on_outcome: Some(OnOutcome(Arc::new(f))),
+            ..self
+        }
+    }
+
+    /// Gives up on a retry if the service does not become ready within
+    /// `timeout`, returning the last result, so that retries do not queue
+    /// behind a backpressured service.
+    ///
+    /// Requests that are given up on this way are reported as
+    /// `Outcome::Backpressured`.
+    pub fn with_ready_timeout(self, timeout: Duration) -> Self {
+        Retry {
+            ready_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Dispatches `request`, retrying it according to `policy` rather than
+    /// this service's policy.
+    ///
+    /// This allows, e.g., a more important call to be retried more times than
+    /// others. As with `Service::call`, `poll_ready` must have returned ready
+    /// first.
+    pub fn call_with_policy<Request>(
+        &mut self,
+        request: Request,
+        policy: P,
+    ) -> ResponseFuture<P, S, Request>
+    where
+        P: Policy<Request, S::Response, S::Error> + Clone,
+        S: Service<Request> + Clone,
+    {
+        let retry = Retry {
+            policy,
+            service: self.service.clone(),
+            clone_limit: self.clone_limit,
+            on_outcome: self.on_outcome.clone(),
+            ready_timeout: self.ready_timeout,
+        };
+        let cloned = retry.clone_request(&request);
+        let future = self.service.call(request);
+        ResponseFuture {
+            replayable: cloned.is_some(),
+            request: cloned,
+            retry,
+            state: State::Called(future),
+        }
+    }
+
+    /// Clones `request` for retry, unless it exceeds the clone limit or is not
+    /// replayable.
+    fn clone_request<Request>(&self, request: &Request) -> Option<Request>
+    where
+        P: Policy<Request, S::Response, S::Error>,
+        S: Service<Request>,
+    {
+        if !self.policy.is_replayable(request) {
+            return None;
+        }
+
+        if let Some(max_size) = self.clone_limit {
+            match self.policy.size_hint(request) {
+                Some(size) if size > max_size => return None,
+                _ => {}
+            }
+        }
+
+        self.policy.clone_request(request)
+    }
+
+    fn report(&self, outcome: Outcome) {
+        if let Some(ref on_outcome) = self.on_outcome {
+            (on_outcome.0)(outcome);
         }
     }
 }
@@ -71,9 +285,10 @@ where
     }
 
     fn call(&mut self, request: Request) -> Self::Future {
-        let cloned = self.policy.clone_request(&request);
+        let cloned = self.clone_request(&request);
         let future = self.service.call(request);
         ResponseFuture {
+            replayable: cloned.is_some(),
             request: cloned,
             retry: self.clone(),
             state: State::Called(future),
@@ -81,6 +296,12 @@ where
     }
 }
 
+impl fmt::Debug for OnOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OnOutcome").finish()
+    }
+}
+
 // ===== impl ResponseFuture =====
 
 impl<P, S, Request> Future for ResponseFuture<P, S, Request>
@@ -101,39 +322,94 @@ where
                         Err(err) => Err(err),
                     };
 
+                    // Once the request has partially streamed, replaying it
+                    // would re-issue an incomplete body.
                     if let Some(ref req) = self.request {
-                        match self.retry.policy.retry(req, result.as_ref()) {
-                            Some(checking) => State::Checking(checking, Some(result)),
-                            None => return result.map(Async::Ready),
-                        }
+                        self.replayable &= self.retry.policy.is_replayable(req);
+                    }
+
+                    // If the request wasn't cloned, there is no way to retry it,
+                    // even if an earlier attempt was retried: this result is
+                    // returned as is.
+                    let unreplayable = if result.is_ok() {
+                        Outcome::Succeeded
                     } else {
-                        // request wasn't cloned, so no way to retry it
-                        return result.map(Async::Ready);
+                        Outcome::NotReplayable
+                    };
+                    let checking = match self.request {
+                        Some(ref req) if self.replayable => {
+                            self.retry.policy.retry_or_give_up(req, result.as_ref())
+                        }
+                        _ => Err(unreplayable),
+                    };
+
+                    match checking {
+                        Ok(checking) => State::Checking(checking, Some(result)),
+                        Err(outcome) => {
+                            // Free the clone now, rather than when this future
+                            // is dropped.
+                            self.request = None;
+                            self.retry.report(outcome);
+                            return result.map(Async::Ready);
+                        }
                     }
                 },
                 State::Checking(ref mut future, ref mut result) => {
                     let policy = match future.poll() {
                         Ok(Async::NotReady) => return Ok(Async::NotReady),
                         Ok(Async::Ready(policy)) => policy,
-                        Err(()) => {
-                            // if Policy::retry() fails, return the original
-                            // result...
-                            return result
-                                .take()
-                                .expect("polled after complete")
+                        Err(error) => {
+                            // if Policy::retry() fails, the policy decides the
+                            // result; by default, the original result...
+                            self.request = None;
+                            self.retry.report(Outcome::PolicyFailed);
+                            let result = result.take().expect("polled after complete");
+                            return self.retry.policy
+                                .on_future_error(error, result)
                                 .map(Async::Ready);
                         }
                     };
                     self.retry.policy = policy;
-                    State::Retrying
+                    // The last result is only needed if the retry may be
+                    // abandoned.
+                    let result = match self.retry.ready_timeout {
+                        Some(_) => result.take(),
+                        None => None,
+                    };
+                    State::Retrying(None, result)
                 },
-                State::Retrying => {
-                    try_ready!(self.retry.poll_ready());
+                State::Retrying(ref mut delay, ref mut result) => {
+                    match self.retry.poll_ready() {
+                        Ok(Async::Ready(())) => {}
+                        Ok(Async::NotReady) => {
+                            let timeout = match self.retry.ready_timeout {
+                                Some(timeout) => timeout,
+                                None => return Ok(Async::NotReady),
+                            };
+                            let delay = delay
+                                .get_or_insert_with(|| Delay::new(clock::now() + timeout));
+                            if let Ok(Async::NotReady) = delay.poll() {
+                                return Ok(Async::NotReady);
+                            }
+
+                            // The timer elapsed or failed: give up on the retry.
+                            self.request = None;
+                            self.retry.report(Outcome::Backpressured);
+                            let result = result.take().expect("polled after complete");
+                            return result.map(Async::Ready);
+                        }
+                        Err(e) => {
+                            self.request = None;
+                            self.retry.report(Outcome::NotRetryable);
+                            return Err(e);
+                        }
+                    }
                     let req = self
                         .request
                         .take()
                         .expect("retrying requires cloned request");
-                    self.request = self.retry.policy.clone_request(&req);
+                    self.request = self.retry.clone_request(&req);
+                    self.replayable = self.request.is_some();
                     State::Called(self.retry.service.call(req))
                 }
             };
@@ -0,0 +1,116 @@
+use futures::{Async, Future, Poll};
+
+use {Outcome, Policy};
+
+/// Wraps a `Policy`, invoking a callback each time it gives up on a request.
+///
+/// Whenever the inner policy gives up on a request, `F` is called with the
+/// number of attempts that were made, including the first, and the final
+/// response or error. This allows give-ups to be logged or counted in one
+/// place, rather than in every policy.
+///
+/// A request that succeeds is not a give-up, so it is not reported, even if it
+/// succeeded after being retried. Requests that cannot be cloned are never passed to `retry`, so they are not
+/// reported.
+#[derive(Clone, Debug)]
+pub struct Observed<P, F> {
+    policy: P,
+    on_give_up: F,
+    /// The number of attempts made so far for the current request.
+    attempts: usize,
+}
+
+/// Yields an `Observed` policy once the inner policy's future completes.
+#[derive(Debug)]
+pub struct ObservedFuture<T, F> {
+    inner: T,
+    on_give_up: Option<F>,
+    attempts: usize,
+}
+
+// ===== impl Observed =====
+
+impl<P, F> Observed<P, F> {
+    pub fn new(policy: P, on_give_up: F) -> Self {
+        Observed {
+            policy,
+            on_give_up,
+            attempts: 1,
+        }
+    }
+
+    /// Get a reference to the inner policy
+    pub fn get_ref(&self) -> &P {
+        &self.policy
+    }
+}
+
+impl<P, F, Req, Res, E> Policy<Req, Res, E> for Observed<P, F>
+where
+    P: Policy<Req, Res, E>,
+    F: Fn(usize, Result<&Res, &E>) + Clone,
+{
+    type Future = ObservedFuture<P::Future, F>;
+
+    fn retry(&self, req: &Req, result: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, result).ok()
+    }
+
+    fn retry_or_give_up(&self, req: &Req, result: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        match self.policy.retry_or_give_up(req, result) {
+            Ok(inner) => Ok(ObservedFuture {
+                inner,
+                on_give_up: Some(self.on_give_up.clone()),
+                attempts: self.attempts + 1,
+            }),
+            Err(Outcome::Succeeded) => Err(Outcome::Succeeded),
+            Err(outcome) => {
+                (self.on_give_up)(self.attempts, result);
+                Err(outcome)
+            }
+        }
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        self.policy.clone_request(req)
+    }
+
+    fn size_hint(&self, req: &Req) -> Option<usize> {
+        self.policy.size_hint(req)
+    }
+
+    fn is_replayable(&self, req: &Req) -> bool {
+        self.policy.is_replayable(req)
+    }
+
+    fn retry_cost(&self, req: &Req) -> u32 {
+        self.policy.retry_cost(req)
+    }
+
+    fn on_future_error(
+        &self,
+        error: <Self::Future as Future>::Error,
+        result: Result<Res, E>,
+    ) -> Result<Res, E> {
+        self.policy.on_future_error(error, result)
+    }
+}
+
+// ===== impl ObservedFuture =====
+
+impl<T, F> Future for ObservedFuture<T, F>
+where
+    T: Future,
+{
+    type Item = Observed<T::Item, F>;
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let policy = try_ready!(self.inner.poll());
+        Ok(Async::Ready(Observed {
+            policy,
+            on_give_up: self.on_give_up.take().expect("polled after complete"),
+            attempts: self.attempts,
+        }))
+    }
+}
@@ -0,0 +1,155 @@
+use futures::{Async, Future, Poll};
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use {Outcome, Policy};
+
+/// Wraps a `Policy`, capping the number of retries for each class of error.
+///
+/// Each error is classified by `F`, e.g. as a timeout or a server error, and
+/// is retried only while fewer retries than its class's cap have been made for
+/// the current request. Retries are counted separately for each class, so a
+/// request that timed out twice may still be retried once for a server error.
+/// Classes without a cap are limited only by the inner policy, which must also
+/// agree to each retry.
+#[derive(Clone, Debug)]
+pub struct PerClass<F, P, C>
+where
+    C: Hash + Eq,
+{
+    policy: P,
+    classify: F,
+    caps: Arc<HashMap<C, usize>>,
+    /// The number of retries made so far for each class.
+    retries: HashMap<C, usize>,
+}
+
+/// Yields a `PerClass` policy once the inner policy's future completes.
+#[derive(Debug)]
+pub struct PerClassFuture<T, F, C>
+where
+    C: Hash + Eq,
+{
+    inner: T,
+    state: Option<(F, Arc<HashMap<C, usize>>, HashMap<C, usize>)>,
+}
+
+// ===== impl PerClass =====
+
+impl<F, P, C> PerClass<F, P, C>
+where
+    C: Hash + Eq,
+{
+    /// Classifies errors with `classify`. Until `cap` is called, no class is
+    /// capped.
+    pub fn new(policy: P, classify: F) -> Self {
+        PerClass {
+            policy,
+            classify,
+            caps: Arc::new(HashMap::new()),
+            retries: HashMap::new(),
+        }
+    }
+
+    /// Allows at most `max_retries` retries of errors classified as `class`.
+    pub fn cap(mut self, class: C, max_retries: usize) -> Self
+    where
+        C: Clone,
+    {
+        Arc::make_mut(&mut self.caps).insert(class, max_retries);
+        self
+    }
+
+    /// Get a reference to the inner policy
+    pub fn get_ref(&self) -> &P {
+        &self.policy
+    }
+}
+
+impl<F, P, C, Req, Res, E> Policy<Req, Res, E> for PerClass<F, P, C>
+where
+    P: Policy<Req, Res, E>,
+    F: Fn(&E) -> C + Clone,
+    C: Hash + Eq + Clone,
+{
+    type Future = PerClassFuture<P::Future, F, C>;
+
+    fn retry(&self, req: &Req, result: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, result).ok()
+    }
+
+    fn retry_or_give_up(&self, req: &Req, result: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        let class = match result {
+            Ok(_) => None,
+            Err(e) => Some((self.classify)(e)),
+        };
+
+        if let Some(ref class) = class {
+            let retries = self.retries.get(class).cloned().unwrap_or(0);
+            match self.caps.get(class) {
+                Some(&cap) if retries >= cap => return Err(Outcome::AttemptsExhausted),
+                _ => {}
+            }
+        }
+
+        let inner = self.policy.retry_or_give_up(req, result)?;
+
+        let mut retries = self.retries.clone();
+        if let Some(class) = class {
+            *retries.entry(class).or_insert(0) += 1;
+        }
+
+        Ok(PerClassFuture {
+            inner,
+            state: Some((self.classify.clone(), self.caps.clone(), retries)),
+        })
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        self.policy.clone_request(req)
+    }
+
+    fn size_hint(&self, req: &Req) -> Option<usize> {
+        self.policy.size_hint(req)
+    }
+
+    fn is_replayable(&self, req: &Req) -> bool {
+        self.policy.is_replayable(req)
+    }
+
+    fn retry_cost(&self, req: &Req) -> u32 {
+        self.policy.retry_cost(req)
+    }
+
+    fn on_future_error(
+        &self,
+        error: <Self::Future as Future>::Error,
+        result: Result<Res, E>,
+    ) -> Result<Res, E> {
+        self.policy.on_future_error(error, result)
+    }
+}
+
+// ===== impl PerClassFuture =====
+
+impl<T, F, C> Future for PerClassFuture<T, F, C>
+where
+    T: Future,
+    C: Hash + Eq,
+{
+    type Item = PerClass<F, T::Item, C>;
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let policy = try_ready!(self.inner.poll());
+        let (classify, caps, retries) = self.state.take().expect("polled after complete");
+        Ok(Async::Ready(PerClass {
+            policy,
+            classify,
+            caps,
+            retries,
+        }))
+    }
+}
@@ -0,0 +1,97 @@
+//! A policy for retrying at a delay specified by the server.
+
+use futures::{Future, Poll};
+use tokio_timer::{self, timer};
+
+use std::time::Duration;
+
+use delayed::Delayed;
+use {Outcome, Policy};
+
+/// Retries requests that fail with an error carrying a delay, such as an HTTP
+/// `Retry-After` header, once that delay has elapsed.
+///
+/// `C` clones requests, so that requests need not implement `Clone`, and `F`
+/// extracts the delay from an error. Errors without a delay are not retried.
+#[derive(Clone, Debug)]
+pub struct RetryAfter<C, F> {
+    remaining: usize,
+    clone_request: C,
+    retry_after: F,
+    /// Drives delays, if not the default timer.
+    timer: Option<timer::Handle>,
+}
+
+/// Yields the policy for the next retry once the server's delay has elapsed.
+#[derive(Debug)]
+pub struct RetryAfterFuture<C, F> {
+    inner: Delayed<RetryAfter<C, F>>,
+}
+
+// ===== impl RetryAfter =====
+
+impl<C, F> RetryAfter<C, F> {
+    /// Retries each request at most `max_retries` times, each after the delay
+    /// that `retry_after` extracts from its error.
+    pub fn new(max_retries: usize, clone_request: C, retry_after: F) -> Self {
+        RetryAfter {
+            remaining: max_retries,
+            clone_request,
+            retry_after,
+            timer: None,
+        }
+    }
+
+    /// Uses `timer` to drive delays, rather than the default timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        RetryAfter {
+            timer: Some(timer),
+            ..self
+        }
+    }
+}
+
+impl<C, F, Req, Res, E> Policy<Req, Res, E> for RetryAfter<C, F>
+where
+    C: Fn(&Req) -> Option<Req> + Clone,
+    F: Fn(&E) -> Option<Duration> + Clone,
+{
+    type Future = RetryAfterFuture<C, F>;
+
+    fn retry(&self, req: &Req, res: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, res).ok()
+    }
+
+    fn retry_or_give_up(&self, _: &Req, res: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        let wait = match res {
+            Ok(_) => return Err(Outcome::Succeeded),
+            Err(e) => (self.retry_after)(e).ok_or(Outcome::NotRetryable)?,
+        };
+        if self.remaining == 0 {
+            return Err(Outcome::AttemptsExhausted);
+        }
+
+        let next = RetryAfter {
+            remaining: self.remaining - 1,
+            ..self.clone()
+        };
+        Ok(RetryAfterFuture {
+            inner: Delayed::new(wait, self.timer.as_ref(), next),
+        })
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        (self.clone_request)(req)
+    }
+}
+
+// ===== impl RetryAfterFuture =====
+
+impl<C, F> Future for RetryAfterFuture<C, F> {
+    type Item = RetryAfter<C, F>;
+    type Error = tokio_timer::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.inner.poll()
+    }
+}
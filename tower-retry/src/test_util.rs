@@ -0,0 +1,120 @@
+//! Utilities for testing middleware that is composed with `Retry`.
+//!
+//! This module is only available when the `test-util` feature is enabled.
+
+use futures::future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {Outcome, Policy};
+
+/// A `Policy` that immediately retries failed requests a fixed number of
+/// times.
+///
+/// Every attempt to send a request, including the first, is recorded. Clones
+/// of a `CountingPolicy` share the same record, so the policy may be handed to
+/// `Retry` while a clone is kept to inspect `attempts`.
+#[derive(Clone, Debug)]
+pub struct CountingPolicy {
+    remaining: usize,
+    attempts: Arc<AtomicUsize>,
+}
+
+// ===== impl CountingPolicy =====
+
+impl CountingPolicy {
+    /// Creates a policy that retries each failed request up to `max_retries`
+    /// times.
+    pub fn new(max_retries: usize) -> Self {
+        CountingPolicy {
+            remaining: max_retries,
+            attempts: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Returns the number of attempts made so far, across all requests.
+    pub fn attempts(&self) -> usize {
+        self.attempts.load(Ordering::SeqCst)
+    }
+}
+
+impl<Req, Res, E> Policy<Req, Res, E> for CountingPolicy
+where
+    Req: Clone,
+{
+    type Future = future::FutureResult<Self, ()>;
+
+    fn retry(&self, req: &Req, result: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, result).ok()
+    }
+
+    fn retry_or_give_up(&self, _: &Req, result: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        match result {
+            Ok(_) => Err(Outcome::Succeeded),
+            Err(_) if self.remaining == 0 => Err(Outcome::AttemptsExhausted),
+            Err(_) => Ok(future::ok(CountingPolicy {
+                remaining: self.remaining - 1,
+                attempts: self.attempts.clone(),
+            })),
+        }
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        // `Retry` clones the request exactly once before each attempt.
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        Some(req.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tower_mock;
+
+    use futures::Future;
+    use tower_service::Service;
+
+    use super::*;
+    use Retry;
+
+    type Mock = tower_mock::Mock<&'static str, &'static str, &'static str>;
+
+    #[test]
+    fn counts_attempts_until_exhausted() {
+        let policy = CountingPolicy::new(2);
+        let (service, mut handle) = Mock::new();
+        let mut service = Retry::new(policy.clone(), service);
+
+        let mut fut = service.call("hello");
+        handle.next_request().unwrap().error("fail");
+        assert_not_ready(&mut fut);
+        handle.next_request().unwrap().error("fail");
+        assert_not_ready(&mut fut);
+        handle.next_request().unwrap().error("fail");
+
+        assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("fail"));
+        assert_eq!(policy.attempts(), 3);
+    }
+
+    #[test]
+    fn counts_attempts_until_success() {
+        let policy = CountingPolicy::new(5);
+        let (service, mut handle) = Mock::new();
+        let mut service = Retry::new(policy.clone(), service);
+
+        let mut fut = service.call("hello");
+        handle.next_request().unwrap().error("fail");
+        assert_not_ready(&mut fut);
+        handle.next_request().unwrap().respond("world");
+
+        assert_eq!(fut.wait().unwrap(), "world");
+        assert_eq!(policy.attempts(), 2);
+    }
+
+    fn assert_not_ready<F: Future>(f: &mut F) where F::Error: ::std::fmt::Debug {
+        use futures::future;
+        future::poll_fn(|| {
+            assert!(f.poll().unwrap().is_not_ready());
+            Ok::<_, ()>(().into())
+        }).wait().unwrap();
+    }
+}
@@ -2,10 +2,15 @@ extern crate futures;
 extern crate tower_mock;
 extern crate tower_retry;
 extern crate tower_service;
+extern crate tower_timeout;
 
 use futures::{future, Future};
-use tower_retry::Policy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower_retry::{Outcome, Policy};
 use tower_service::Service;
+use tower_timeout::test_util::with_mock_timer;
 
 #[test]
 fn retry_errors() {
@@ -51,6 +56,134 @@ fn retry_limit() {
     assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retry 3"));
 }
 
+#[test]
+fn per_call_policy_override() {
+    let (mut service, mut handle) = new_service(Limit(2));
+
+    // A single attempt.
+    let fut = service.call_with_policy("hello", Limit(0));
+    handle.next_request().unwrap().error("retry 1");
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retry 1"));
+
+    // Up to five attempts.
+    let mut fut = service.call_with_policy("hello", Limit(4));
+    for _ in 0..4 {
+        handle.next_request().unwrap().error("retry me");
+        assert_not_ready(&mut fut);
+    }
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.wait().unwrap(), "world");
+
+    // Other calls use the service's policy.
+    let mut fut = service.call("hello");
+    for _ in 0..2 {
+        handle.next_request().unwrap().error("retry me");
+        assert_not_ready(&mut fut);
+    }
+    handle.next_request().unwrap().error("retry 3");
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retry 3"));
+}
+
+#[test]
+fn give_up_observed_once() {
+    let give_ups = Arc::new(Mutex::new(Vec::new()));
+    let policy = {
+        let give_ups = give_ups.clone();
+        tower_retry::Observed::new(Limit(2), move |attempts, result: Result<&Res, &Error>| {
+            give_ups.lock().unwrap().push((attempts, result.is_err()));
+        })
+    };
+    let (mut service, mut handle) = new_service(policy);
+
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().error("retry 2");
+    assert_not_ready(&mut fut);
+    assert!(give_ups.lock().unwrap().is_empty());
+
+    handle.next_request().unwrap().error("retry 3");
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retry 3"));
+    assert_eq!(*give_ups.lock().unwrap(), vec![(3, true)]);
+
+    // A success is not a give-up.
+    let fut = service.call("hello");
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.wait().unwrap(), "world");
+    assert_eq!(*give_ups.lock().unwrap(), vec![(3, true)]);
+}
+
+#[test]
+fn success_after_retry_is_not_observed() {
+    let give_ups = Arc::new(Mutex::new(Vec::new()));
+    let policy = {
+        let give_ups = give_ups.clone();
+        tower_retry::Observed::new(Limit(2), move |attempts, result: Result<&Res, &Error>| {
+            give_ups.lock().unwrap().push((attempts, result.is_err()));
+        })
+    };
+    let (mut service, mut handle) = new_service(policy);
+
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.wait().unwrap(), "world");
+    assert!(give_ups.lock().unwrap().is_empty());
+}
+
+#[test]
+fn policy_future_error_gives_up_with_custom_error() {
+    let (mut service, mut handle) = new_service(Exhausted);
+
+    let fut = service.call("hello");
+    handle.next_request().unwrap().error("retry me");
+
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retries exhausted"));
+}
+
+#[test]
+fn outcomes_are_classified() {
+    // Succeeded, after a retry.
+    let (mut service, mut handle, outcomes) = new_observed_service(Limit(2));
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.wait().unwrap(), "world");
+    assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::Succeeded]);
+
+    // Gave up after the policy's attempts were exhausted.
+    let (mut service, mut handle, outcomes) = new_observed_service(Limit(1));
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().error("retry 2");
+    assert!(fut.wait().is_err());
+    assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::AttemptsExhausted]);
+
+    // Gave up on an error the policy does not retry.
+    let (mut service, mut handle, outcomes) = new_observed_service(UnlessErr("reject"));
+    let fut = service.call("hello");
+    handle.next_request().unwrap().error("reject");
+    assert!(fut.wait().is_err());
+    assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::NotRetryable]);
+
+    // Gave up because the request could not be cloned.
+    let (mut service, mut handle, outcomes) = new_observed_service(CannotClone);
+    let fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert!(fut.wait().is_err());
+    assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::NotReplayable]);
+
+    // Gave up because the policy's future failed.
+    let (mut service, mut handle, outcomes) = new_observed_service(Exhausted);
+    let fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert!(fut.wait().is_err());
+    assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::PolicyFailed]);
+}
+
 #[test]
 fn retry_error_inspection() {
     let (mut service, mut handle) = new_service(UnlessErr("reject"));
@@ -97,6 +230,223 @@ fn success_with_cannot_clone() {
     assert_eq!(fut.wait().unwrap(), "world");
 }
 
+#[test]
+fn error_returned_once_clone_disappears_mid_retry() {
+    let (mut service, mut handle, outcomes) = new_observed_service(CloneOnce::default());
+
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert_not_ready(&mut fut);
+
+    // The retry could not be cloned, so its error is final.
+    handle.next_request().unwrap().error("retry 2");
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retry 2"));
+    assert!(future::lazy(|| handle.poll_request()).wait().unwrap().is_not_ready());
+    assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::NotReplayable]);
+}
+
+#[test]
+fn response_returned_once_clone_disappears_mid_retry() {
+    let (mut service, mut handle, outcomes) = new_observed_service(CloneOnce::default());
+
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("retry 1");
+    assert_not_ready(&mut fut);
+
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.wait().unwrap(), "world");
+    assert!(future::lazy(|| handle.poll_request()).wait().unwrap().is_not_ready());
+    assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::Succeeded]);
+}
+
+#[test]
+fn clone_dropped_on_first_success() {
+    let body = Arc::new(());
+    let (service, mut handle) = BodyMock::new();
+    let mut service = tower_retry::Retry::new(RetryBodies, service);
+
+    let mut fut = service.call(Body(body.clone(), 1));
+    // Held by the test, the dispatched request, and the clone for retry.
+    assert_eq!(Arc::strong_count(&body), 3);
+
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.poll().unwrap(), futures::Async::Ready("world"));
+    assert_eq!(Arc::strong_count(&body), 1);
+}
+
+#[test]
+fn oversized_request_not_cloned() {
+    let body = Arc::new(());
+    let (service, mut handle) = BodyMock::new();
+    let mut service = tower_retry::Retry::new(RetryBodies, service).with_clone_limit(10);
+
+    let fut = service.call(Body(body.clone(), 11));
+    assert_eq!(Arc::strong_count(&body), 2);
+
+    handle.next_request().unwrap().error("retry me");
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retry me"));
+
+    // Requests within the limit are still retried.
+    let mut fut = service.call(Body(body.clone(), 10));
+    handle.next_request().unwrap().error("retry me");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.wait().unwrap(), "world");
+}
+
+#[test]
+fn partially_streamed_request_not_retried() {
+    let (service, mut handle) = StreamMock::new();
+    let mut service = tower_retry::Retry::new(RetryStreams, service);
+
+    // The inner service fails after it has begun reading the body.
+    let mut fut = service.call(Stream::default());
+    let req = handle.next_request().unwrap();
+    req.0.store(true, Ordering::SeqCst);
+    req.error("retry me");
+
+    assert_eq!(fut.poll().unwrap_err(), tower_mock::Error::Other("retry me"));
+
+    // Requests that fail before streaming are still retried.
+    let mut fut = service.call(Stream::default());
+    handle.next_request().unwrap().error("retry me");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().respond("world");
+    assert_eq!(fut.wait().unwrap(), "world");
+}
+
+#[test]
+fn retries_capped_per_error_class() {
+    fn classify(error: &Error) -> &'static str {
+        match *error {
+            tower_mock::Error::Other(msg) => msg,
+            _ => "other",
+        }
+    }
+
+    let policy = tower_retry::PerClass::new(RetryErrors, classify)
+        .cap("timeout", 2)
+        .cap("5xx", 1);
+    let (mut service, mut handle) = new_service(policy);
+
+    // Timeouts and server errors are counted separately.
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("timeout");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().error("5xx");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().error("timeout");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().error("5xx");
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("5xx"));
+
+    // Each request starts with a fresh count.
+    let mut fut = service.call("hello");
+    handle.next_request().unwrap().error("timeout");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().error("timeout");
+    assert_not_ready(&mut fut);
+    handle.next_request().unwrap().error("timeout");
+    assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("timeout"));
+}
+
+#[test]
+fn idempotent_retries_transient_errors_up_to_cap() {
+    with_mock_timer(|time, timer| {
+        let policy = tower_retry::idempotent(clone_req, is_unavailable).with_timer(timer.handle());
+        let (mut service, mut handle) = new_service(policy);
+
+        let mut fut = service.call("hello");
+        for &backoff in &[50u64, 100, 200] {
+            handle.next_request().unwrap().error("unavailable");
+            assert_not_ready(&mut fut);
+
+            // The request is only sent again once its backoff elapses.
+            *time.as_mut() += Duration::from_millis(backoff - 1);
+            timer.turn(None).unwrap();
+            assert_not_ready(&mut fut);
+            assert!(future::lazy(|| handle.poll_request()).wait().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            assert_not_ready(&mut fut);
+        }
+        handle.next_request().unwrap().error("unavailable");
+        assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("unavailable"));
+    });
+}
+
+#[test]
+fn idempotent_does_not_retry_other_errors() {
+    with_mock_timer(|_, timer| {
+        let policy = tower_retry::idempotent(clone_req, is_unavailable).with_timer(timer.handle());
+        let (mut service, mut handle) = new_service(policy);
+
+        let fut = service.call("hello");
+        handle.next_request().unwrap().error("not found");
+        assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("not found"));
+    });
+}
+
+#[test]
+fn retry_after_waits_for_server_delay() {
+    with_mock_timer(|time, timer| {
+        let policy = tower_retry::RetryAfter::new(2, clone_req, server_delay).with_timer(timer.handle());
+        let (mut service, mut handle) = new_service(policy);
+
+        let mut fut = service.call("hello");
+        handle.next_request().unwrap().error("slow down");
+        assert_not_ready(&mut fut);
+
+        // The request is only sent again once the server's delay elapses.
+        *time.as_mut() += Duration::from_millis(249);
+        timer.turn(None).unwrap();
+        assert_not_ready(&mut fut);
+        assert!(future::lazy(|| handle.poll_request()).wait().unwrap().is_not_ready());
+
+        *time.as_mut() += Duration::from_millis(1);
+        timer.turn(None).unwrap();
+        assert_not_ready(&mut fut);
+        handle.next_request().unwrap().respond("world");
+        assert_eq!(fut.wait().unwrap(), "world");
+    });
+}
+
+#[test]
+fn retry_after_ignores_errors_without_delay() {
+    with_mock_timer(|_, timer| {
+        let policy = tower_retry::RetryAfter::new(2, clone_req, server_delay).with_timer(timer.handle());
+        let (mut service, mut handle) = new_service(policy);
+
+        let fut = service.call("hello");
+        handle.next_request().unwrap().error("not found");
+        assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("not found"));
+    });
+}
+
+#[test]
+fn retry_abandoned_when_service_stays_not_ready() {
+    with_mock_timer(|time, timer| {
+        let (service, mut handle, outcomes) = new_observed_service(RetryErrors);
+        let mut service = service.with_ready_timeout(Duration::from_secs(1));
+
+        let mut fut = service.call("hello");
+        handle.allow(0);
+        handle.next_request().unwrap().error("retry me");
+        assert_not_ready(&mut fut);
+
+        *time.as_mut() += Duration::from_millis(999);
+        timer.turn(None).unwrap();
+        assert_not_ready(&mut fut);
+
+        // The last result is returned once the bound elapses.
+        *time.as_mut() += Duration::from_millis(1);
+        timer.turn(None).unwrap();
+        assert_eq!(fut.wait().unwrap_err(), tower_mock::Error::Other("retry me"));
+        assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::Backpressured]);
+    });
+}
+
 type Req = &'static str;
 type Res = &'static str;
 type InnerError = &'static str;
@@ -135,6 +485,14 @@ impl Policy<Req, Res, Error> for Limit {
         }
     }
 
+    fn retry_or_give_up(&self, req: &Req, result: Result<&Res, &Error>) -> Result<Self::Future, Outcome> {
+        match self.retry(req, result) {
+            Some(future) => Ok(future),
+            None if result.is_ok() => Err(Outcome::Succeeded),
+            None => Err(Outcome::AttemptsExhausted),
+        }
+    }
+
     fn clone_request(&self, req: &Req) -> Option<Req> {
         Some(*req)
     }
@@ -177,12 +535,142 @@ impl Policy<Req, Res, Error> for CannotClone {
     }
 }
 
+/// Retries every error, but can only clone a request once, so that the
+/// clone disappears between attempts.
+#[derive(Clone, Default)]
+struct CloneOnce(Arc<AtomicBool>);
+
+impl Policy<Req, Res, Error> for CloneOnce {
+    type Future = future::FutureResult<Self, ()>;
+    fn retry(&self, _: &Req, result: Result<&Res, &Error>) -> Option<Self::Future> {
+        result.err().map(|_| future::ok(self.clone()))
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        if self.0.swap(true, Ordering::SeqCst) {
+            None
+        } else {
+            Some(*req)
+        }
+    }
+}
+
+/// Wants to retry errors, but its future fails, as if retries were exhausted.
+#[derive(Clone)]
+struct Exhausted;
+
+impl Policy<Req, Res, Error> for Exhausted {
+    type Future = future::FutureResult<Self, &'static str>;
+    fn retry(&self, _: &Req, result: Result<&Res, &Error>) -> Option<Self::Future> {
+        if result.is_err() {
+            Some(future::err("retries exhausted"))
+        } else {
+            None
+        }
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        Some(*req)
+    }
+
+    fn on_future_error(&self, why: &'static str, _: Result<Res, Error>) -> Result<Res, Error> {
+        Err(tower_mock::Error::Other(why))
+    }
+}
+
+/// A request body, sized by its second field.
+#[derive(Clone, Debug)]
+struct Body(Arc<()>, usize);
+
+type BodyMock = tower_mock::Mock<Body, Res, InnerError>;
+
+#[derive(Clone)]
+struct RetryBodies;
+
+impl Policy<Body, Res, Error> for RetryBodies {
+    type Future = future::FutureResult<Self, ()>;
+    fn retry(&self, _: &Body, result: Result<&Res, &Error>) -> Option<Self::Future> {
+        if result.is_err() {
+            Some(future::ok(RetryBodies))
+        } else {
+            None
+        }
+    }
+
+    fn clone_request(&self, req: &Body) -> Option<Body> {
+        Some(req.clone())
+    }
+
+    fn size_hint(&self, req: &Body) -> Option<usize> {
+        Some(req.1)
+    }
+}
+
+/// A request body, which records whether it has begun streaming.
+///
+/// Clones share the flag, as clones of a streaming body share its source.
+#[derive(Clone, Debug, Default)]
+struct Stream(Arc<AtomicBool>);
+
+type StreamMock = tower_mock::Mock<Stream, Res, InnerError>;
+
+#[derive(Clone)]
+struct RetryStreams;
+
+impl Policy<Stream, Res, Error> for RetryStreams {
+    type Future = future::FutureResult<Self, ()>;
+    fn retry(&self, _: &Stream, result: Result<&Res, &Error>) -> Option<Self::Future> {
+        if result.is_err() {
+            Some(future::ok(RetryStreams))
+        } else {
+            None
+        }
+    }
+
+    fn clone_request(&self, req: &Stream) -> Option<Stream> {
+        Some(req.clone())
+    }
+
+    fn is_replayable(&self, req: &Stream) -> bool {
+        !req.0.load(Ordering::SeqCst)
+    }
+}
+
 fn new_service<P: Policy<Req, Res, Error> + Clone>(policy: P) -> (tower_retry::Retry<P, Mock>, Handle) {
     let (service, handle) = Mock::new();
     let service = tower_retry::Retry::new(policy, service);
     (service, handle)
 }
 
+fn new_observed_service<P>(policy: P) -> (tower_retry::Retry<P, Mock>, Handle, Arc<Mutex<Vec<Outcome>>>)
+where
+    P: Policy<Req, Res, Error> + Clone,
+{
+    let (service, handle) = new_service(policy);
+    let outcomes = Arc::new(Mutex::new(Vec::new()));
+    let service = {
+        let outcomes = outcomes.clone();
+        service.on_outcome(move |outcome| outcomes.lock().unwrap().push(outcome))
+    };
+    (service, handle, outcomes)
+}
+
+fn clone_req(req: &Req) -> Option<Req> {
+    Some(*req)
+}
+
+fn is_unavailable(error: &Error) -> bool {
+    *error == tower_mock::Error::Other("unavailable")
+}
+
+/// Asks for the request to be retried after 250ms, as a `Retry-After` might.
+fn server_delay(error: &Error) -> Option<Duration> {
+    match *error {
+        tower_mock::Error::Other("slow down") => Some(Duration::from_millis(250)),
+        _ => None,
+    }
+}
+
 fn assert_not_ready<F: Future>(f: &mut F) where F::Error: ::std::fmt::Debug {
     use futures::future;
     future::poll_fn(|| {
@@ -2,23 +2,71 @@
 //!
 //! If the response does not complete within the specified timeout, the response
 //! will be aborted.
+//!
+//! To bound only the wait for a service to become ready, rather than its
+//! responses, see `PollReadyTimeout`.
+//!
+//! Responses that are delivered incrementally may instead be bounded by an idle
+//! timeout: see `Timeout::streaming`.
+//!
+//! By default, timeouts are driven by the default timer of the current
+//! execution context. A specific timer may be used instead, e.g. a timer driven
+//! by a mock clock in tests: see `Timeout::with_timer`.
 
 extern crate futures;
 extern crate tower_service;
 extern crate tokio_timer;
+#[cfg(any(test, feature = "test-util"))]
+extern crate tokio_executor;
 
-use futures::{Future, Poll, Async};
+use futures::{Future, Poll, Async, Stream};
+use futures::future::MapErr;
 use tower_service::Service;
-use tokio_timer::{clock, Delay};
+use tokio_timer::{clock, timer, Delay};
 
 use std::{error, fmt};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
+/// A handle to a timer, as accepted by `Timeout::with_timer`.
+pub use tokio_timer::timer::Handle as TimerHandle;
 
 /// Applies a timeout to requests.
 #[derive(Debug)]
 pub struct Timeout<T> {
     inner: T,
     timeout: Duration,
+    idle: bool,
+    /// Drives timeouts, if not the default timer.
+    timer: Option<timer::Handle>,
+}
+
+/// Fails requests that do not complete by an absolute deadline.
+///
+/// This is useful when a deadline has been computed upstream, e.g. propagated
+/// from a caller, rather than configured as a duration.
+#[derive(Debug)]
+pub struct WithDeadline<T> {
+    inner: T,
+    deadline: Instant,
+    /// Drives timeouts, if not the default timer.
+    timer: Option<timer::Handle>,
+}
+
+/// Fails `poll_ready` if the inner service does not become ready in time.
+///
+/// Only the wait for readiness is bounded: once the service is ready, the
+/// timer is reset, and responses are not timed.
+#[derive(Debug)]
+pub struct PollReadyTimeout<T> {
+    inner: T,
+    timeout: Duration,
+    /// Started when the inner service is first observed not ready.
+    sleep: Option<Delay>,
+    /// Drives timeouts, if not the default timer.
+    timer: Option<timer::Handle>,
 }
 
 /// Errors produced by `Timeout`.
@@ -29,24 +77,72 @@ pub enum Error<T> {
 
     /// The request did not complete within the specified timeout.
     Timeout,
+
+    /// The service did not become ready within the specified timeout.
+    ReadyTimeout,
 }
 
 /// `Timeout` response future
+///
+/// When the inner response is a `Stream`, `ResponseFuture` is also a `Stream`
+/// and each item yielded counts as progress. A response that is only a
+/// `Future` is treated as a stream of a single item.
 #[derive(Debug)]
 pub struct ResponseFuture<T> {
     response: T,
     sleep: Delay,
+    /// When set, the deadline is pushed back by this much after each item.
+    idle: Option<Duration>,
 }
 
 // ===== impl Timeout =====
 
 impl<T> Timeout<T> {
+    /// Fails responses that do not complete within `timeout`.
     pub fn new(inner: T, timeout: Duration) -> Self {
         Timeout {
             inner,
             timeout,
+            idle: false,
+            timer: None,
+        }
+    }
+
+    /// Fails responses that go longer than `timeout` without making progress.
+    ///
+    /// The deadline is reset each time a streaming response yields an item, so
+    /// a long-lived stream is only aborted once it stalls. For a response that
+    /// is only a `Future`, this is equivalent to `Timeout::new`.
+    pub fn streaming(inner: T, timeout: Duration) -> Self {
+        Timeout {
+            inner,
+            timeout,
+            idle: true,
+            timer: None,
+        }
+    }
+
+    /// Uses `timer` to drive timeouts, rather than the default timer.
+    ///
+    /// Deadlines are still computed from `tokio_timer::clock::now`, so `timer`
+    /// should share the default clock. This allows a test to trip a timeout
+    /// deterministically by advancing a mock clock and turning the timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        Timeout {
+            timer: Some(timer),
+            ..self
         }
     }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
 }
 
 impl<S, Request> Service<Request> for Timeout<S>
@@ -65,11 +161,121 @@ where
     fn call(&mut self, request: Request) -> Self::Future {
         ResponseFuture {
             response: self.inner.call(request),
-            sleep: Delay::new(clock::now() + self.timeout),
+            sleep: delay(&self.timer, clock::now() + self.timeout),
+            idle: if self.idle { Some(self.timeout) } else { None },
+        }
+    }
+}
+
+// ===== impl WithDeadline =====
+
+impl<T> WithDeadline<T> {
+    /// Fails responses that do not complete by `deadline`.
+    ///
+    /// If `deadline` has already passed, every response fails with
+    /// `Error::Timeout` unless it is immediately available.
+    pub fn new(inner: T, deadline: Instant) -> Self {
+        WithDeadline {
+            inner,
+            deadline,
+            timer: None,
+        }
+    }
+
+    /// Uses `timer` to drive timeouts, rather than the default timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        WithDeadline {
+            timer: Some(timer),
+            ..self
+        }
+    }
+
+    /// Returns the deadline applied to responses.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl<S, Request> Service<Request> for WithDeadline<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = Error<S::Error>;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+            .map_err(Error::Inner)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        ResponseFuture {
+            response: self.inner.call(request),
+            sleep: delay(&self.timer, self.deadline),
+            idle: None,
         }
     }
 }
 
+// ===== impl PollReadyTimeout =====
+
+impl<T> PollReadyTimeout<T> {
+    /// Fails `poll_ready` if `inner` is not ready within `timeout`.
+    pub fn new(inner: T, timeout: Duration) -> Self {
+        PollReadyTimeout {
+            inner,
+            timeout,
+            sleep: None,
+            timer: None,
+        }
+    }
+
+    /// Uses `timer` to drive timeouts, rather than the default timer.
+    pub fn with_timer(self, timer: timer::Handle) -> Self {
+        PollReadyTimeout {
+            timer: Some(timer),
+            ..self
+        }
+    }
+}
+
+impl<S, Request> Service<Request> for PollReadyTimeout<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = Error<S::Error>;
+    type Future = MapErr<S::Future, fn(S::Error) -> Error<S::Error>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        match self.inner.poll_ready() {
+            Ok(Async::NotReady) => {}
+            ready => {
+                self.sleep = None;
+                return ready.map_err(Error::Inner);
+            }
+        }
+
+        if self.sleep.is_none() {
+            let sleep = delay(&self.timer, clock::now() + self.timeout);
+            self.sleep = Some(sleep);
+        }
+
+        match self.sleep.as_mut().expect("sleep").poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(_)) | Err(_) => {
+                self.sleep = None;
+                Err(Error::ReadyTimeout)
+            }
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.inner.call(request).map_err(Error::Inner as fn(_) -> _)
+    }
+}
+
 // ===== impl ResponseFuture =====
 
 impl<T> Future for ResponseFuture<T>
@@ -86,6 +292,36 @@ where T: Future,
             Err(e) => return Err(Error::Inner(e)),
         }
 
+        self.poll_sleep()
+    }
+}
+
+impl<T> Stream for ResponseFuture<T>
+where T: Stream,
+{
+    type Item = T::Item;
+    type Error = Error<T::Error>;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // First, try polling the stream
+        match self.response.poll() {
+            Ok(Async::Ready(Some(v))) => {
+                if let Some(idle) = self.idle {
+                    self.sleep.reset(clock::now() + idle);
+                }
+                return Ok(Async::Ready(Some(v)));
+            }
+            Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+            Ok(Async::NotReady) => {}
+            Err(e) => return Err(Error::Inner(e)),
+        }
+
+        self.poll_sleep()
+    }
+}
+
+impl<T> ResponseFuture<T> {
+    fn poll_sleep<U, E>(&mut self) -> Poll<U, Error<E>> {
         // Now check the sleep
         match self.sleep.poll() {
             Ok(Async::NotReady) => Ok(Async::NotReady),
@@ -106,6 +342,7 @@ where
         match *self {
             Error::Inner(ref why) => fmt::Display::fmt(why, f),
             Error::Timeout => f.pad("request timed out"),
+            Error::ReadyTimeout => f.pad("service not ready in time"),
         }
     }
 }
@@ -126,7 +363,340 @@ where
         match *self {
             Error::Inner(_) => "inner service error",
             Error::Timeout => "request timed out",
+            Error::ReadyTimeout => "service not ready in time",
+        }
+    }
+
+}
+
+/// Returns a `Delay` until `at`, driven by `timer` if set, or else by the
+/// default timer.
+fn delay(timer: &Option<timer::Handle>, at: Instant) -> Delay {
+    match *timer {
+        Some(ref timer) => timer.delay(at),
+        None => Delay::new(at),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use super::*;
+    use test_util::{with_mock_clock, MockPark};
+
+    /// A response that may be consumed whole or item by item.
+    struct Chunks(VecDeque<&'static str>);
+
+    impl Future for Chunks {
+        type Item = Vec<&'static str>;
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<Self::Item, ()> {
+            Ok(Async::Ready(self.0.drain(..).collect()))
+        }
+    }
+
+    impl Stream for Chunks {
+        type Item = &'static str;
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<Option<Self::Item>, ()> {
+            Ok(Async::Ready(self.0.pop_front()))
+        }
+    }
+
+    struct Svc;
+
+    impl Service<()> for Svc {
+        type Response = Vec<&'static str>;
+        type Error = ();
+        type Future = Chunks;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, _: ()) -> Chunks {
+            Chunks(vec!["a", "b"].into_iter().collect())
+        }
+    }
+
+    #[test]
+    fn unary() {
+        with_mock_clock(|_| {
+            let mut svc = Timeout::streaming(Svc, Duration::from_secs(1));
+            let mut rsp = svc.call(());
+            match Future::poll(&mut rsp) {
+                Ok(Async::Ready(items)) => assert_eq!(items, vec!["a", "b"]),
+                _ => panic!("response should be ready"),
+            }
+        });
+    }
+
+    #[test]
+    fn streaming_resets_deadline() {
+        with_mock_clock(|time| {
+            let mut svc = Timeout::streaming(Svc, Duration::from_secs(1));
+            let mut rsp = svc.call(());
+            let start = rsp.sleep.deadline();
+
+            *time.as_mut() += Duration::from_millis(500);
+            assert_eq!(Stream::poll(&mut rsp).unwrap(), Async::Ready(Some("a")));
+            assert_eq!(rsp.sleep.deadline(), start + Duration::from_millis(500));
+
+            *time.as_mut() += Duration::from_millis(500);
+            assert_eq!(Stream::poll(&mut rsp).unwrap(), Async::Ready(Some("b")));
+            assert_eq!(rsp.sleep.deadline(), start + Duration::from_secs(1));
+
+            assert_eq!(Stream::poll(&mut rsp).unwrap(), Async::Ready(None));
+        });
+    }
+
+    #[test]
+    fn total_deadline_is_fixed() {
+        with_mock_clock(|time| {
+            let mut svc = Timeout::new(Svc, Duration::from_secs(1));
+            let mut rsp = svc.call(());
+            let start = rsp.sleep.deadline();
+
+            *time.as_mut() += Duration::from_millis(500);
+            assert_eq!(Stream::poll(&mut rsp).unwrap(), Async::Ready(Some("a")));
+            assert_eq!(rsp.sleep.deadline(), start);
+        });
+    }
+
+    /// A streaming response whose items arrive when they are pushed to the
+    /// shared queue.
+    struct Trickle(Rc<RefCell<VecDeque<&'static str>>>);
+
+    impl Future for Trickle {
+        type Item = ();
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<(), ()> {
+            Ok(Async::NotReady)
+        }
+    }
+
+    impl Stream for Trickle {
+        type Item = &'static str;
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<Option<Self::Item>, ()> {
+            match self.0.borrow_mut().pop_front() {
+                Some(item) => Ok(Async::Ready(Some(item))),
+                None => Ok(Async::NotReady),
+            }
         }
     }
 
+    impl Service<()> for Trickle {
+        type Response = ();
+        type Error = ();
+        type Future = Trickle;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, _: ()) -> Trickle {
+            Trickle(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn streaming_fails_only_when_idle() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let items = Rc::new(RefCell::new(VecDeque::new()));
+            let mut svc = Timeout::streaming(Trickle(items.clone()), Duration::from_secs(1))
+                .with_timer(timer.handle());
+            let mut rsp = svc.call(());
+
+            let mut poll = || future::lazy(|| Ok::<_, ()>(Stream::poll(&mut rsp))).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            // Items that keep arriving keep the response alive past the timeout.
+            for &item in &["a", "b", "c"] {
+                *time.as_mut() += Duration::from_millis(999);
+                timer.turn(None).unwrap();
+                items.borrow_mut().push_back(item);
+                assert_eq!(poll().unwrap(), Async::Ready(Some(item)));
+            }
+            assert!(poll().unwrap().is_not_ready());
+
+            // Once items stop arriving, the response fails after an idle gap.
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::Timeout) => {}
+                _ => panic!("idle response should have timed out"),
+            }
+        });
+    }
+
+    /// Never responds.
+    struct Pending;
+
+    impl Service<()> for Pending {
+        type Response = ();
+        type Error = ();
+        type Future = future::Empty<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::empty()
+        }
+    }
+
+    #[test]
+    fn mock_timer_trips_timeout() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let mut svc = Timeout::new(Pending, Duration::from_secs(1)).with_timer(timer.handle());
+            let mut rsp = svc.call(());
+
+            let mut poll = || future::lazy(|| Ok::<_, ()>(Future::poll(&mut rsp))).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::Timeout) => {}
+                _ => panic!("response should have timed out"),
+            }
+        });
+    }
+
+    #[test]
+    fn past_deadline_times_out_immediately() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let deadline = clock::now();
+            *time.as_mut() += Duration::from_secs(1);
+
+            let mut svc = WithDeadline::new(Pending, deadline).with_timer(timer.handle());
+            let mut rsp = svc.call(());
+
+            let mut poll = || future::lazy(|| Ok::<_, ()>(Future::poll(&mut rsp))).wait().unwrap();
+            if poll().is_ok() {
+                // The elapsed deadline is observed on the timer's next turn.
+                timer.turn(None).unwrap();
+            }
+            match poll() {
+                Err(Error::Timeout) => {}
+                _ => panic!("response should have timed out"),
+            }
+        });
+    }
+
+    #[test]
+    fn future_deadline_times_out_when_reached() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let deadline = clock::now() + Duration::from_secs(1);
+
+            let mut svc = WithDeadline::new(Pending, deadline).with_timer(timer.handle());
+            let mut rsp = svc.call(());
+
+            let mut poll = || future::lazy(|| Ok::<_, ()>(Future::poll(&mut rsp))).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::Timeout) => {}
+                _ => panic!("response should have timed out"),
+            }
+        });
+    }
+
+    /// Ready only while the shared flag is set.
+    struct Gated(Rc<Cell<bool>>);
+
+    impl Service<()> for Gated {
+        type Response = ();
+        type Error = ();
+        type Future = future::FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            if self.0.get() {
+                Ok(().into())
+            } else {
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(())
+        }
+    }
+
+    #[test]
+    fn not_ready_past_timeout() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let gated = Gated(Rc::new(Cell::new(false)));
+            let mut svc = PollReadyTimeout::new(gated, Duration::from_secs(1))
+                .with_timer(timer.handle());
+
+            let mut poll = || {
+                future::lazy(|| Ok::<_, ()>(Service::<()>::poll_ready(&mut svc))).wait().unwrap()
+            };
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_secs(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::ReadyTimeout) => {}
+                _ => panic!("poll_ready should have timed out"),
+            }
+        });
+    }
+
+    #[test]
+    fn ready_in_time_resets_timer() {
+        with_mock_clock(|time| {
+            let mut timer = timer::Timer::new_with_now(MockPark, time.clone());
+            let ready = Rc::new(Cell::new(false));
+            let mut svc = PollReadyTimeout::new(Gated(ready.clone()), Duration::from_secs(1))
+                .with_timer(timer.handle());
+
+            let mut poll = || {
+                future::lazy(|| Ok::<_, ()>(Service::<()>::poll_ready(&mut svc))).wait().unwrap()
+            };
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            ready.set(true);
+            assert!(poll().unwrap().is_ready());
+
+            // A later wait for readiness gets the full timeout again.
+            ready.set(false);
+            assert!(poll().unwrap().is_not_ready());
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(poll().unwrap().is_not_ready());
+        });
+    }
 }
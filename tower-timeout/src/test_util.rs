@@ -0,0 +1,86 @@
+//! Utilities for testing middleware that is driven by a timer.
+//!
+//! This module is only available when the `test-util` feature is enabled.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio_executor::enter;
+use tokio_executor::park::{Park, Unpark};
+use tokio_timer::{self, clock, timer};
+
+/// A clock whose time only advances when it is set.
+#[derive(Clone, Debug)]
+pub struct MockNow(Arc<Mutex<Instant>>);
+
+/// Parks without blocking, so that a timer may be turned on demand.
+#[derive(Debug)]
+pub struct MockPark;
+
+/// Unparks a `MockPark`, which does nothing.
+#[derive(Debug)]
+pub struct MockUnpark;
+
+/// Runs `f` with a mock clock as the default clock.
+pub fn with_mock_clock<F: FnOnce(&MockNow)>(f: F) {
+    let time = MockNow(Arc::new(Mutex::new(Instant::now())));
+    let clock = clock::Clock::new_with_now(time.clone());
+    clock::with_default(&clock, &mut enter().unwrap(), |_| f(&time));
+}
+
+/// Returns a timer driven by `time`, which is turned on demand.
+pub fn mock_timer(time: &MockNow) -> timer::Timer<MockPark, MockNow> {
+    timer::Timer::new_with_now(MockPark, time.clone())
+}
+
+/// Runs `f` with a mock clock, and a default timer driven by it that is turned
+/// on demand.
+pub fn with_mock_timer<F>(f: F)
+where
+    F: FnOnce(&MockNow, &mut timer::Timer<MockPark, MockNow>),
+{
+    let time = MockNow(Arc::new(Mutex::new(Instant::now())));
+    let clock = clock::Clock::new_with_now(time.clone());
+    clock::with_default(&clock, &mut enter().unwrap(), |enter| {
+        let mut timer = mock_timer(&time);
+        let handle = timer.handle();
+        tokio_timer::with_default(&handle, enter, |_| f(&time, &mut timer))
+    });
+}
+
+// ===== impl MockNow =====
+
+impl MockNow {
+    /// Returns the current time, which may be advanced through the guard.
+    pub fn as_mut(&self) -> MutexGuard<Instant> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl clock::Now for MockNow {
+    fn now(&self) -> Instant {
+        *self.0.lock().expect("now")
+    }
+}
+
+// ===== impl MockPark =====
+
+impl Park for MockPark {
+    type Unpark = MockUnpark;
+    type Error = ();
+
+    fn unpark(&self) -> MockUnpark {
+        MockUnpark
+    }
+
+    fn park(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn park_timeout(&mut self, _: Duration) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+impl Unpark for MockUnpark {
+    fn unpark(&self) {}
+}
@@ -5,11 +5,13 @@
 //! be useful when the service instance cannot be explicitly named for whatever
 //! reason.
 //!
-//! There are two variants of service objects. `BoxService` requires both the
+//! There are three variants of service objects. `BoxService` requires both the
 //! service and the response future to be `Send`. These values can move freely
 //! across threads. `UnsyncBoxService` requires both the service and the
 //! response future to remain on the current thread. This is useful for
 //! representing services that are backed by `Rc` or other non-`Send` types.
+//! `CloneBoxService` is a `BoxService` that may also be cloned, e.g. so that
+//! it may be provided to a balancer through `Discover`.
 //!
 //! # Examples
 //!
@@ -58,7 +60,9 @@
 use futures::{Future, Poll};
 use tower_service::Service;
 
+use std::error::Error;
 use std::fmt;
+use std::marker::PhantomData;
 
 /// A boxed `Service + Send` trait object.
 ///
@@ -73,12 +77,39 @@ pub struct BoxService<T, U, E> {
                             Future = BoxFuture<U, E>> + Send>,
 }
 
+/// A boxed `Service + Clone + Send` trait object.
+///
+/// `CloneBoxService` is like `BoxService`, except that the erased service may
+/// be cloned. Each clone boxes a clone of the underlying service.
+///
+/// See module level documentation for more details.
+pub struct CloneBoxService<T, U, E> {
+    inner: Box<CloneService<T, U, E> + Send>,
+}
+
+/// Boxes the response futures of a service, keeping the service itself.
+///
+/// Unlike `BoxService`, the service type stays concrete and only its response
+/// future is erased, into a `BoxFuture`. This gives services with unnameable
+/// futures, e.g. built from closures, a nameable `Future` type at the cost of
+/// an allocation per request.
+pub struct BoxedFuture<S, R> {
+    inner: S,
+    _p: PhantomData<fn(R)>,
+}
+
 /// A boxed `Future + Send` trait object.
 ///
 /// This type alias represents a boxed future that is `Send` and can be moved
 /// across threads.
 pub type BoxFuture<T, E> = Box<Future<Item = T, Error = E> + Send>;
 
+/// A boxed `Error + Send + Sync` trait object.
+///
+/// This type alias is used to unify the error types of services. See
+/// `ServiceExt::err_into_boxed`.
+pub type BoxError = Box<Error + Send + Sync>;
+
 /// A boxed `Service` trait object.
 pub struct UnsyncBoxService<T, U, E> {
     inner: Box<Service<T, Response = U,
@@ -97,6 +128,11 @@ struct Boxed<S> {
     inner: S,
 }
 
+/// A `Service` that may be cloned into a new trait object.
+trait CloneService<T, U, E>: Service<T, Response = U, Error = E, Future = BoxFuture<U, E>> {
+    fn clone_box(&self) -> Box<CloneService<T, U, E> + Send>;
+}
+
 #[derive(Debug)]
 struct UnsyncBoxed<S> {
     inner: S,
@@ -140,6 +176,50 @@ where T: fmt::Debug,
     }
 }
 
+// ===== impl CloneBoxService =====
+
+impl<T, U, E> CloneBoxService<T, U, E>
+{
+    pub fn new<S>(inner: S) -> Self
+        where S: Service<T, Response = U, Error = E> + Clone + Send + 'static,
+              S::Future: Send + 'static,
+    {
+        let inner = Box::new(Boxed { inner });
+        CloneBoxService { inner }
+    }
+}
+
+impl<T, U, E> Service<T> for CloneBoxService<T, U, E> {
+    type Response = U;
+    type Error = E;
+    type Future = BoxFuture<U, E>;
+
+    fn poll_ready(&mut self) -> Poll<(), E> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, request: T) -> BoxFuture<U, E> {
+        self.inner.call(request)
+    }
+}
+
+impl<T, U, E> Clone for CloneBoxService<T, U, E> {
+    fn clone(&self) -> Self {
+        CloneBoxService { inner: self.inner.clone_box() }
+    }
+}
+
+impl<T, U, E> fmt::Debug for CloneBoxService<T, U, E>
+where T: fmt::Debug,
+      U: fmt::Debug,
+      E: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CloneBoxService")
+            .finish()
+    }
+}
+
 // ===== impl UnsyncBoxService =====
 
 impl<T, U, E> UnsyncBoxService<T, U, E> {
@@ -177,6 +257,58 @@ where T: fmt::Debug,
     }
 }
 
+// ===== impl BoxedFuture =====
+
+impl<S, R> BoxedFuture<S, R> {
+    pub fn new(inner: S) -> Self
+        where S: Service<R>,
+              S::Future: Send + 'static,
+    {
+        BoxedFuture { inner, _p: PhantomData }
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S, R> Service<R> for BoxedFuture<S, R>
+where S: Service<R>,
+      S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        Box::new(self.inner.call(request))
+    }
+}
+
+impl<S: Clone, R> Clone for BoxedFuture<S, R> {
+    fn clone(&self) -> Self {
+        BoxedFuture { inner: self.inner.clone(), _p: PhantomData }
+    }
+}
+
+impl<S: fmt::Debug, R> fmt::Debug for BoxedFuture<S, R> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("BoxedFuture")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
 // ===== impl Boxed =====
 
 impl<S, Request> Service<Request> for Boxed<S>
@@ -197,6 +329,15 @@ where S: Service<Request> + 'static,
     }
 }
 
+impl<S, Request> CloneService<Request, S::Response, S::Error> for Boxed<S>
+where S: Service<Request> + Clone + Send + 'static,
+      S::Future: Send + 'static,
+{
+    fn clone_box(&self) -> Box<CloneService<Request, S::Response, S::Error> + Send> {
+        Box::new(Boxed { inner: self.inner.clone() })
+    }
+}
+
 // ===== impl UnsyncBoxed =====
 
 impl<S, Request> Service<Request> for UnsyncBoxed<S>
@@ -216,3 +357,118 @@ where S: Service<Request> + 'static,
         Box::new(self.inner.call(request))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::{Async, Future};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Responds with its name and counts requests across clones.
+    #[derive(Clone)]
+    struct Named(&'static str, Arc<AtomicUsize>);
+
+    impl Service<()> for Named {
+        type Response = &'static str;
+        type Error = ();
+        type Future = FutureResult<&'static str, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            future::ok(self.0)
+        }
+    }
+
+    #[test]
+    fn boxed_clone_issues_requests_from_clones() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut a = Named("a", count.clone()).boxed_clone();
+        let mut b = a.clone();
+
+        assert_eq!(a.call(()).wait(), Ok("a"));
+        assert_eq!(b.call(()).wait(), Ok("a"));
+        drop(a);
+        assert_eq!(b.call(()).wait(), Ok("a"));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn clone_box_clones_dispatch_to_the_same_service() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let svc = Named("a", count.clone()).clone_box();
+
+        let mut clones = vec![svc.clone(), svc.clone(), svc];
+        clones.push(clones[0].clone());
+        for svc in clones.iter_mut() {
+            assert!(svc.poll_ready().unwrap().is_ready());
+            assert_eq!(svc.call(()).wait(), Ok("a"));
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn boxed_clone_unifies_service_types() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let ok = Named("ok", count.clone()).boxed_clone();
+        let mapped = Named("mapped", count.clone())
+            .map(|name: &'static str| &name[..3])
+            .boxed_clone();
+
+        let services: Vec<CloneBoxService<(), &'static str, ()>> = vec![ok, mapped];
+        let rsps = services
+            .iter()
+            .cloned()
+            .map(|mut svc| svc.call(()).wait().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(rsps, vec!["ok", "map"]);
+    }
+
+    /// Responds with its name, through a future combinator chain.
+    struct Greeter(&'static str);
+
+    impl Service<()> for Greeter {
+        type Response = String;
+        type Error = ();
+        type Future = future::Map<FutureResult<&'static str, ()>, fn(&'static str) -> String>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            fn greet(name: &'static str) -> String {
+                format!("hello, {}", name)
+            }
+            future::ok(self.0).map(greet as fn(_) -> _)
+        }
+    }
+
+    /// Holds a service and its in-flight response with nameable types.
+    struct Client {
+        service: BoxedFuture<Greeter, ()>,
+        pending: Option<BoxFuture<String, ()>>,
+    }
+
+    #[test]
+    fn boxed_future_keeps_service_concrete() {
+        let mut client = Client {
+            service: Greeter("world").boxed_future(),
+            pending: None,
+        };
+
+        assert!(client.service.poll_ready().unwrap().is_ready());
+        client.pending = Some(client.service.call(()));
+        assert_eq!(client.service.get_ref().0, "world");
+
+        let rsp = client.pending.take().unwrap().wait();
+        assert_eq!(rsp, Ok("hello, world".to_string()));
+    }
+}
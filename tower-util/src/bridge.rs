@@ -0,0 +1,320 @@
+//! Contains `Bridge` and related types and functions.
+//!
+//! See `Bridge` documentation for more details.
+
+use futures::stream::FuturesUnordered;
+use futures::sync::{mpsc, oneshot};
+use futures::{Async, Future, Poll, Stream};
+use tower_service::Service;
+
+use std::{error, fmt, io, thread};
+
+/// A `Send` handle to a service that is not `Send`, which runs on a dedicated
+/// thread.
+///
+/// The inner service is built on, and never leaves, a worker thread. Requests
+/// are sent to it over a channel, so the handle may be cloned and used from
+/// any thread, e.g. from tasks on a multithreaded runtime. This is similar to
+/// `Buffer`, except that the inner service is never moved to an executor.
+///
+/// The worker drives the inner service and its response futures without a
+/// runtime, so they must not depend on one, e.g. on a reactor or timer.
+pub struct Bridge<Request, Response, E> {
+    tx: mpsc::Sender<Message<Request, Response, E>>,
+}
+
+/// Response future returned by `Bridge`.
+pub struct ResponseFuture<T, E> {
+    rx: Option<oneshot::Receiver<Result<T, E>>>,
+}
+
+/// Error produced by `Bridge` responding to a request.
+#[derive(Debug)]
+pub enum Error<T> {
+    /// The inner service produced an error.
+    Inner(T),
+    /// The worker has stopped, e.g. because the inner service failed.
+    Closed,
+}
+
+struct Message<Request, Response, E> {
+    request: Request,
+    tx: oneshot::Sender<Result<Response, E>>,
+}
+
+/// Drives the inner service on the worker thread.
+struct Worker<S, Request>
+where
+    S: Service<Request>,
+{
+    service: S,
+    rx: Option<mpsc::Receiver<Message<Request, S::Response, S::Error>>>,
+    /// A request that has been received but not yet dispatched.
+    pending: Option<Message<Request, S::Response, S::Error>>,
+    in_flight: FuturesUnordered<Dispatch<S::Future>>,
+}
+
+/// Sends the result of a response future back to its caller.
+struct Dispatch<F: Future> {
+    fut: F,
+    tx: Option<oneshot::Sender<Result<F::Item, F::Error>>>,
+}
+
+// ===== impl Bridge =====
+
+impl<Request, Response, E> Bridge<Request, Response, E>
+where
+    Request: Send + 'static,
+    Response: Send + 'static,
+    E: Send + 'static,
+{
+    /// Spawns a worker thread that builds a service with `make_service`.
+    ///
+    /// `bound` gives the maximal number of requests that can be queued for the
+    /// service before backpressure is applied to callers.
+    pub fn spawn<S, F>(make_service: F, bound: usize) -> io::Result<Self>
+    where
+        F: FnOnce() -> S + Send + 'static,
+        S: Service<Request, Response = Response, Error = E> + 'static,
+    {
+        let (tx, rx) = mpsc::channel(bound);
+
+        thread::Builder::new()
+            .name("tower-bridge".into())
+            .spawn(move || {
+                let worker = Worker {
+                    service: make_service(),
+                    rx: Some(rx),
+                    pending: None,
+                    in_flight: FuturesUnordered::new(),
+                };
+                let _ = worker.wait();
+            })?;
+
+        Ok(Bridge { tx })
+    }
+}
+
+impl<Request, Response, E> Service<Request> for Bridge<Request, Response, E> {
+    type Response = Response;
+    type Error = Error<E>;
+    type Future = ResponseFuture<Response, E>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.tx.poll_ready().map_err(|_| Error::Closed)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let (tx, rx) = oneshot::channel();
+        let rx = match self.tx.try_send(Message { request, tx }) {
+            Ok(()) => Some(rx),
+            Err(_) => None,
+        };
+        ResponseFuture { rx }
+    }
+}
+
+impl<Request, Response, E> Clone for Bridge<Request, Response, E> {
+    fn clone(&self) -> Self {
+        Bridge {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<Request, Response, E> fmt::Debug for Bridge<Request, Response, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bridge").finish()
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<T, E> Future for ResponseFuture<T, E> {
+    type Item = T;
+    type Error = Error<E>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let rx = match self.rx {
+            Some(ref mut rx) => rx,
+            None => return Err(Error::Closed),
+        };
+
+        match rx.poll() {
+            Ok(Async::Ready(Ok(rsp))) => Ok(Async::Ready(rsp)),
+            Ok(Async::Ready(Err(e))) => Err(Error::Inner(e)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => Err(Error::Closed),
+        }
+    }
+}
+
+// ===== impl Worker =====
+
+impl<S, Request> Worker<S, Request>
+where
+    S: Service<Request>,
+{
+    /// Dispatches received requests until the inner service is not ready, or
+    /// no more requests are available.
+    fn dispatch(&mut self) {
+        loop {
+            if let Some(msg) = self.pending.take() {
+                match self.service.poll_ready() {
+                    Ok(Async::Ready(())) => {
+                        let fut = self.service.call(msg.request);
+                        self.in_flight.push(Dispatch {
+                            fut,
+                            tx: Some(msg.tx),
+                        });
+                    }
+                    Ok(Async::NotReady) => {
+                        self.pending = Some(msg);
+                        return;
+                    }
+                    Err(e) => {
+                        // The service has failed, so no further requests are
+                        // accepted. Requests that are already queued fail with
+                        // `Error::Closed` as the receiver is dropped.
+                        let _ = msg.tx.send(Err(e));
+                        self.rx = None;
+                        return;
+                    }
+                }
+            }
+
+            let msg = match self.rx {
+                Some(ref mut rx) => rx.poll(),
+                None => return,
+            };
+            match msg {
+                Ok(Async::Ready(Some(msg))) => self.pending = Some(msg),
+                Ok(Async::Ready(None)) | Err(()) => {
+                    self.rx = None;
+                    return;
+                }
+                Ok(Async::NotReady) => return,
+            }
+        }
+    }
+}
+
+impl<S, Request> Future for Worker<S, Request>
+where
+    S: Service<Request>,
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        self.dispatch();
+
+        while let Async::Ready(Some(())) = self.in_flight.poll()? {}
+
+        if self.rx.is_none() && self.pending.is_none() && self.in_flight.is_empty() {
+            return Ok(Async::Ready(()));
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+// ===== impl Dispatch =====
+
+impl<F: Future> Future for Dispatch<F> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let result = match self.fut.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(rsp)) => Ok(rsp),
+            Err(e) => Err(e),
+        };
+
+        let tx = self.tx.take().expect("polled after complete");
+        // The caller may have dropped its response future.
+        let _ = tx.send(result);
+        Ok(Async::Ready(()))
+    }
+}
+
+// ===== impl Error =====
+
+impl<T> fmt::Display for Error<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Inner(ref why) => fmt::Display::fmt(why, f),
+            Error::Closed => f.pad("bridge closed"),
+        }
+    }
+}
+
+impl<T> error::Error for Error<T>
+where
+    T: error::Error,
+{
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Inner(ref why) => Some(why),
+            Error::Closed => None,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match *self {
+            Error::Inner(_) => "inner service error",
+            Error::Closed => "bridge closed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Numbers each request, sharing its count through an `Rc`.
+    struct Counter(Rc<Cell<usize>>);
+
+    impl Service<&'static str> for Counter {
+        type Response = String;
+        type Error = ();
+        type Future = FutureResult<String, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            future::ok(format!("{} {}", req, self.0.get()))
+        }
+    }
+
+    #[test]
+    fn drives_unsend_service_from_another_thread() {
+        let bridge = Bridge::spawn(|| Counter(Rc::new(Cell::new(0))), 4).unwrap();
+
+        let handle = {
+            let bridge = bridge.clone();
+            thread::spawn(move || {
+                bridge
+                    .ready()
+                    .and_then(|mut bridge| bridge.call("hello"))
+                    .wait()
+                    .unwrap()
+            })
+        };
+        assert_eq!(handle.join().unwrap(), "hello 1");
+
+        let rsp = bridge.ready().and_then(|mut bridge| bridge.call("again")).wait();
+        assert_eq!(rsp.unwrap(), "again 2");
+    }
+}
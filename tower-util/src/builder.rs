@@ -0,0 +1,407 @@
+//! Builds a stack of middleware around a service, one layer at a time.
+//!
+//! Layers are accumulated in nested tuples, so the type of the resulting
+//! service is fully known, and each layer may be inspected before it is
+//! applied.
+
+use tower_service::Service;
+
+#[cfg(feature = "retry")]
+use tower_retry::{Policy, Retry};
+#[cfg(feature = "timeout")]
+use tower_timeout::{Timeout, TimerHandle};
+#[cfg(feature = "rate-limit")]
+use tower_rate_limit::{Rate, RateLimit};
+#[cfg(feature = "rate-limit")]
+use tokio_timer::Timer;
+#[cfg(feature = "buffer")]
+use tower_buffer::Buffer;
+
+#[cfg(any(feature = "timeout", feature = "rate-limit"))]
+use std::time::Duration;
+
+/// Wraps a service in middleware.
+pub trait Layer<S, Request> {
+    /// The wrapped service.
+    type Service: Service<Request>;
+
+    /// Wraps `inner`.
+    fn layer(&self, inner: S) -> Self::Service;
+}
+
+/// Builds a stack of middleware around a service.
+///
+/// Layers are applied in the order they are added: the first layer added is
+/// the outermost, and receives requests first.
+///
+/// ```ignore
+/// let svc = ServiceBuilder::new()
+///     .timeout(Duration::from_secs(1))
+///     .retry(policy)
+///     .service(inner);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServiceBuilder<L> {
+    layers: L,
+}
+
+/// Applies `Timeout`.
+#[cfg(feature = "timeout")]
+#[derive(Clone, Debug)]
+pub struct TimeoutLayer {
+    timeout: Duration,
+    /// Drives timeouts, if not the default timer.
+    timer: Option<TimerHandle>,
+}
+
+/// Applies `RateLimit`.
+#[cfg(feature = "rate-limit")]
+#[derive(Clone, Debug)]
+pub struct RateLimitLayer {
+    rate: Rate,
+    timer: Timer,
+}
+
+/// Applies `Retry`.
+#[cfg(feature = "retry")]
+#[derive(Clone, Debug)]
+pub struct RetryLayer<P> {
+    policy: P,
+}
+
+/// Applies `Buffer`.
+#[cfg(feature = "buffer")]
+#[derive(Clone, Debug)]
+pub struct BufferLayer {
+    bound: usize,
+}
+
+// ===== impl ServiceBuilder =====
+
+impl ServiceBuilder<()> {
+    /// Creates a builder with no layers.
+    pub fn new() -> Self {
+        ServiceBuilder { layers: () }
+    }
+}
+
+impl Default for ServiceBuilder<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> ServiceBuilder<L> {
+    /// Adds a layer, inside the layers that have already been added.
+    pub fn layer<T>(self, layer: T) -> ServiceBuilder<(L, T)> {
+        ServiceBuilder {
+            layers: (self.layers, layer),
+        }
+    }
+
+    /// Fails requests that do not complete within `timeout`.
+    #[cfg(feature = "timeout")]
+    pub fn timeout(self, timeout: Duration) -> ServiceBuilder<(L, TimeoutLayer)> {
+        self.layer(TimeoutLayer { timeout, timer: None })
+    }
+
+    /// Fails requests that do not complete within `timeout`, as measured by
+    /// `timer` rather than the default timer, e.g. in tests.
+    #[cfg(feature = "timeout")]
+    pub fn timeout_with_clock(self, timeout: Duration, timer: TimerHandle) -> ServiceBuilder<(L, TimeoutLayer)> {
+        self.layer(TimeoutLayer {
+            timeout,
+            timer: Some(timer),
+        })
+    }
+
+    /// Admits at most `num` requests every `per`.
+    #[cfg(feature = "rate-limit")]
+    pub fn rate_limit(self, num: u64, per: Duration, timer: Timer) -> ServiceBuilder<(L, RateLimitLayer)> {
+        self.layer(RateLimitLayer {
+            rate: Rate::new(num, per),
+            timer,
+        })
+    }
+
+    /// Retries requests according to `policy`.
+    #[cfg(feature = "retry")]
+    pub fn retry<P>(self, policy: P) -> ServiceBuilder<(L, RetryLayer<P>)> {
+        self.layer(RetryLayer { policy })
+    }
+
+    /// Queues up to `bound` requests for a service driven on the default
+    /// executor.
+    #[cfg(feature = "buffer")]
+    pub fn buffer(self, bound: usize) -> ServiceBuilder<(L, BufferLayer)> {
+        self.layer(BufferLayer { bound })
+    }
+
+    /// Returns the layers that have been added, as nested tuples.
+    pub fn layers(&self) -> &L {
+        &self.layers
+    }
+
+    /// Wraps `inner` in each of the layers.
+    pub fn service<S, Request>(&self, inner: S) -> L::Service
+    where
+        L: Layer<S, Request>,
+    {
+        self.layers.layer(inner)
+    }
+}
+
+// ===== impl Layer =====
+
+impl<S, Request> Layer<S, Request> for ()
+where
+    S: Service<Request>,
+{
+    type Service = S;
+
+    fn layer(&self, inner: S) -> S {
+        inner
+    }
+}
+
+impl<Outer, Inner, S, Request> Layer<S, Request> for (Outer, Inner)
+where
+    Inner: Layer<S, Request>,
+    Outer: Layer<Inner::Service, Request>,
+{
+    type Service = Outer::Service;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.0.layer(self.1.layer(inner))
+    }
+}
+
+#[cfg(feature = "timeout")]
+impl<S, Request> Layer<S, Request> for TimeoutLayer
+where
+    S: Service<Request>,
+{
+    type Service = Timeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let timeout = Timeout::new(inner, self.timeout);
+        match self.timer {
+            Some(ref timer) => timeout.with_timer(timer.clone()),
+            None => timeout,
+        }
+    }
+}
+
+#[cfg(feature = "rate-limit")]
+impl<S, Request> Layer<S, Request> for RateLimitLayer
+where
+    S: Service<Request>,
+{
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit::new(inner, self.rate, self.timer.clone())
+    }
+}
+
+#[cfg(feature = "retry")]
+impl<P, S, Request> Layer<S, Request> for RetryLayer<P>
+where
+    P: Policy<Request, S::Response, S::Error> + Clone,
+    S: Service<Request> + Clone,
+{
+    type Service = Retry<P, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Retry::new(self.policy.clone(), inner)
+    }
+}
+
+/// # Panics
+///
+/// Panics if the buffer's worker cannot be spawned on the default executor.
+#[cfg(feature = "buffer")]
+impl<S, Request> Layer<S, Request> for BufferLayer
+where
+    S: Service<Request> + Send + 'static,
+    S::Future: Send,
+    Request: Send + 'static,
+{
+    type Service = Buffer<S, Request>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        match Buffer::new(inner, self.bound) {
+            Ok(buffer) => buffer,
+            Err(_) => panic!("failed to spawn buffer worker"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::{Async, Future, Poll};
+
+    use super::*;
+    use ext::MapErr;
+
+    /// Echoes its request.
+    struct Echo;
+
+    impl Service<usize> for Echo {
+        type Response = usize;
+        type Error = ();
+        type Future = FutureResult<usize, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, n: usize) -> Self::Future {
+            future::ok(n)
+        }
+    }
+
+    /// Adds a constant to each request.
+    struct Offset<S>(S, usize);
+
+    impl<S: Service<usize>> Service<usize> for Offset<S> {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self) -> Poll<(), S::Error> {
+            self.0.poll_ready()
+        }
+
+        fn call(&mut self, n: usize) -> Self::Future {
+            self.0.call(n + self.1)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Add(usize);
+
+    impl<S: Service<usize>> Layer<S, usize> for Add {
+        type Service = Offset<S>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            Offset(inner, self.0)
+        }
+    }
+
+    /// Describes errors.
+    #[derive(Debug)]
+    struct Describe;
+
+    impl<S: Service<usize, Error = ()>> Layer<S, usize> for Describe {
+        type Service = MapErr<S, fn(()) -> &'static str, &'static str>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            fn describe(_: ()) -> &'static str {
+                "failed"
+            }
+            MapErr::new(inner, describe as fn(()) -> &'static str)
+        }
+    }
+
+    /// Fails every request.
+    #[cfg(feature = "retry")]
+    #[derive(Clone)]
+    struct Fail;
+
+    #[cfg(feature = "retry")]
+    impl Service<usize> for Fail {
+        type Response = usize;
+        type Error = ();
+        type Future = FutureResult<usize, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: usize) -> Self::Future {
+            future::err(())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "retry")]
+    fn retry_layer_retries() {
+        use tower_retry::test_util::CountingPolicy;
+
+        let policy = CountingPolicy::new(2);
+        let mut svc = ServiceBuilder::new().retry(policy.clone()).service(Fail);
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(1).wait(), Err(()));
+        assert_eq!(policy.attempts(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "timeout")]
+    fn timeout_layer_uses_timer() {
+        use futures::future::Empty;
+        use std::time::Duration;
+        use tower_timeout::test_util::{mock_timer, with_mock_clock};
+        use tower_timeout::Error;
+
+        /// Never responds.
+        struct Pending;
+
+        impl Service<usize> for Pending {
+            type Response = usize;
+            type Error = ();
+            type Future = Empty<usize, ()>;
+
+            fn poll_ready(&mut self) -> Poll<(), ()> {
+                Ok(Async::Ready(()))
+            }
+
+            fn call(&mut self, _: usize) -> Self::Future {
+                future::empty()
+            }
+        }
+
+        with_mock_clock(|time| {
+            let mut timer = mock_timer(time);
+            let builder = ServiceBuilder::new().timeout_with_clock(Duration::from_secs(1), timer.handle());
+            let mut rsp = builder.service(Pending).call(1);
+            let mut poll = || future::lazy(|| Ok::<_, ()>(rsp.poll())).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_secs(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::Timeout) => {}
+                _ => panic!("response should have timed out"),
+            }
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "rate-limit")]
+    fn rate_limit_layer_limits() {
+        use std::time::Duration;
+
+        let builder = ServiceBuilder::new().rate_limit(1, Duration::from_secs(3600), Timer::default());
+        let mut svc = builder.service(Echo);
+
+        future::lazy(|| {
+            assert!(svc.poll_ready().unwrap().is_ready());
+            assert_eq!(svc.call(1).wait().unwrap(), 1);
+            assert!(svc.poll_ready().unwrap().is_not_ready());
+            Ok::<_, ()>(())
+        }).wait().unwrap();
+    }
+
+    #[test]
+    fn layers_applied_in_order() {
+        let builder = ServiceBuilder::new().layer(Describe).layer(Add(2));
+        assert_eq!((builder.layers().1).0, 2);
+
+        let mut svc: MapErr<Offset<Echo>, _, &'static str> = builder.service(Echo);
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(1).wait(), Ok(3));
+        assert_eq!(svc.call(5).wait(), Ok(7));
+    }
+}
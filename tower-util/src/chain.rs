@@ -0,0 +1,129 @@
+/// Builds a stack of services, reading from the outermost layer inwards.
+///
+/// Each layer but the last is a constructor call in which `_` stands in for
+/// the argument that receives the layer below it. The last entry is the
+/// innermost service. For example,
+///
+/// ```ignore
+/// let svc = chain! {
+///     Timeout::new(_, Duration::from_secs(1)),
+///     InFlightLimit::new(_, 10),
+///     Retry::new(policy, _),
+///     service,
+/// };
+/// ```
+///
+/// expands to
+///
+/// ```ignore
+/// let svc = Timeout::new(
+///     InFlightLimit::new(Retry::new(policy, service), 10),
+///     Duration::from_secs(1),
+/// );
+/// ```
+///
+/// Only an argument consisting solely of `_` is replaced, so underscores
+/// within other arguments, e.g. in closure patterns, are left as-is. Each
+/// layer must have exactly one such argument.
+#[macro_export]
+macro_rules! chain {
+    // At the start of an argument: substitute a lone `_`.
+    (@args [$($ctor:tt)*] [$inner:expr] [$($done:tt)*] _ , $($args:tt)*) => {
+        chain!(@rest [$($ctor)*] [$($done)* $inner ,] $($args)*)
+    };
+    (@args [$($ctor:tt)*] [$inner:expr] [$($done:tt)*] _) => {
+        $($ctor)* ( $($done)* $inner )
+    };
+    (@args [$($ctor:tt)*] [$inner:expr] [$($done:tt)*]) => {
+        compile_error!("each layer of `chain!` must have a `_` argument")
+    };
+    (@args [$($ctor:tt)*] [$inner:expr] [$($done:tt)*] $($args:tt)+) => {
+        chain!(@arg [$($ctor)*] [$inner] [$($done)*] $($args)+)
+    };
+
+    // Within any other argument: copy tokens through the next comma.
+    (@arg [$($ctor:tt)*] [$inner:expr] [$($done:tt)*] , $($args:tt)*) => {
+        chain!(@args [$($ctor)*] [$inner] [$($done)* ,] $($args)*)
+    };
+    (@arg [$($ctor:tt)*] [$inner:expr] [$($done:tt)*] $tok:tt $($args:tt)*) => {
+        chain!(@arg [$($ctor)*] [$inner] [$($done)* $tok] $($args)*)
+    };
+    (@arg [$($ctor:tt)*] [$inner:expr] [$($done:tt)*]) => {
+        compile_error!("each layer of `chain!` must have a `_` argument")
+    };
+
+    // After the substitution: copy the remaining arguments.
+    (@rest [$($ctor:tt)*] [$($done:tt)*] $($args:tt)*) => {
+        $($ctor)* ( $($done)* $($args)* )
+    };
+
+    // A layer, which wraps the remainder of the chain.
+    ($($ctor:ident)::+ ( $($args:tt)* ), $($rest:tt)+) => {
+        chain!(@args [$($ctor)::+] [chain!($($rest)+)] [] $($args)*)
+    };
+
+    // The innermost service.
+    ($svc:expr) => { $svc };
+    ($svc:expr,) => { $svc };
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::{Async, Future, Poll};
+    use tower_service::Service;
+
+    use ext::{Map, MapErr};
+    use ReadyCache;
+
+    /// Echoes non-zero requests and fails on zero.
+    struct Srv;
+
+    impl Service<usize> for Srv {
+        type Response = usize;
+        type Error = ();
+        type Future = FutureResult<usize, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, n: usize) -> Self::Future {
+            match n {
+                0 => future::err(()),
+                n => future::ok(n),
+            }
+        }
+    }
+
+    fn responses<S>(mut svc: S) -> Vec<Result<usize, &'static str>>
+    where
+        S: Service<usize, Response = usize, Error = &'static str>,
+    {
+        vec![1, 0, 3]
+            .into_iter()
+            .map(|n| {
+                assert!(svc.poll_ready().unwrap().is_ready());
+                svc.call(n).wait()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_manual_nesting() {
+        let chained = chain! {
+            MapErr::new(_, |_| "failed"),
+            Map::new(_, |n: usize| n * 2),
+            ReadyCache::new(_),
+            Srv,
+        };
+
+        let nested = MapErr::new(
+            Map::new(ReadyCache::new(Srv), |n: usize| n * 2),
+            |_| "failed",
+        );
+
+        assert_eq!(responses(chained), vec![Ok(2), Err("failed"), Ok(6)]);
+        assert_eq!(responses(nested), vec![Ok(2), Err("failed"), Ok(6)]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use futures::Poll;
+use tower_balance::Load;
+use tower_in_flight_limit::{Error, InFlightLimit, ResponseFuture};
+use tower_service::Service;
+
+/// Limits the number of in-flight requests, and reports that number as a load.
+///
+/// This combines an `InFlightLimit` with a `Load` implementation, so that a
+/// balanced endpoint may be limited and measured by a single wrapper. The load
+/// includes capacity reserved by `poll_ready` for a request that has not yet
+/// been dispatched.
+pub struct ConcurrencyLimitLoad<S, R> {
+    inner: InFlightLimit<S>,
+    _p: PhantomData<fn(R)>,
+}
+
+// ===== impl ConcurrencyLimitLoad =====
+
+impl<S, R> ConcurrencyLimitLoad<S, R>
+where
+    S: Service<R>,
+{
+    /// Wraps `inner`, allowing at most `max` requests in flight.
+    pub fn new(inner: S, max: usize) -> Self {
+        ConcurrencyLimitLoad {
+            inner: InFlightLimit::new(inner, max),
+            _p: PhantomData,
+        }
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut()
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner()
+    }
+}
+
+impl<S, R> Load for ConcurrencyLimitLoad<S, R> {
+    type Metric = usize;
+
+    fn load(&self) -> usize {
+        self.inner.in_flight()
+    }
+}
+
+impl<S, R> Service<R> for ConcurrencyLimitLoad<S, R>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = Error<S::Error>;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+impl<S, R> Clone for ConcurrencyLimitLoad<S, R>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        ConcurrencyLimitLoad {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, R> fmt::Debug for ConcurrencyLimitLoad<S, R>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConcurrencyLimitLoad")
+            .field("inner", &self.inner)
+            .field("load", &self.load())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, Empty};
+    use futures::{Async, Future};
+
+    use super::*;
+    use ServiceExt;
+
+    /// Never responds.
+    struct Srv;
+
+    impl Service<()> for Srv {
+        type Response = ();
+        type Error = ();
+        type Future = Empty<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::empty()
+        }
+    }
+
+    #[test]
+    fn limits_and_reports_in_flight() {
+        future::lazy(|| {
+            let mut svc = Srv.concurrency_limit_load(2);
+            assert_eq!(svc.load(), 0);
+
+            assert!(svc.poll_ready().unwrap().is_ready());
+            let first = svc.call(());
+            assert_eq!(svc.load(), 1);
+
+            assert!(svc.poll_ready().unwrap().is_ready());
+            let _second = svc.call(());
+            assert_eq!(svc.load(), 2);
+
+            // At the limit, no further requests are admitted.
+            assert!(svc.poll_ready().unwrap().is_not_ready());
+
+            drop(first);
+            assert_eq!(svc.load(), 1);
+            assert!(svc.poll_ready().unwrap().is_ready());
+            assert_eq!(svc.load(), 2);
+
+            Ok::<_, ()>(())
+        }).wait().unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use futures::{Future, Poll};
+use tower_service::Service;
+
+/// Reports the number of in-flight requests to a callback.
+///
+/// `F` is invoked with the new in-flight count each time a request is
+/// dispatched and each time a response future completes or is dropped. This is
+/// intended for feeding a gauge, e.g. on a dashboard; unlike an in-flight
+/// limit, it never applies backpressure.
+pub struct ConcurrencyObserve<S, F, R> {
+    inner: S,
+    shared: Arc<Shared<F>>,
+    _p: PhantomData<fn(R)>,
+}
+
+/// Response future returned by `ConcurrencyObserve`.
+pub struct ResponseFuture<T, F>
+where
+    F: FnMut(usize),
+{
+    inner: T,
+    handle: Option<Handle<F>>,
+}
+
+struct Shared<F> {
+    /// Holds the in-flight count while the callback is invoked, so that
+    /// updates are reported in order.
+    in_flight: Mutex<(usize, F)>,
+}
+
+/// Decrements the in-flight count when dropped.
+struct Handle<F>
+where
+    F: FnMut(usize),
+{
+    shared: Arc<Shared<F>>,
+}
+
+// ===== impl ConcurrencyObserve =====
+
+impl<S, F, R> ConcurrencyObserve<S, F, R>
+where
+    S: Service<R>,
+    F: FnMut(usize),
+{
+    /// Wraps `inner`, reporting its in-flight count to `f`.
+    pub fn new(inner: S, f: F) -> Self {
+        ConcurrencyObserve {
+            inner,
+            shared: Arc::new(Shared {
+                in_flight: Mutex::new((0, f)),
+            }),
+            _p: PhantomData,
+        }
+    }
+
+    /// Returns the number of requests currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.shared.in_flight.lock().expect("in_flight lock").0
+    }
+}
+
+impl<S, F, R> Service<R> for ConcurrencyObserve<S, F, R>
+where
+    S: Service<R>,
+    F: FnMut(usize),
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, F>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.shared.update(|n| n + 1);
+        ResponseFuture {
+            inner: self.inner.call(request),
+            handle: Some(Handle {
+                shared: self.shared.clone(),
+            }),
+        }
+    }
+}
+
+impl<S, F, R> Clone for ConcurrencyObserve<S, F, R>
+where
+    S: Clone,
+{
+    /// Clones share the in-flight count and the callback.
+    fn clone(&self) -> Self {
+        ConcurrencyObserve {
+            inner: self.inner.clone(),
+            shared: self.shared.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> fmt::Debug for ConcurrencyObserve<S, F, R>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConcurrencyObserve")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<T, F> Future for ResponseFuture<T, F>
+where
+    T: Future,
+    F: FnMut(usize),
+{
+    type Item = T::Item;
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let ret = self.inner.poll();
+        if ret.as_ref().map(|a| a.is_ready()).unwrap_or(true) {
+            // Report completion eagerly rather than when the future is dropped.
+            self.handle.take();
+        }
+        ret
+    }
+}
+
+// ===== impl Shared =====
+
+impl<F> Shared<F>
+where
+    F: FnMut(usize),
+{
+    fn update<U: FnOnce(usize) -> usize>(&self, u: U) {
+        let mut lock = self.in_flight.lock().expect("in_flight lock");
+        let (ref mut n, ref mut f) = *lock;
+        *n = u(*n);
+        f(*n);
+    }
+}
+
+// ===== impl Handle =====
+
+impl<F> Drop for Handle<F>
+where
+    F: FnMut(usize),
+{
+    fn drop(&mut self) {
+        self.shared.update(|n| n - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{ok, FutureResult};
+    use futures::Async;
+
+    use super::*;
+
+    struct Srv;
+
+    impl Service<()> for Srv {
+        type Response = ();
+        type Error = ();
+        type Future = FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            ok(())
+        }
+    }
+
+    #[test]
+    fn reports_rising_and_falling_counts() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut srv = {
+            let reports = reports.clone();
+            ConcurrencyObserve::new(Srv, move |n| reports.lock().unwrap().push(n))
+        };
+
+        let mut a = srv.call(());
+        let b = srv.call(());
+        let mut c = srv.call(());
+        assert_eq!(srv.in_flight(), 3);
+
+        assert_eq!(a.poll(), Ok(Async::Ready(())));
+        drop(b);
+        assert_eq!(c.poll(), Ok(Async::Ready(())));
+        assert_eq!(srv.in_flight(), 0);
+
+        // Completed futures do not report again when dropped.
+        drop(a);
+        drop(c);
+        assert_eq!(*reports.lock().unwrap(), vec![1, 2, 3, 2, 1, 0]);
+    }
+}
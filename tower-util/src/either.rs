@@ -2,7 +2,7 @@
 //!
 //! See `EitherService` documentation for more details.
 
-use futures::Poll;
+use futures::{Future, Poll};
 use futures::future::Either;
 use tower_service::Service;
 
@@ -44,3 +44,114 @@ where A: Service<Request>,
         }
     }
 }
+
+/// Combine two different service types into a single type, preserving each
+/// service's error type.
+///
+/// Unlike `EitherService`, the services need not have the same error type.
+/// Errors from `A` are returned as `Either::A`, and errors from `B` as
+/// `Either::B`.
+pub enum TryEither<A, B> {
+    A(A),
+    B(B),
+}
+
+/// Response future returned by `TryEither`.
+pub enum TryEitherFuture<A, B> {
+    A(A),
+    B(B),
+}
+
+impl<A, B, Request> Service<Request> for TryEither<A, B>
+where A: Service<Request>,
+      B: Service<Request, Response = A::Response>,
+{
+    type Response = A::Response;
+    type Error = Either<A::Error, B::Error>;
+    type Future = TryEitherFuture<A::Future, B::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        use self::TryEither::*;
+
+        match *self {
+            A(ref mut service) => service.poll_ready().map_err(Either::A),
+            B(ref mut service) => service.poll_ready().map_err(Either::B),
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        use self::TryEither::*;
+
+        match *self {
+            A(ref mut service) => TryEitherFuture::A(service.call(request)),
+            B(ref mut service) => TryEitherFuture::B(service.call(request)),
+        }
+    }
+}
+
+impl<A, B> Future for TryEitherFuture<A, B>
+where A: Future,
+      B: Future<Item = A::Item>,
+{
+    type Item = A::Item;
+    type Error = Either<A::Error, B::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        use self::TryEitherFuture::*;
+
+        match *self {
+            A(ref mut future) => future.poll().map_err(Either::A),
+            B(ref mut future) => future.poll().map_err(Either::B),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::Async;
+
+    use super::*;
+
+    /// Fails every request with a fixed error.
+    struct Fail<E>(E);
+
+    impl<E: Copy> Service<()> for Fail<E> {
+        type Response = ();
+        type Error = E;
+        type Future = FutureResult<(), E>;
+
+        fn poll_ready(&mut self) -> Poll<(), E> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::err(self.0)
+        }
+    }
+
+    fn route(to_a: bool) -> TryEither<Fail<&'static str>, Fail<u32>> {
+        if to_a {
+            TryEither::A(Fail("a"))
+        } else {
+            TryEither::B(Fail(2))
+        }
+    }
+
+    #[test]
+    fn errors_keep_their_types() {
+        let mut a = route(true);
+        assert!(a.poll_ready().unwrap().is_ready());
+        match a.call(()).wait() {
+            Err(Either::A("a")) => {}
+            _ => panic!("expected an error from A"),
+        }
+
+        let mut b = route(false);
+        assert!(b.poll_ready().unwrap().is_ready());
+        match b.call(()).wait() {
+            Err(Either::B(2)) => {}
+            _ => panic!("expected an error from B"),
+        }
+    }
+}
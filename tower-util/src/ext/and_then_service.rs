@@ -0,0 +1,183 @@
+use futures::{Future, Poll};
+use tower_service::Service;
+
+/// Service for the `and_then_service` combinator, feeding the response of one
+/// service into another service.
+///
+/// Unlike `AndThen`, the second service's readiness is not checked up front.
+/// Instead, each response future waits for `B` to become ready after `A` has
+/// responded, so that `B` applies backpressure between the two calls.
+///
+/// This is created by the `ServiceExt::and_then_service` method.
+#[derive(Clone, Debug)]
+pub struct AndThenService<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> AndThenService<A, B> {
+    /// Create new `AndThenService` combinator
+    pub fn new<Request>(a: A, b: B) -> Self
+    where
+        A: Service<Request>,
+        B: Service<A::Response> + Clone,
+        B::Error: From<A::Error>,
+    {
+        AndThenService { a, b }
+    }
+}
+
+impl<A, B, Request> Service<Request> for AndThenService<A, B>
+where
+    A: Service<Request>,
+    B: Service<A::Response> + Clone,
+    B::Error: From<A::Error>,
+{
+    type Response = B::Response;
+    type Error = B::Error;
+    type Future = AndThenServiceFuture<A, B, Request>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.a.poll_ready().map_err(B::Error::from)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        AndThenServiceFuture {
+            fut_a: self.a.call(req),
+            response: None,
+            b: self.b.clone(),
+            fut_b: None,
+        }
+    }
+}
+
+pub struct AndThenServiceFuture<A, B, Request>
+where
+    A: Service<Request>,
+    B: Service<A::Response>,
+{
+    fut_a: A::Future,
+    response: Option<A::Response>,
+    b: B,
+    fut_b: Option<B::Future>,
+}
+
+impl<A, B, Request> Future for AndThenServiceFuture<A, B, Request>
+where
+    A: Service<Request>,
+    B: Service<A::Response>,
+    B::Error: From<A::Error>,
+{
+    type Item = B::Response;
+    type Error = B::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(ref mut fut) = self.fut_b {
+            return fut.poll();
+        }
+
+        if self.response.is_none() {
+            let rsp = try_ready!(self.fut_a.poll().map_err(B::Error::from));
+            self.response = Some(rsp);
+        }
+
+        try_ready!(self.b.poll_ready());
+        let rsp = self.response.take().expect("polled after complete");
+        self.fut_b = Some(self.b.call(rsp));
+        self.poll()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{err, ok, FutureResult};
+    use futures::Async;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    #[derive(Debug, PartialEq)]
+    struct Unauthorized;
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Auth(Unauthorized),
+        NotFound,
+    }
+
+    impl From<Unauthorized> for Error {
+        fn from(e: Unauthorized) -> Self {
+            Error::Auth(e)
+        }
+    }
+
+    /// Resolves a token to a user name.
+    struct Auth;
+
+    impl Service<&'static str> for Auth {
+        type Response = &'static str;
+        type Error = Unauthorized;
+        type Future = FutureResult<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, token: &'static str) -> Self::Future {
+            match token {
+                "secret" => ok("alice"),
+                _ => err(Unauthorized),
+            }
+        }
+    }
+
+    /// Looks up a user's data, refusing to be ready on every other poll.
+    #[derive(Clone)]
+    struct Data(Rc<Cell<usize>>);
+
+    impl Service<&'static str> for Data {
+        type Response = u32;
+        type Error = Error;
+        type Future = FutureResult<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            let polls = self.0.get();
+            self.0.set(polls + 1);
+            if polls % 2 == 0 {
+                Ok(Async::NotReady)
+            } else {
+                Ok(Async::Ready(()))
+            }
+        }
+
+        fn call(&mut self, user: &'static str) -> Self::Future {
+            match user {
+                "alice" => ok(42),
+                _ => err(Error::NotFound),
+            }
+        }
+    }
+
+    #[test]
+    fn test_call() {
+        let polls = Rc::new(Cell::new(0));
+        let mut srv = Auth.and_then_service(Data(polls.clone()));
+        let mut fut = srv.call("secret");
+
+        assert_eq!(fut.poll(), Ok(Async::NotReady));
+        assert_eq!(fut.poll(), Ok(Async::Ready(42)));
+        assert_eq!(polls.get(), 2);
+    }
+
+    #[test]
+    fn test_first_error() {
+        let polls = Rc::new(Cell::new(0));
+        let mut srv = Auth.and_then_service(Data(polls.clone()));
+        let mut fut = srv.call("guess");
+
+        assert_eq!(fut.poll(), Err(Error::Auth(Unauthorized)));
+        assert_eq!(polls.get(), 0);
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+/// Future yielding the response to a single request, dispatched once the
+/// borrowed `Service` is ready.
+///
+/// `CallReady` values are produced by `ServiceExt::call_ready`.
+pub struct CallReady<'a, T, Request>
+where
+    T: Service<Request> + 'a,
+{
+    state: State<'a, T, Request>,
+}
+
+enum State<'a, T, Request>
+where
+    T: Service<Request> + 'a,
+{
+    /// Waiting for the service to become ready.
+    NotReady(&'a mut T, Request),
+    /// Waiting for the response.
+    Called(T::Future),
+    Done,
+}
+
+impl<'a, T, Request> CallReady<'a, T, Request>
+where
+    T: Service<Request>,
+{
+    pub(super) fn new(service: &'a mut T, request: Request) -> Self {
+        CallReady {
+            state: State::NotReady(service, request),
+        }
+    }
+}
+
+impl<'a, T, Request> Future for CallReady<'a, T, Request>
+where
+    T: Service<Request>,
+{
+    type Item = T::Response;
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, T::Error> {
+        use std::mem;
+
+        loop {
+            match mem::replace(&mut self.state, State::Done) {
+                State::NotReady(service, request) => match service.poll_ready()? {
+                    Async::Ready(()) => {
+                        self.state = State::Called(service.call(request));
+                    }
+                    Async::NotReady => {
+                        self.state = State::NotReady(service, request);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                State::Called(mut response) => {
+                    let ready = response.poll()?;
+                    if ready.is_not_ready() {
+                        self.state = State::Called(response);
+                    }
+                    return Ok(ready);
+                }
+                State::Done => panic!("called `poll` after future completed"),
+            }
+        }
+    }
+}
+
+impl<'a, T, Request> fmt::Debug for CallReady<'a, T, Request>
+where
+    T: Service<Request> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let service = match self.state {
+            State::NotReady(ref service, _) => Some(service),
+            State::Called(_) | State::Done => None,
+        };
+        f.debug_struct("CallReady")
+            .field("service", &service)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::task;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Becomes ready on every other poll, yielding in between, and counts the requests it serves.
+    struct Counter {
+        ready: bool,
+        served: usize,
+    }
+
+    impl Service<&'static str> for Counter {
+        type Response = String;
+        type Error = ();
+        type Future = FutureResult<String, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            self.ready = !self.ready;
+            if self.ready {
+                Ok(Async::Ready(()))
+            } else {
+                task::current().notify();
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            self.served += 1;
+            future::ok(format!("{}-{}", req, self.served))
+        }
+    }
+
+    #[test]
+    fn not_ready_service_is_called_once_ready() {
+        let mut svc = Counter { ready: true, served: 0 };
+
+        let mut call = svc.call_ready("a");
+        let first = future::lazy(|| Ok::<_, ()>(call.poll())).wait().unwrap();
+        assert_eq!(first, Ok(Async::NotReady));
+        assert_eq!(call.wait(), Ok("a-1".to_string()));
+
+        // The service is only borrowed, so it may be called again.
+        assert_eq!(svc.call_ready("b").wait(), Ok("b-2".to_string()));
+        assert_eq!(svc.served, 2);
+    }
+}
@@ -0,0 +1,157 @@
+use futures::{Future, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `enrich_err` combinator, attaching context captured from
+/// each request to the errors its response future produces.
+///
+/// The context is captured when the request is dispatched, so it is available
+/// even though the request itself has been moved into the inner service.
+///
+/// This is created by the `ServiceExt::enrich_err` method.
+pub struct EnrichErr<S, C, F, R, E> {
+    service: S,
+    capture: C,
+    f: F,
+    _p: PhantomData<fn(R) -> E>,
+}
+
+pub struct EnrichErrFuture<T, Ctx, F> {
+    /// Taken when the response future fails.
+    ctx: Option<Ctx>,
+    f: F,
+    fut: T,
+}
+
+impl<S, C, F, R, E> EnrichErr<S, C, F, R, E> {
+    /// Create new `EnrichErr` combinator
+    pub fn new<Ctx>(service: S, capture: C, f: F) -> Self
+    where
+        S: Service<R>,
+        C: FnMut(&R) -> Ctx,
+        F: FnMut(S::Error, Ctx) -> E + Clone,
+        E: From<S::Error>,
+    {
+        EnrichErr {
+            service,
+            capture,
+            f,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, C, F, R, E> Clone for EnrichErr<S, C, F, R, E>
+where
+    S: Clone,
+    C: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        EnrichErr {
+            service: self.service.clone(),
+            capture: self.capture.clone(),
+            f: self.f.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, C, F, R, E, Ctx> Service<R> for EnrichErr<S, C, F, R, E>
+where
+    S: Service<R>,
+    C: FnMut(&R) -> Ctx,
+    F: FnMut(S::Error, Ctx) -> E + Clone,
+    E: From<S::Error>,
+{
+    type Response = S::Response;
+    type Error = E;
+    type Future = EnrichErrFuture<S::Future, Ctx, F>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready().map_err(E::from)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let ctx = (self.capture)(&req);
+        EnrichErrFuture {
+            ctx: Some(ctx),
+            f: self.f.clone(),
+            fut: self.service.call(req),
+        }
+    }
+}
+
+impl<T, Ctx, F, E> Future for EnrichErrFuture<T, Ctx, F>
+where
+    T: Future,
+    F: FnMut(T::Error, Ctx) -> E,
+{
+    type Item = T::Item;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.fut.poll().map_err(|e| {
+            let ctx = self.ctx.take().expect("polled after error");
+            (self.f)(e, ctx)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{err, ok, FutureResult};
+    use futures::Async;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Fails requests for odd numbers.
+    struct Srv;
+
+    impl Service<u32> for Srv {
+        type Response = u32;
+        type Error = &'static str;
+        type Future = FutureResult<u32, &'static str>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, n: u32) -> Self::Future {
+            if n % 2 == 0 {
+                ok(n)
+            } else {
+                err("odd")
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Enriched {
+        request_id: u32,
+        cause: &'static str,
+    }
+
+    impl From<&'static str> for Enriched {
+        fn from(cause: &'static str) -> Self {
+            Enriched { request_id: 0, cause }
+        }
+    }
+
+    #[test]
+    fn errors_carry_request_context() {
+        let mut srv = Srv.enrich_err(
+            |n: &u32| *n,
+            |cause, request_id| Enriched { request_id, cause },
+        );
+
+        assert_eq!(srv.call(2).poll(), Ok(Async::Ready(2)));
+
+        let mut rsp3 = srv.call(3);
+        let mut rsp5 = srv.call(5);
+        assert_eq!(rsp5.poll(), Err(Enriched { request_id: 5, cause: "odd" }));
+        assert_eq!(rsp3.poll(), Err(Enriched { request_id: 3, cause: "odd" }));
+    }
+}
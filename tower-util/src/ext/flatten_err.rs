@@ -0,0 +1,143 @@
+use std::marker::PhantomData;
+
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+/// Service for the `flatten_err` combinator, lifting errors carried in a
+/// service's responses into its error.
+///
+/// This is created by the `ServiceExt::flatten_err` method.
+pub struct FlattenErr<A, E> {
+    service: A,
+    _e: PhantomData<E>,
+}
+
+/// Response future for `FlattenErr`.
+pub struct FlattenErrFuture<A, E> {
+    fut: A,
+    _e: PhantomData<E>,
+}
+
+impl<A, E> FlattenErr<A, E> {
+    pub(crate) fn new<Request, T, U>(service: A) -> Self
+    where
+        A: Service<Request, Response = Result<T, U>>,
+        E: From<U> + From<A::Error>,
+    {
+        FlattenErr {
+            service,
+            _e: PhantomData,
+        }
+    }
+}
+
+impl<A, E> Clone for FlattenErr<A, E>
+where
+    A: Clone,
+{
+    fn clone(&self) -> Self {
+        FlattenErr {
+            service: self.service.clone(),
+            _e: PhantomData,
+        }
+    }
+}
+
+impl<A, E, T, U, Request> Service<Request> for FlattenErr<A, E>
+where
+    A: Service<Request, Response = Result<T, U>>,
+    E: From<U> + From<A::Error>,
+{
+    type Response = T;
+    type Error = E;
+    type Future = FlattenErrFuture<A::Future, E>;
+
+    fn poll_ready(&mut self) -> Poll<(), E> {
+        Ok(self.service.poll_ready().map_err(E::from)?)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        FlattenErrFuture {
+            fut: self.service.call(req),
+            _e: PhantomData,
+        }
+    }
+}
+
+impl<A, E, T, U> Future for FlattenErrFuture<A, E>
+where
+    A: Future<Item = Result<T, U>>,
+    E: From<U> + From<A::Error>,
+{
+    type Item = T;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<T, E> {
+        match try_ready!(self.fut.poll().map_err(E::from)) {
+            Ok(rsp) => Ok(Async::Ready(rsp)),
+            Err(e) => Err(E::from(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+
+    use super::*;
+    use ServiceExt;
+
+    /// Responds with an application-level error for the request `"bad"`, and
+    /// fails the request `"down"`.
+    struct Srv;
+
+    #[derive(Debug, PartialEq)]
+    struct Rejected;
+
+    #[derive(Debug, PartialEq)]
+    struct Unavailable;
+
+    #[derive(Debug, PartialEq)]
+    enum Flat {
+        Rejected,
+        Unavailable,
+    }
+
+    impl From<Rejected> for Flat {
+        fn from(_: Rejected) -> Flat {
+            Flat::Rejected
+        }
+    }
+
+    impl From<Unavailable> for Flat {
+        fn from(_: Unavailable) -> Flat {
+            Flat::Unavailable
+        }
+    }
+
+    impl Service<&'static str> for Srv {
+        type Response = Result<&'static str, Rejected>;
+        type Error = Unavailable;
+        type Future = FutureResult<Self::Response, Unavailable>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            match req {
+                "bad" => future::ok(Err(Rejected)),
+                "down" => future::err(Unavailable),
+                _ => future::ok(Ok(req)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_flatten_err() {
+        let mut srv = Srv.flatten_err::<Flat, _, _>();
+        assert_eq!(srv.call("hello").wait(), Ok("hello"));
+        assert_eq!(srv.call("bad").wait(), Err(Flat::Rejected));
+        assert_eq!(srv.call("down").wait(), Err(Flat::Unavailable));
+    }
+}
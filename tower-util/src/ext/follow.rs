@@ -0,0 +1,193 @@
+use std::marker::PhantomData;
+
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+/// Service for the `follow` combinator, re-issuing requests as directed by
+/// the responses they produce.
+///
+/// Each response is passed to `F`. If `F` returns a new request, that request
+/// is sent to the service in place of returning the response, as with an HTTP
+/// redirect. At most `max_hops` follow-up requests are sent for each call;
+/// once the limit is reached, the last response is returned as-is.
+///
+/// This is created by the `ServiceExt::follow` method.
+pub struct Follow<S, F, R> {
+    service: S,
+    f: F,
+    max_hops: usize,
+    _p: PhantomData<fn(R)>,
+}
+
+pub struct FollowFuture<S, F, R>
+where
+    S: Service<R>,
+{
+    service: S,
+    f: F,
+    hops: usize,
+    state: State<S::Future, R>,
+}
+
+enum State<T, R> {
+    /// Waiting for a response.
+    Called(T),
+    /// Waiting for the service to be ready to follow a response.
+    Following(Option<R>),
+}
+
+impl<S, F, R> Follow<S, F, R> {
+    /// Create new `Follow` combinator
+    pub fn new(service: S, f: F, max_hops: usize) -> Self
+    where
+        S: Service<R> + Clone,
+        F: Fn(&S::Response) -> Option<R> + Clone,
+    {
+        Follow {
+            service,
+            f,
+            max_hops,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> Clone for Follow<S, F, R>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Follow {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            max_hops: self.max_hops,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> Service<R> for Follow<S, F, R>
+where
+    S: Service<R> + Clone,
+    F: Fn(&S::Response) -> Option<R> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FollowFuture<S, F, R>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let fut = self.service.call(req);
+        FollowFuture {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            hops: self.max_hops,
+            state: State::Called(fut),
+        }
+    }
+}
+
+impl<S, F, R> Future for FollowFuture<S, F, R>
+where
+    S: Service<R>,
+    F: Fn(&S::Response) -> Option<R>,
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let next = match self.state {
+                State::Called(ref mut fut) => {
+                    let rsp = try_ready!(fut.poll());
+                    if self.hops == 0 {
+                        return Ok(Async::Ready(rsp));
+                    }
+                    match (self.f)(&rsp) {
+                        Some(req) => {
+                            self.hops -= 1;
+                            State::Following(Some(req))
+                        }
+                        None => return Ok(Async::Ready(rsp)),
+                    }
+                }
+                State::Following(ref mut req) => {
+                    try_ready!(self.service.poll_ready());
+                    let req = req.take().expect("polled after complete");
+                    State::Called(self.service.call(req))
+                }
+            };
+            self.state = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{ok, FutureResult};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    #[derive(Debug, PartialEq)]
+    enum Rsp {
+        Moved(&'static str),
+        Found(&'static str),
+    }
+
+    /// Redirects `/old` to `/new` and `/loop` to itself.
+    #[derive(Clone)]
+    struct Srv(Rc<RefCell<Vec<&'static str>>>);
+
+    impl Service<&'static str> for Srv {
+        type Response = Rsp;
+        type Error = ();
+        type Future = FutureResult<Rsp, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, path: &'static str) -> Self::Future {
+            self.0.borrow_mut().push(path);
+            match path {
+                "/old" => ok(Rsp::Moved("/new")),
+                "/loop" => ok(Rsp::Moved("/loop")),
+                _ => ok(Rsp::Found(path)),
+            }
+        }
+    }
+
+    fn location(rsp: &Rsp) -> Option<&'static str> {
+        match *rsp {
+            Rsp::Moved(path) => Some(path),
+            Rsp::Found(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_follow_once() {
+        let paths = Rc::new(RefCell::new(Vec::new()));
+        let mut srv = Srv(paths.clone()).follow(location, 5);
+
+        let res = srv.call("/old").poll();
+        assert_eq!(res, Ok(Async::Ready(Rsp::Found("/new"))));
+        assert_eq!(*paths.borrow(), vec!["/old", "/new"]);
+    }
+
+    #[test]
+    fn test_max_hops() {
+        let paths = Rc::new(RefCell::new(Vec::new()));
+        let mut srv = Srv(paths.clone()).follow(location, 2);
+
+        let res = srv.call("/loop").poll();
+        assert_eq!(res, Ok(Async::Ready(Rsp::Moved("/loop"))));
+        assert_eq!(paths.borrow().len(), 3);
+    }
+}
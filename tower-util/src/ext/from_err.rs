@@ -50,10 +50,7 @@ where
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
-        FromErrFuture {
-            fut: self.service.call(req),
-            f: PhantomData,
-        }
+        FromErrFuture::new(self.service.call(req))
     }
 }
 
@@ -62,6 +59,12 @@ pub struct FromErrFuture<A, E> {
     f: PhantomData<E>,
 }
 
+impl<A, E> FromErrFuture<A, E> {
+    pub(crate) fn new(fut: A) -> Self {
+        FromErrFuture { fut, f: PhantomData }
+    }
+}
+
 impl<A, E> Future for FromErrFuture<A, E>
 where
     A: Future,
@@ -0,0 +1,174 @@
+use futures::{Future, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// A context, such as a tracing span, that is entered while work is done on
+/// behalf of a request.
+///
+/// This is intentionally minimal, so that `Instrument` may be used with any
+/// tracing library without depending on it.
+pub trait Span {
+    /// Marks the span as active.
+    fn enter(&mut self);
+
+    /// Marks the span as no longer active.
+    fn exit(&mut self);
+}
+
+/// Service for the `instrument` combinator, attaching a span to each call.
+///
+/// `F` creates a span from each request. The span is entered each time the
+/// response future is polled, and exited before the poll returns.
+///
+/// This is created by the `ServiceExt::instrument` method.
+pub struct Instrument<T, F, R> {
+    service: T,
+    make_span: F,
+    _p: PhantomData<fn(R)>,
+}
+
+pub struct InstrumentFuture<T, S> {
+    fut: T,
+    span: S,
+}
+
+impl<T, F, R> Instrument<T, F, R> {
+    /// Create new `Instrument` combinator
+    pub fn new<S>(service: T, make_span: F) -> Self
+    where
+        T: Service<R>,
+        F: FnMut(&R) -> S,
+        S: Span,
+    {
+        Instrument {
+            service,
+            make_span,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T, F, R> Clone for Instrument<T, F, R>
+where
+    T: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Instrument {
+            service: self.service.clone(),
+            make_span: self.make_span.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T, F, R, S> Service<R> for Instrument<T, F, R>
+where
+    T: Service<R>,
+    F: FnMut(&R) -> S,
+    S: Span,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = InstrumentFuture<T::Future, S>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let span = (self.make_span)(&req);
+        InstrumentFuture {
+            fut: self.service.call(req),
+            span,
+        }
+    }
+}
+
+impl<T, S> Future for InstrumentFuture<T, S>
+where
+    T: Future,
+    S: Span,
+{
+    type Item = T::Item;
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.span.enter();
+        let ret = self.fut.poll();
+        self.span.exit();
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use futures::Async;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Records when its future is polled.
+    struct Srv(Log);
+
+    impl Service<&'static str> for Srv {
+        type Response = ();
+        type Error = ();
+        type Future = Box<Future<Item = (), Error = ()>>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            let log = self.0.clone();
+            let mut polls = 0;
+            Box::new(future::poll_fn(move || {
+                log.borrow_mut().push(format!("poll {}", req));
+                polls += 1;
+                if polls < 2 {
+                    Ok(Async::NotReady)
+                } else {
+                    Ok(Async::Ready(()))
+                }
+            }))
+        }
+    }
+
+    struct StubSpan(&'static str, Log);
+
+    impl Span for StubSpan {
+        fn enter(&mut self) {
+            self.1.borrow_mut().push(format!("enter {}", self.0));
+        }
+
+        fn exit(&mut self) {
+            self.1.borrow_mut().push(format!("exit {}", self.0));
+        }
+    }
+
+    #[test]
+    fn test_span_entered_around_poll() {
+        let log = Log::default();
+        let mut srv = {
+            let log = log.clone();
+            Srv(log.clone()).instrument(move |req: &&'static str| StubSpan(*req, log.clone()))
+        };
+
+        let mut fut = srv.call("a");
+        assert!(log.borrow().is_empty());
+
+        assert_eq!(fut.poll(), Ok(Async::NotReady));
+        assert_eq!(fut.poll(), Ok(Async::Ready(())));
+        assert_eq!(
+            *log.borrow(),
+            vec!["enter a", "poll a", "exit a", "enter a", "poll a", "exit a"]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use futures::{Async, Poll};
+use tower_direct_service::DirectService;
+use tower_service::Service;
+
+/// A `DirectService` that delegates to a plain `Service`.
+///
+/// A `Service` drives its own response futures, so `poll_service` and
+/// `poll_close` have nothing to do and complete immediately.
+///
+/// `IntoDirect` values are produced by `ServiceExt::into_direct_service`.
+#[derive(Clone, Debug)]
+pub struct IntoDirect<T> {
+    inner: T,
+}
+
+impl<T> IntoDirect<T> {
+    pub(super) fn new(inner: T) -> Self {
+        IntoDirect { inner }
+    }
+
+    /// Returns a reference to the inner service.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner service.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes `self`, returning the inner service.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, Request> DirectService<Request> for IntoDirect<T>
+where
+    T: Service<Request>,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = T::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn poll_service(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn poll_close(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::Future;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Doubles each request, counting the requests it serves.
+    struct Double(Rc<Cell<usize>>);
+
+    impl Service<usize> for Double {
+        type Response = usize;
+        type Error = ();
+        type Future = FutureResult<usize, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            future::ok(req * 2)
+        }
+    }
+
+    /// Dispatches `request` to a `DirectService`, driving it until the response
+    /// is available, and then closes it.
+    fn drive<D: DirectService<usize>>(svc: &mut D, request: usize) -> Result<D::Response, D::Error> {
+        future::poll_fn(|| svc.poll_ready()).wait()?;
+        let mut rsp = svc.call(request);
+        let rsp = future::poll_fn(|| {
+            svc.poll_service()?;
+            rsp.poll()
+        }).wait()?;
+        future::poll_fn(|| svc.poll_close()).wait()?;
+        Ok(rsp)
+    }
+
+    #[test]
+    fn plain_service_is_usable_as_direct_service() {
+        let served = Rc::new(Cell::new(0));
+        let mut svc = Double(served.clone()).into_direct_service();
+
+        assert_eq!(drive(&mut svc, 2), Ok(4));
+        assert_eq!(drive(&mut svc, 5), Ok(10));
+        assert_eq!(served.get(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+use futures::future;
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `join` combinator, calling two services with each request
+/// and combining their responses.
+///
+/// Each request is cloned, so that both services are called with it. The
+/// service is ready once both services are ready. Responses are yielded as a
+/// tuple once both complete, and the first error from either fails the call.
+///
+/// This is created by the `ServiceExt::join` method.
+pub struct Join<A, B, R> {
+    a: A,
+    b: B,
+    _p: PhantomData<fn(R)>,
+}
+
+impl<A, B, R> Join<A, B, R> {
+    /// Create new `Join` combinator
+    pub fn new(a: A, b: B) -> Self
+    where
+        A: Service<R>,
+        B: Service<R, Error = A::Error>,
+        R: Clone,
+    {
+        Join {
+            a,
+            b,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<A, B, R> Clone for Join<A, B, R>
+where
+    A: Clone,
+    B: Clone,
+{
+    fn clone(&self) -> Self {
+        Join {
+            a: self.a.clone(),
+            b: self.b.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<A, B, R> Service<R> for Join<A, B, R>
+where
+    A: Service<R>,
+    B: Service<R, Error = A::Error>,
+    R: Clone,
+{
+    type Response = (A::Response, B::Response);
+    type Error = A::Error;
+    type Future = future::Join<A::Future, B::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        // Both services are polled, so that each may make progress.
+        let a = self.a.poll_ready()?;
+        let b = self.b.poll_ready()?;
+        if a.is_ready() && b.is_ready() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.a.call(request.clone()).join(self.b.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::FutureResult;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Looks up a field of a user by name.
+    struct Lookup(fn(&'static str) -> Result<String, &'static str>);
+
+    impl Service<&'static str> for Lookup {
+        type Response = String;
+        type Error = &'static str;
+        type Future = FutureResult<String, &'static str>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, name: &'static str) -> Self::Future {
+            (self.0)(name).into()
+        }
+    }
+
+    fn email(name: &'static str) -> Result<String, &'static str> {
+        Ok(format!("{}@example.com", name))
+    }
+
+    fn avatar(name: &'static str) -> Result<String, &'static str> {
+        Ok(format!("/avatars/{}.png", name))
+    }
+
+    fn missing(_: &'static str) -> Result<String, &'static str> {
+        Err("no such user")
+    }
+
+    #[test]
+    fn responses_are_combined() {
+        let mut profile = Lookup(email).join(Lookup(avatar));
+        assert!(profile.poll_ready().unwrap().is_ready());
+
+        let rsp = profile.call("alice").wait().unwrap();
+        assert_eq!(rsp, ("alice@example.com".to_string(), "/avatars/alice.png".to_string()));
+    }
+
+    #[test]
+    fn either_error_fails_the_call() {
+        let mut profile = Lookup(email).join(Lookup(missing));
+        assert_eq!(profile.call("alice").wait(), Err("no such user"));
+
+        let mut profile = Lookup(missing).join(Lookup(avatar));
+        assert_eq!(profile.call("alice").wait(), Err("no such user"));
+    }
+}
@@ -90,8 +90,10 @@ where
 #[cfg(test)]
 mod tests {
     use futures::future::{err, FutureResult};
+    use std::{error, fmt};
 
     use super::*;
+    use boxed::{BoxError, BoxService};
     use ServiceExt;
 
     struct Srv;
@@ -125,4 +127,65 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(res.err().unwrap(), "error");
     }
+
+    #[derive(Debug)]
+    struct Timeout;
+
+    impl fmt::Display for Timeout {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.pad("timed out")
+        }
+    }
+
+    impl error::Error for Timeout {
+        fn description(&self) -> &str {
+            "timed out"
+        }
+    }
+
+    struct Slow;
+
+    impl Service<()> for Slow {
+        type Response = ();
+        type Error = Timeout;
+        type Future = FutureResult<(), Timeout>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            err(Timeout)
+        }
+    }
+
+    struct Broken;
+
+    impl Service<()> for Broken {
+        type Response = ();
+        type Error = fmt::Error;
+        type Future = FutureResult<(), fmt::Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            err(fmt::Error)
+        }
+    }
+
+    #[test]
+    fn test_err_into_boxed() {
+        let mut services: Vec<BoxService<(), (), BoxError>> = vec![
+            BoxService::new(Slow.err_into_boxed()),
+            BoxService::new(Broken.err_into_boxed()),
+        ];
+
+        let errors = services
+            .iter_mut()
+            .map(|s| s.call(()).wait().unwrap_err().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(errors, vec!["timed out", "an error occurred when formatting an argument"]);
+    }
 }
@@ -0,0 +1,137 @@
+use futures::Poll;
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+use super::from_err::FromErrFuture;
+
+/// Service for the `map_poll_ready` combinator, transforming the result of
+/// `poll_ready`.
+///
+/// Every result of the inner service's `poll_ready`, whether ready, not ready
+/// or failed, is passed through `f`. This allows, e.g., shedding load by
+/// turning `NotReady` into an error. Errors from response futures are converted
+/// with `From`.
+///
+/// This is created by the `ServiceExt::map_poll_ready` method.
+pub struct MapPollReady<S, F, R> {
+    service: S,
+    f: F,
+    _p: PhantomData<fn(R)>,
+}
+
+impl<S, F, R> MapPollReady<S, F, R> {
+    /// Create new `MapPollReady` combinator
+    pub fn new<E>(service: S, f: F) -> Self
+    where
+        S: Service<R>,
+        F: FnMut(Poll<(), S::Error>) -> Poll<(), E>,
+        E: From<S::Error>,
+    {
+        MapPollReady {
+            service,
+            f,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> Clone for MapPollReady<S, F, R>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        MapPollReady {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R, E> Service<R> for MapPollReady<S, F, R>
+where
+    S: Service<R>,
+    F: FnMut(Poll<(), S::Error>) -> Poll<(), E>,
+    E: From<S::Error>,
+{
+    type Response = S::Response;
+    type Error = E;
+    type Future = FromErrFuture<S::Future, E>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        (self.f)(self.service.poll_ready())
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        FromErrFuture::new(self.service.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::{Async, Future};
+
+    use super::*;
+    use ServiceExt;
+
+    /// Is ready on every other poll.
+    struct Alternating {
+        ready: bool,
+    }
+
+    impl Service<()> for Alternating {
+        type Response = ();
+        type Error = &'static str;
+        type Future = FutureResult<(), &'static str>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            self.ready = !self.ready;
+            if self.ready {
+                Ok(Async::Ready(()))
+            } else {
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::err("failed")
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Overloaded,
+        Inner(&'static str),
+    }
+
+    impl From<&'static str> for Error {
+        fn from(e: &'static str) -> Self {
+            Error::Inner(e)
+        }
+    }
+
+    fn shed_load(poll: Poll<(), &'static str>) -> Poll<(), Error> {
+        match poll {
+            Ok(Async::NotReady) => Err(Error::Overloaded),
+            Ok(Async::Ready(())) => Ok(Async::Ready(())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[test]
+    fn not_ready_becomes_overloaded() {
+        let mut svc = Alternating { ready: false }.map_poll_ready(shed_load);
+        assert_eq!(svc.poll_ready(), Ok(Async::Ready(())));
+        assert_eq!(svc.poll_ready(), Err(Error::Overloaded));
+        assert_eq!(svc.poll_ready(), Ok(Async::Ready(())));
+    }
+
+    #[test]
+    fn response_errors_are_converted() {
+        let mut svc = Alternating { ready: false }.map_poll_ready(shed_load);
+        assert_eq!(svc.call(()).wait(), Err(Error::Inner("failed")));
+    }
+}
@@ -0,0 +1,184 @@
+use futures::{Future, IntoFuture, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `map_request_async` combinator, transforming each request
+/// asynchronously before it is dispatched to the inner service.
+///
+/// `poll_ready` reports the readiness of the inner service, so callers are not
+/// admitted while it is saturated. Since a transform may take arbitrarily long,
+/// each response future also waits for its handle to the inner service to
+/// become ready once the transform completes, rather than relying on readiness
+/// observed before the transform began.
+///
+/// This is created by the `ServiceExt::map_request_async` method.
+pub struct MapRequestAsync<S, F, R2> {
+    service: S,
+    f: F,
+    _p: PhantomData<fn(R2)>,
+}
+
+pub struct MapRequestAsyncFuture<S, T, Request>
+where
+    S: Service<Request>,
+{
+    transform: T,
+    request: Option<Request>,
+    service: S,
+    fut: Option<S::Future>,
+}
+
+impl<S, F, R2> MapRequestAsync<S, F, R2> {
+    /// Create new `MapRequestAsync` combinator
+    pub fn new<Fut>(service: S, f: F) -> Self
+    where
+        S: Service<Fut::Item> + Clone,
+        F: FnMut(R2) -> Fut,
+        Fut: IntoFuture,
+        S::Error: From<Fut::Error>,
+    {
+        MapRequestAsync {
+            service,
+            f,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R2> Clone for MapRequestAsync<S, F, R2>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        MapRequestAsync {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R2, Fut> Service<R2> for MapRequestAsync<S, F, R2>
+where
+    S: Service<Fut::Item> + Clone,
+    F: FnMut(R2) -> Fut,
+    Fut: IntoFuture,
+    S::Error: From<Fut::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MapRequestAsyncFuture<S, Fut::Future, Fut::Item>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: R2) -> Self::Future {
+        MapRequestAsyncFuture {
+            transform: (self.f)(req).into_future(),
+            request: None,
+            service: self.service.clone(),
+            fut: None,
+        }
+    }
+}
+
+impl<S, T, Request> Future for MapRequestAsyncFuture<S, T, Request>
+where
+    S: Service<Request>,
+    T: Future<Item = Request>,
+    S::Error: From<T::Error>,
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(ref mut fut) = self.fut {
+            return fut.poll();
+        }
+
+        if self.request.is_none() {
+            let req = try_ready!(self.transform.poll().map_err(S::Error::from));
+            self.request = Some(req);
+        }
+
+        try_ready!(self.service.poll_ready());
+        let req = self.request.take().expect("polled after complete");
+        self.fut = Some(self.service.call(req));
+        self.poll()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::sync::oneshot;
+    use futures::Async;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Echoes requests, refusing to be ready unless it is open.
+    #[derive(Clone)]
+    struct Srv(Rc<Cell<bool>>);
+
+    impl Service<String> for Srv {
+        type Response = String;
+        type Error = ();
+        type Future = FutureResult<String, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            if self.0.get() {
+                Ok(Async::Ready(()))
+            } else {
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, req: String) -> Self::Future {
+            future::ok(req)
+        }
+    }
+
+    #[test]
+    fn delayed_transform_then_dispatch() {
+        future::lazy(|| {
+            let open = Rc::new(Cell::new(true));
+            let signers = Rc::new(RefCell::new(Vec::new()));
+
+            // Signs each request once its signer has been triggered.
+            let mut srv = {
+                let signers = signers.clone();
+                Srv(open.clone()).map_request_async(move |req: &'static str| {
+                    let (tx, rx) = oneshot::channel();
+                    signers.borrow_mut().push(tx);
+                    rx.map(move |()| format!("{} (signed)", req)).map_err(|_| ())
+                })
+            };
+
+            assert!(srv.poll_ready().unwrap().is_ready());
+            let mut fut = srv.call("hello");
+            assert_eq!(fut.poll(), Ok(Async::NotReady));
+
+            signers.borrow_mut().remove(0).send(()).unwrap();
+            assert_eq!(fut.poll(), Ok(Async::Ready("hello (signed)".to_owned())));
+
+            // Backpressure from the inner service is observed after the
+            // transform completes.
+            let mut fut = srv.call("again");
+            signers.borrow_mut().remove(0).send(()).unwrap();
+            open.set(false);
+            assert_eq!(fut.poll(), Ok(Async::NotReady));
+            assert!(srv.poll_ready().unwrap().is_not_ready());
+
+            open.set(true);
+            assert_eq!(fut.poll(), Ok(Async::Ready("again (signed)".to_owned())));
+
+            Ok::<_, ()>(())
+        }).wait().unwrap();
+    }
+}
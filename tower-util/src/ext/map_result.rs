@@ -0,0 +1,150 @@
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `map_result` combinator, changing the type of a service's
+/// response and error in one step.
+///
+/// Errors from `poll_ready` are converted with `From`, since there is no
+/// response for `F` to produce in their place.
+///
+/// This is created by the `ServiceExt::map_result` method.
+pub struct MapResult<T, F, R, E> {
+    service: T,
+    f: F,
+    _p: PhantomData<fn() -> (R, E)>,
+}
+
+pub struct MapResultFuture<T, F> {
+    f: F,
+    fut: T,
+}
+
+impl<T, F, R, E> MapResult<T, F, R, E> {
+    /// Create new `MapResult` combinator
+    pub fn new<Request>(service: T, f: F) -> Self
+    where
+        T: Service<Request>,
+        F: FnMut(Result<T::Response, T::Error>) -> Result<R, E> + Clone,
+        E: From<T::Error>,
+    {
+        MapResult {
+            service,
+            f,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T, F, R, E> Clone for MapResult<T, F, R, E>
+where
+    T: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        MapResult {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T, F, R, E, Request> Service<Request> for MapResult<T, F, R, E>
+where
+    T: Service<Request>,
+    F: FnMut(Result<T::Response, T::Error>) -> Result<R, E> + Clone,
+    E: From<T::Error>,
+{
+    type Response = R;
+    type Error = E;
+    type Future = MapResultFuture<T::Future, F>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready().map_err(E::from)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        MapResultFuture {
+            f: self.f.clone(),
+            fut: self.service.call(req),
+        }
+    }
+}
+
+impl<T, F, R, E> Future for MapResultFuture<T, F>
+where
+    T: Future,
+    F: FnMut(Result<T::Item, T::Error>) -> Result<R, E>,
+{
+    type Item = R;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let result = match self.fut.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(rsp)) => Ok(rsp),
+            Err(e) => Err(e),
+        };
+        (self.f)(result).map(Async::Ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{err, ok, FutureResult};
+
+    use super::*;
+    use ServiceExt;
+
+    /// Fails requests for odd numbers.
+    struct Srv;
+
+    impl Service<u32> for Srv {
+        type Response = u32;
+        type Error = u32;
+        type Future = FutureResult<u32, u32>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, n: u32) -> Self::Future {
+            if n % 2 == 0 {
+                ok(n)
+            } else {
+                err(n)
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Error(String);
+
+    impl From<u32> for Error {
+        fn from(n: u32) -> Self {
+            Error(format!("poll_ready failed: {}", n))
+        }
+    }
+
+    /// Treats 1 as a successful "not found", and fails on 2.
+    fn lookup(result: Result<u32, u32>) -> Result<Option<u32>, Error> {
+        match result {
+            Ok(2) => Err(Error("2 is forbidden".into())),
+            Ok(n) => Ok(Some(n)),
+            Err(1) => Ok(None),
+            Err(n) => Err(Error(format!("{} failed", n))),
+        }
+    }
+
+    #[test]
+    fn test_converts_both_arms() {
+        let mut srv = Srv.map_result(lookup);
+
+        assert_eq!(srv.call(4).poll(), Ok(Async::Ready(Some(4))));
+        assert_eq!(srv.call(1).poll(), Ok(Async::Ready(None)));
+        assert_eq!(srv.call(2).poll(), Err(Error("2 is forbidden".into())));
+        assert_eq!(srv.call(3).poll(), Err(Error("3 failed".into())));
+    }
+}
@@ -1,23 +1,83 @@
 //! Combinators for working with `Service`s
 
-use futures::IntoFuture;
+use futures::future::Executor;
+use futures::{IntoFuture, Poll};
+#[cfg(feature = "retry")]
+use tower_retry::{budget::Budget, Policy, Retry, RetryAfter};
 use tower_service::Service;
 
+use std::error::Error;
+use std::hash::Hash;
+#[cfg(feature = "retry")]
+use std::sync::Arc;
+#[cfg(any(feature = "timeout", feature = "retry"))]
+use std::time::Duration;
+#[cfg(feature = "timeout")]
+use std::time::Instant;
+
+use boxed::{BoxError, BoxedFuture, CloneBoxService};
+use fire_and_forget::{Background, FireAndForget};
+use option::OptionService;
+use per_key_concurrency::PerKeyConcurrency;
+use queue_ahead::QueueAhead;
+#[cfg(feature = "timeout")]
+use tower_timeout::{PollReadyTimeout, Timeout, TimerHandle, WithDeadline};
+#[cfg(feature = "load")]
+use ConcurrencyLimitLoad;
+
 mod and_then;
+mod and_then_service;
 mod apply;
+mod call_ready;
+mod enrich_err;
+mod flatten_err;
+mod follow;
 mod from_err;
+mod instrument;
+mod into_direct;
+mod join;
 mod map;
 mod map_err;
+mod map_poll_ready;
+mod map_request_async;
+mod map_result;
+mod named;
 mod ready;
+mod ready_oneshot;
+mod repeat_until;
+#[cfg(feature = "retry")]
+mod retry;
+mod tap_err;
 mod then;
+mod try_map_request;
+mod unwrap_or_else;
 
 pub use self::and_then::AndThen;
+pub use self::and_then_service::AndThenService;
 pub use self::apply::Apply;
+pub use self::call_ready::CallReady;
+pub use self::enrich_err::{EnrichErr, EnrichErrFuture};
+pub use self::flatten_err::{FlattenErr, FlattenErrFuture};
+pub use self::follow::Follow;
 pub use self::from_err::FromErr;
+pub use self::instrument::{Instrument, InstrumentFuture, Span};
+pub use self::into_direct::IntoDirect;
+pub use self::join::Join;
 pub use self::map::Map;
 pub use self::map_err::MapErr;
+pub use self::map_poll_ready::MapPollReady;
+pub use self::map_request_async::{MapRequestAsync, MapRequestAsyncFuture};
+pub use self::map_result::MapResult;
+pub use self::named::Named;
 pub use self::ready::Ready;
+pub use self::ready_oneshot::ReadyOneshot;
+pub use self::repeat_until::{RepeatUntil, RepeatUntilFuture};
+#[cfg(feature = "retry")]
+pub use self::retry::{Budgeted, BudgetedFuture, BudgetedRetry, SimpleRetry};
+pub use self::tap_err::{TapErr, TapErrFuture};
 pub use self::then::Then;
+pub use self::try_map_request::TryMapRequest;
+pub use self::unwrap_or_else::{UnwrapOrElse, UnwrapOrElseFuture};
 
 impl<T: ?Sized, Request> ServiceExt<Request> for T
 where
@@ -35,6 +95,101 @@ pub trait ServiceExt<Request>: Service<Request> {
         Ready::new(self)
     }
 
+    /// A future that waits for this service to become ready, dispatches
+    /// `request`, and yields the response along with the service, so that the
+    /// caller regains ownership of it, e.g. to return it to a pool.
+    ///
+    /// If the service fails, it is dropped along with the future.
+    fn ready_oneshot(self, request: Request) -> ReadyOneshot<Self, Request>
+    where
+        Self: Sized,
+    {
+        ReadyOneshot::new(self, request)
+    }
+
+    /// A future that waits for this service to become ready, dispatches
+    /// `request`, and yields the response.
+    ///
+    /// Unlike `ready_oneshot`, the service is only borrowed, so it need not be
+    /// returned to the caller.
+    fn call_ready(&mut self, request: Request) -> CallReady<Self, Request>
+    where
+        Self: Sized,
+    {
+        CallReady::new(self, request)
+    }
+
+    /// Call both this service and `other` with (clones of) each request,
+    /// yielding both responses as a tuple, e.g. to fan out to two backends.
+    ///
+    /// The call fails as soon as either service fails.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn join<B>(self, other: B) -> Join<Self, B, Request>
+    where
+        Self: Sized,
+        B: Service<Request, Error = Self::Error>,
+        Request: Clone,
+    {
+        Join::new(self, other)
+    }
+
+    /// Wrap this service as a `DirectService`, so that it may be used where one
+    /// is required.
+    ///
+    /// A `Service` drives its own response futures, so the returned service's
+    /// `poll_service` and `poll_close` complete immediately.
+    fn into_direct_service(self) -> IntoDirect<Self>
+    where
+        Self: Sized,
+    {
+        IntoDirect::new(self)
+    }
+
+    /// Limit the number of in-flight requests for each key returned by `key`,
+    /// e.g. each tenant, to `max`.
+    ///
+    /// A request whose key is already at its limit fails immediately, while
+    /// requests for other keys proceed.
+    fn throttle_concurrency_per_key<F, K>(self, key: F, max: usize) -> PerKeyConcurrency<Self, F, K>
+    where
+        Self: Sized,
+        F: Fn(&Request) -> K,
+        K: Hash + Eq,
+    {
+        PerKeyConcurrency::new(self, key, max)
+    }
+
+    /// Queue up to `capacity` requests in-process while this service is not
+    /// ready, dispatching them in order as it becomes ready.
+    ///
+    /// Unlike `tower-buffer`, no worker task is spawned: queued requests are
+    /// dispatched from `poll_ready`, so the returned service must keep being
+    /// polled. It is intended for single-threaded use.
+    fn buffer_requests(self, capacity: usize) -> QueueAhead<Self, Request>
+    where
+        Self: Sized,
+    {
+        QueueAhead::new(self, capacity)
+    }
+
+    /// Re-issue each request until its response satisfies `f`, e.g. to poll
+    /// the status of a long-running operation, dispatching it at most `max`
+    /// times.
+    ///
+    /// The last response is yielded if none satisfies `f`. Requests are cloned
+    /// for each attempt, and each response future waits for a clone of this
+    /// service to become ready before re-issuing its request.
+    fn repeat_until<F>(self, f: F, max: usize) -> RepeatUntil<Self, F, Request>
+    where
+        Self: Sized + Clone,
+        F: Fn(&Self::Response) -> bool + Clone,
+        Request: Clone,
+    {
+        RepeatUntil::new(self, f, max)
+    }
+
     fn apply<F, In, Out>(self, f: F) -> Apply<Self, F, In, Out, Request>
     where
         Self: Service<Request> + Clone + Sized,
@@ -61,6 +216,24 @@ pub trait ServiceExt<Request>: Service<Request> {
         AndThen::new(self, service)
     }
 
+    /// Call another service with the response of this one, waiting for the
+    /// second service to become ready before calling it.
+    ///
+    /// Unlike `and_then`, readiness of `service` is driven by the response
+    /// future rather than by `poll_ready`. Errors from this service are
+    /// converted into the second service's error type via `From`.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn and_then_service<B>(self, service: B) -> AndThenService<Self, B>
+    where
+        Self: Sized,
+        B: Service<Self::Response> + Clone,
+        B::Error: From<Self::Error>,
+    {
+        AndThenService::new(self, service)
+    }
+
     /// Map this service's error to any error implementing `From` for
     /// this service`s `Error`.
     ///
@@ -87,6 +260,77 @@ pub trait ServiceExt<Request>: Service<Request> {
         Then::new(self, service)
     }
 
+    /// Re-issue requests as directed by the responses they produce.
+    ///
+    /// `f` inspects each response and may return a follow-up request, which is
+    /// sent to this service in place of returning the response, as with an HTTP
+    /// redirect. At most `max_hops` follow-up requests are sent per call; the
+    /// last response is returned once the limit is reached.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn follow<F>(self, f: F, max_hops: usize) -> Follow<Self, F, Request>
+    where
+        Self: Clone + Sized,
+        F: Fn(&Self::Response) -> Option<Request> + Clone,
+    {
+        Follow::new(self, f, max_hops)
+    }
+
+    /// Attach a span, created from each request by `make_span`, to each call.
+    ///
+    /// The span is entered while the response future is polled. `Span` is
+    /// implemented by the caller, so that any tracing library may be used.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn instrument<F, S>(self, make_span: F) -> Instrument<Self, F, Request>
+    where
+        Self: Sized,
+        F: FnMut(&Request) -> S,
+        S: Span,
+    {
+        Instrument::new(self, make_span)
+    }
+
+    /// Label this service for debugging.
+    ///
+    /// `name` prefixes the service's `Debug` output, and each call is traced
+    /// to `log` under it, so that layers of a nested stack can be told apart.
+    fn named(self, name: &'static str) -> Named<Self>
+    where
+        Self: Sized,
+    {
+        Named::new(self, name)
+    }
+
+    /// Wrap this service in an `OptionService` that forwards requests to it.
+    ///
+    /// This is useful when a layer in a stack may be absent: the absent case
+    /// may be built with `optional_none`, so that both have the same type.
+    fn optional(self) -> OptionService<Self>
+    where
+        Self: Sized,
+    {
+        OptionService::some(self)
+    }
+
+    /// Dispatch requests for their side effects only, resolving each call to
+    /// `()` immediately.
+    ///
+    /// Response futures are spawned onto `executor`, which drives them to
+    /// completion in the background. Their errors are logged.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn fire_and_forget<E>(self, executor: E) -> FireAndForget<Self, E>
+    where
+        Self: Sized,
+        E: Executor<Background<Self::Future>>,
+    {
+        FireAndForget::new(self, executor)
+    }
+
     /// Map this service's output to a different type, returning a new service of
     /// the resulting type.
     ///
@@ -119,4 +363,418 @@ pub trait ServiceExt<Request>: Service<Request> {
     {
         MapErr::new(self, f)
     }
+
+    /// Flatten a service that responds with a `Result` into one that fails
+    /// with that result's error, converting both it and this service's error
+    /// into `E` with `From`.
+    ///
+    /// This collapses an application-level error carried in a response, e.g.
+    /// from a `then` or `map_result` adapter, into a single error at the top of
+    /// a stack.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn flatten_err<E, T, U>(self) -> FlattenErr<Self, E>
+    where
+        Self: Sized + Service<Request, Response = Result<T, U>>,
+        E: From<U> + From<Self::Error>,
+    {
+        FlattenErr::new(self)
+    }
+
+    /// Transform each result of this service's `poll_ready`, e.g. to shed load
+    /// by turning `NotReady` into an error.
+    ///
+    /// Errors from response futures are converted with `From`.
+    fn map_poll_ready<F, E>(self, f: F) -> MapPollReady<Self, F, Request>
+    where
+        Self: Sized,
+        F: FnMut(Poll<(), Self::Error>) -> Poll<(), E>,
+        E: From<Self::Error>,
+    {
+        MapPollReady::new(self, f)
+    }
+
+    /// Turn this service's errors into fallback responses, e.g. to serve a
+    /// degraded response rather than fail.
+    ///
+    /// `f` is called with each error, and the response it returns is yielded
+    /// instead. The returned service never fails, so its error type `E` may be
+    /// chosen freely, e.g. to match other services it is combined with.
+    fn unwrap_or_else<F, E>(self, f: F) -> UnwrapOrElse<Self, F, Request, E>
+    where
+        Self: Sized,
+        F: Fn(Self::Error) -> Self::Response + Clone,
+    {
+        UnwrapOrElse::new(self, f)
+    }
+
+    /// Fail responses that do not complete by the absolute instant `at`, e.g.
+    /// a deadline computed upstream.
+    ///
+    /// This is only available when the `timeout` feature is enabled.
+    #[cfg(feature = "timeout")]
+    fn deadline(self, at: Instant) -> WithDeadline<Self>
+    where
+        Self: Sized,
+    {
+        WithDeadline::new(self, at)
+    }
+
+    /// Fail responses that do not complete within `dur`, as measured by `timer`
+    /// rather than the default timer.
+    ///
+    /// This allows a test to trip the timeout deterministically, with a timer
+    /// driven by a mock clock that is also the default clock.
+    ///
+    /// This is only available when the `timeout` feature is enabled.
+    #[cfg(feature = "timeout")]
+    fn timeout_with_clock(self, dur: Duration, timer: TimerHandle) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        Timeout::new(self, dur).with_timer(timer)
+    }
+
+    /// Fail `poll_ready` with `Error::ReadyTimeout` if this service does not
+    /// become ready within `dur`. Responses are not timed.
+    ///
+    /// This is only available when the `timeout` feature is enabled.
+    #[cfg(feature = "timeout")]
+    fn poll_ready_timeout(self, dur: Duration) -> PollReadyTimeout<Self>
+    where
+        Self: Sized,
+    {
+        PollReadyTimeout::new(self, dur)
+    }
+
+    /// Attach context captured from each request, e.g. a request id, to the
+    /// errors produced by its response future.
+    ///
+    /// `capture` is called with each request before it is dispatched, and `f`
+    /// combines an error with the context captured from the request that
+    /// produced it. Errors from `poll_ready` carry no request, so they are
+    /// converted with `From`.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn enrich_err<C, Ctx, F, E>(self, capture: C, f: F) -> EnrichErr<Self, C, F, Request, E>
+    where
+        Self: Sized,
+        C: FnMut(&Request) -> Ctx,
+        F: FnMut(Self::Error, Ctx) -> E + Clone,
+        E: From<Self::Error>,
+    {
+        EnrichErr::new(self, capture, f)
+    }
+
+    /// Observe each error produced by this service's response futures, e.g. to
+    /// count failures, leaving the error unchanged.
+    ///
+    /// `f` is not called for successful responses, nor for errors from
+    /// `poll_ready`.
+    fn tap_err<F>(self, f: F) -> TapErr<Self, F, Request>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Error) + Clone,
+    {
+        TapErr::new(self, f)
+    }
+
+    /// Transform each request asynchronously before dispatching it to this
+    /// service, e.g. to sign it with a remote key service.
+    ///
+    /// This service must be `Clone`, so that each response future can wait for
+    /// it to become ready once its transform completes.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn map_request_async<F, R2, Fut>(self, f: F) -> MapRequestAsync<Self, F, R2>
+    where
+        Self: Clone + Sized,
+        F: FnMut(R2) -> Fut,
+        Fut: IntoFuture<Item = Request>,
+        Self::Error: From<Fut::Error>,
+    {
+        MapRequestAsync::new(self, f)
+    }
+
+    /// Transform each request with a fallible function before dispatching it
+    /// to this service, e.g. to parse or validate it.
+    ///
+    /// A request that fails to transform fails immediately, without being
+    /// dispatched; its error is converted to this service's error with `From`.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn map_request_result<F, R2, E>(self, f: F) -> TryMapRequest<Self, F, R2>
+    where
+        Self: Sized,
+        F: FnMut(R2) -> Result<Request, E>,
+        Self::Error: From<E>,
+    {
+        TryMapRequest::new(self, f)
+    }
+
+    /// Map this service's result, both its response and its error, to a
+    /// different result, returning a new service.
+    ///
+    /// This is more general than `map` and `map_err`: `f` may, for instance,
+    /// turn an error into a response. Errors from `poll_ready` are converted
+    /// to the new error type with `From`.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn map_result<F, R, E>(self, f: F) -> MapResult<Self, F, R, E>
+    where
+        Self: Sized,
+        F: FnMut(Result<Self::Response, Self::Error>) -> Result<R, E> + Clone,
+        E: From<Self::Error>,
+    {
+        MapResult::new(self, f)
+    }
+
+    /// Box this service's error into a `BoxError` trait object.
+    ///
+    /// This is useful for unifying the error types of services that are
+    /// otherwise compatible, e.g. so that they can be stored together as
+    /// `BoxService`s.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn err_into_boxed(self) -> MapErr<Self, fn(Self::Error) -> BoxError, BoxError>
+    where
+        Self: Sized,
+        Self::Error: Error + Send + Sync + 'static,
+    {
+        MapErr::new(self, into_box_error::<Self::Error> as fn(_) -> _)
+    }
+
+    /// Erase this service's type into a `CloneBoxService`.
+    ///
+    /// Unlike a `BoxService`, the result may be cloned. This allows services of
+    /// differing types to be stored together, e.g. in a `Vec` or a `Discover`
+    /// providing endpoints to a balancer.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn clone_box(self) -> CloneBoxService<Request, Self::Response, Self::Error>
+    where
+        Self: Clone + Send + Sized + 'static,
+        Self::Future: Send + 'static,
+    {
+        CloneBoxService::new(self)
+    }
+
+    /// Box this service's response futures, keeping the service type concrete.
+    ///
+    /// This gives a service whose future type cannot be named, e.g. because
+    /// it is built from closures, a `BoxFuture` as its future type.
+    ///
+    /// Note that this function consumes the receiving service and returns a
+    /// wrapped version of it.
+    fn boxed_future(self) -> BoxedFuture<Self, Request>
+    where
+        Self: Sized,
+        Self::Future: Send + 'static,
+    {
+        BoxedFuture::new(self)
+    }
+
+    /// Erase this service's type into a `CloneBoxService`.
+    ///
+    /// This is equivalent to `clone_box`.
+    fn boxed_clone(self) -> CloneBoxService<Request, Self::Response, Self::Error>
+    where
+        Self: Clone + Send + Sized + 'static,
+        Self::Future: Send + 'static,
+    {
+        self.clone_box()
+    }
+
+    /// Limit the number of in-flight requests to `max`, exposing the current
+    /// in-flight count as a `Load` metric.
+    ///
+    /// This is only available when the `load` feature is enabled.
+    #[cfg(feature = "load")]
+    fn concurrency_limit_load(self, max: usize) -> ConcurrencyLimitLoad<Self, Request>
+    where
+        Self: Sized,
+    {
+        ConcurrencyLimitLoad::new(self, max)
+    }
+
+    /// Retry failed requests according to `policy`, limited by `budget`.
+    ///
+    /// Every request through the returned service deposits into `budget`, and
+    /// every retry withdraws from it, so that retries are capped at a fraction
+    /// of overall traffic. The budget may be shared with other services.
+    ///
+    /// This is only available when the `retry` feature is enabled.
+    #[cfg(feature = "retry")]
+    fn retry_with_budget<P>(self, policy: P, budget: Arc<Budget>) -> BudgetedRetry<P, Self>
+    where
+        Self: Clone + Sized,
+        P: Policy<Request, Self::Response, Self::Error> + Clone,
+    {
+        BudgetedRetry::new(policy, self, budget)
+    }
+
+    /// Retry requests that fail with a retryable error, up to `max_retries`
+    /// times, without writing a `Policy`.
+    ///
+    /// `clone_request` produces the copy of each request that is held for
+    /// retry, so requests need not implement `Clone`; if it returns `None`,
+    /// the request is not retried. `is_retryable` decides whether an error is
+    /// worth retrying.
+    ///
+    /// This is only available when the `retry` feature is enabled.
+    #[cfg(feature = "retry")]
+    fn simple_retry<C, F>(
+        self,
+        max_retries: usize,
+        clone_request: C,
+        is_retryable: F,
+    ) -> Retry<SimpleRetry<C, F>, Self>
+    where
+        Self: Clone + Sized,
+        C: Fn(&Request) -> Option<Request> + Clone,
+        F: Fn(&Self::Error) -> bool + Clone,
+    {
+        Retry::new(SimpleRetry::new(max_retries, clone_request, is_retryable), self)
+    }
+
+    /// Retry requests that fail with an error carrying a server-specified
+    /// delay, such as an HTTP `Retry-After`, once that delay has elapsed.
+    ///
+    /// `retry_after` extracts the delay from an error; errors without one are
+    /// not retried. Each request is retried at most `max_retries` times, and
+    /// `clone_request` is used as with `simple_retry`.
+    ///
+    /// This is only available when the `retry` feature is enabled.
+    #[cfg(feature = "retry")]
+    fn with_retry_after_extraction<C, F>(
+        self,
+        max_retries: usize,
+        clone_request: C,
+        retry_after: F,
+    ) -> Retry<RetryAfter<C, F>, Self>
+    where
+        Self: Clone + Sized,
+        C: Fn(&Request) -> Option<Request> + Clone,
+        F: Fn(&Self::Error) -> Option<Duration> + Clone,
+    {
+        Retry::new(RetryAfter::new(max_retries, clone_request, retry_after), self)
+    }
+}
+
+fn into_box_error<E>(e: E) -> BoxError
+where
+    E: Error + Send + Sync + 'static,
+{
+    Box::new(e)
+}
+
+#[cfg(all(test, feature = "timeout"))]
+mod tests {
+    use futures::future::{self, Empty};
+    use futures::{Async, Future};
+    use tower_timeout::test_util::{mock_timer, with_mock_clock, with_mock_timer};
+    use tower_timeout::Error;
+
+    use super::*;
+
+    /// Never responds.
+    struct Pending;
+
+    impl Service<()> for Pending {
+        type Response = ();
+        type Error = ();
+        type Future = Empty<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::empty()
+        }
+    }
+
+    #[test]
+    fn deadline_fails_late_responses() {
+        with_mock_timer(|time, timer| {
+            let deadline = *time.as_mut() + Duration::from_secs(1);
+            let mut rsp = Pending.deadline(deadline).call(());
+            let mut poll = || future::lazy(|| Ok::<_, ()>(rsp.poll())).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::Timeout) => {}
+                _ => panic!("response should have timed out"),
+            }
+        });
+    }
+
+    #[test]
+    fn timeout_with_clock_trips_on_mock_clock() {
+        with_mock_clock(|time| {
+            let mut timer = mock_timer(time);
+            let mut rsp = Pending
+                .timeout_with_clock(Duration::from_secs(1), timer.handle())
+                .call(());
+            let mut poll = || future::lazy(|| Ok::<_, ()>(rsp.poll())).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::Timeout) => {}
+                _ => panic!("response should have timed out"),
+            }
+        });
+    }
+
+    /// Never becomes ready.
+    struct Unready;
+
+    impl Service<()> for Unready {
+        type Response = ();
+        type Error = ();
+        type Future = Empty<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::NotReady)
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::empty()
+        }
+    }
+
+    #[test]
+    fn poll_ready_timeout_fails_unready_service() {
+        with_mock_timer(|time, timer| {
+            let mut svc = Unready.poll_ready_timeout(Duration::from_secs(1));
+            let mut poll = || future::lazy(|| Ok::<_, ()>(svc.poll_ready())).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+
+            *time.as_mut() += Duration::from_secs(1);
+            timer.turn(None).unwrap();
+            match poll() {
+                Err(Error::ReadyTimeout) => {}
+                _ => panic!("poll_ready should have timed out"),
+            }
+        });
+    }
 }
@@ -0,0 +1,101 @@
+use futures::Poll;
+use tower_service::Service;
+
+use std::fmt;
+
+/// Service for the `named` combinator, labeling a service for debugging.
+///
+/// The label prefixes the inner service's `Debug` output, so that layers in a
+/// deeply nested stack can be told apart, and each call is traced to `log`
+/// under the label.
+///
+/// This is created by the `ServiceExt::named` method.
+#[derive(Clone)]
+pub struct Named<S> {
+    name: &'static str,
+    service: S,
+}
+
+impl<S> Named<S> {
+    /// Create new `Named` combinator
+    pub fn new(service: S, name: &'static str) -> Self {
+        Named { name, service }
+    }
+
+    /// Returns the label given to this service.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+}
+
+impl<S, R> Service<R> for Named<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        trace!("{}: poll_ready", self.name);
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        trace!("{}: call", self.name);
+        self.service.call(req)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Named<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}", self.name, self.service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::{Async, Future};
+
+    use super::*;
+    use ServiceExt;
+
+    #[derive(Debug)]
+    struct Echo;
+
+    impl Service<&'static str> for Echo {
+        type Response = &'static str;
+        type Error = ();
+        type Future = FutureResult<&'static str, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            future::ok(req)
+        }
+    }
+
+    #[test]
+    fn label_appears_in_debug() {
+        let svc = Echo.named("auth");
+        assert_eq!(format!("{:?}", svc), "auth: Echo");
+
+        let nested = Echo.named("inner").named("outer");
+        assert_eq!(format!("{:?}", nested), "outer: inner: Echo");
+    }
+
+    #[test]
+    fn dispatches_to_inner() {
+        let mut svc = Echo.named("echo");
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call("hello").wait(), Ok("hello"));
+    }
+}
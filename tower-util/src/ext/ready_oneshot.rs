@@ -0,0 +1,139 @@
+use std::fmt;
+
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+/// Future yielding the response to a single request along with the `Service`
+/// that produced it.
+///
+/// `ReadyOneshot` values are produced by `ServiceExt::ready_oneshot`.
+pub struct ReadyOneshot<T, Request>
+where
+    T: Service<Request>,
+{
+    state: State<T, Request>,
+}
+
+enum State<T, Request>
+where
+    T: Service<Request>,
+{
+    /// Waiting for the service to become ready.
+    NotReady(T, Request),
+    /// Waiting for the response.
+    Called(T::Future, T),
+    Done,
+}
+
+impl<T, Request> ReadyOneshot<T, Request>
+where
+    T: Service<Request>,
+{
+    pub(super) fn new(service: T, request: Request) -> Self {
+        ReadyOneshot {
+            state: State::NotReady(service, request),
+        }
+    }
+}
+
+impl<T, Request> Future for ReadyOneshot<T, Request>
+where
+    T: Service<Request>,
+{
+    type Item = (T::Response, T);
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, T::Error> {
+        use std::mem;
+
+        loop {
+            match mem::replace(&mut self.state, State::Done) {
+                State::NotReady(mut service, request) => {
+                    match service.poll_ready()? {
+                        Async::Ready(()) => {
+                            let response = service.call(request);
+                            self.state = State::Called(response, service);
+                        }
+                        Async::NotReady => {
+                            self.state = State::NotReady(service, request);
+                            return Ok(Async::NotReady);
+                        }
+                    }
+                }
+                State::Called(mut response, service) => {
+                    match response.poll()? {
+                        Async::Ready(rsp) => return Ok(Async::Ready((rsp, service))),
+                        Async::NotReady => {
+                            self.state = State::Called(response, service);
+                            return Ok(Async::NotReady);
+                        }
+                    }
+                }
+                State::Done => panic!("called `poll` after future completed"),
+            }
+        }
+    }
+}
+
+impl<T, Request> fmt::Debug for ReadyOneshot<T, Request>
+where
+    T: Service<Request> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let service = match self.state {
+            State::NotReady(ref service, _) | State::Called(_, ref service) => Some(service),
+            State::Done => None,
+        };
+        f.debug_struct("ReadyOneshot")
+            .field("service", &service)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::task;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Becomes ready on every other poll, yielding in between, and counts the requests it serves.
+    struct Counter {
+        ready: bool,
+        served: usize,
+    }
+
+    impl Service<&'static str> for Counter {
+        type Response = String;
+        type Error = ();
+        type Future = FutureResult<String, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            self.ready = !self.ready;
+            if self.ready {
+                Ok(Async::Ready(()))
+            } else {
+                task::current().notify();
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            self.served += 1;
+            future::ok(format!("{}-{}", req, self.served))
+        }
+    }
+
+    #[test]
+    fn service_is_recovered_for_reuse() {
+        let svc = Counter { ready: true, served: 0 };
+
+        let (rsp, svc) = svc.ready_oneshot("a").wait().unwrap();
+        assert_eq!(rsp, "a-1");
+
+        let (rsp, svc) = svc.ready_oneshot("b").wait().unwrap();
+        assert_eq!(rsp, "b-2");
+        assert_eq!(svc.served, 2);
+    }
+}
@@ -0,0 +1,182 @@
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `repeat_until` combinator, re-issuing each request until its
+/// response satisfies a predicate, e.g. to poll the status of a long-running
+/// operation.
+///
+/// Each request is dispatched up to `max` times. The first response for which
+/// `f` returns true is yielded, or, if none does, the last response. Between
+/// attempts, the response future waits for its own clone of the service to
+/// become ready. Errors are returned as-is, without repeating the request.
+///
+/// This is created by the `ServiceExt::repeat_until` method.
+pub struct RepeatUntil<S, F, R> {
+    service: S,
+    f: F,
+    max: usize,
+    _p: PhantomData<fn(R)>,
+}
+
+pub struct RepeatUntilFuture<S, F, R>
+where
+    S: Service<R>,
+{
+    service: S,
+    f: F,
+    request: R,
+    /// The number of times the request may still be re-issued.
+    remaining: usize,
+    state: State<S::Future>,
+}
+
+enum State<T> {
+    /// Waiting for the response to the latest attempt.
+    Called(T),
+    /// Waiting for the service to become ready for the next attempt.
+    Repeating,
+}
+
+impl<S, F, R> RepeatUntil<S, F, R> {
+    /// Create new `RepeatUntil` combinator
+    ///
+    /// # Panics
+    ///
+    /// If `max` is zero.
+    pub fn new(service: S, f: F, max: usize) -> Self
+    where
+        S: Service<R> + Clone,
+        F: Fn(&S::Response) -> bool + Clone,
+        R: Clone,
+    {
+        assert!(max > 0, "each request must be dispatched at least once");
+        RepeatUntil {
+            service,
+            f,
+            max,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> Clone for RepeatUntil<S, F, R>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        RepeatUntil {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            max: self.max,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> Service<R> for RepeatUntil<S, F, R>
+where
+    S: Service<R> + Clone,
+    F: Fn(&S::Response) -> bool + Clone,
+    R: Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RepeatUntilFuture<S, F, R>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let response = self.service.call(req.clone());
+        RepeatUntilFuture {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            request: req,
+            remaining: self.max - 1,
+            state: State::Called(response),
+        }
+    }
+}
+
+impl<S, F, R> Future for RepeatUntilFuture<S, F, R>
+where
+    S: Service<R>,
+    F: Fn(&S::Response) -> bool,
+    R: Clone,
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let next = match self.state {
+                State::Called(ref mut response) => {
+                    let response = try_ready!(response.poll());
+                    if self.remaining == 0 || (self.f)(&response) {
+                        return Ok(Async::Ready(response));
+                    }
+                    State::Repeating
+                }
+                State::Repeating => {
+                    try_ready!(self.service.poll_ready());
+                    self.remaining -= 1;
+                    State::Called(self.service.call(self.request.clone()))
+                }
+            };
+            self.state = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Responds with the number of requests it has received.
+    #[derive(Clone)]
+    struct Status(Rc<Cell<usize>>);
+
+    impl Service<()> for Status {
+        type Response = usize;
+        type Error = ();
+        type Future = FutureResult<usize, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            future::ok(self.0.get())
+        }
+    }
+
+    #[test]
+    fn repeats_until_predicate_is_satisfied() {
+        let calls = Rc::new(Cell::new(0));
+        let mut svc = Status(calls.clone()).repeat_until(|n: &usize| *n == 3, 5);
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(()).wait(), Ok(3));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn yields_last_response_after_max_attempts() {
+        let calls = Rc::new(Cell::new(0));
+        let mut svc = Status(calls.clone()).repeat_until(|_: &usize| false, 2);
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(()).wait(), Ok(2));
+        assert_eq!(calls.get(), 2);
+    }
+}
@@ -0,0 +1,444 @@
+use futures::future::{self, FutureResult};
+use futures::{Async, Future, Poll};
+use tower_retry::budget::Budget;
+use tower_retry::{Outcome, Policy, ResponseFuture, Retry};
+use tower_service::Service;
+
+use std::sync::Arc;
+
+/// Service for the `retry_with_budget` combinator, retrying requests as
+/// allowed by both a policy and a shared `Budget`.
+///
+/// Each request deposits into the budget, and each retry withdraws its
+/// `Policy::retry_cost` from it.
+/// Once the budget is overdrawn, failed requests are no longer retried, even if
+/// the policy would retry them.
+///
+/// This is created by the `ServiceExt::retry_with_budget` method.
+#[derive(Clone, Debug)]
+pub struct BudgetedRetry<P, S> {
+    retry: Retry<Budgeted<P>, S>,
+    budget: Arc<Budget>,
+}
+
+/// A `Policy` that only retries while its `Budget` can be withdrawn from.
+#[derive(Clone, Debug)]
+pub struct Budgeted<P> {
+    policy: P,
+    budget: Arc<Budget>,
+}
+
+/// A `Policy` that retries failed requests a fixed number of times, built from
+/// closures.
+///
+/// `C` clones requests, so that requests need not implement `Clone`, and `F`
+/// decides whether an error is worth retrying.
+///
+/// This is created by the `ServiceExt::simple_retry` method.
+#[derive(Clone, Debug)]
+pub struct SimpleRetry<C, F> {
+    remaining: usize,
+    clone_request: C,
+    is_retryable: F,
+}
+
+/// Yields a `Budgeted` policy once the wrapped policy's future completes.
+#[derive(Debug)]
+pub struct BudgetedFuture<F> {
+    future: F,
+    budget: Option<Arc<Budget>>,
+}
+
+// ===== impl BudgetedRetry =====
+
+impl<P, S> BudgetedRetry<P, S> {
+    /// Create new `BudgetedRetry` combinator
+    pub fn new<Request>(policy: P, service: S, budget: Arc<Budget>) -> Self
+    where
+        P: Policy<Request, S::Response, S::Error> + Clone,
+        S: Service<Request> + Clone,
+    {
+        let policy = Budgeted {
+            policy,
+            budget: budget.clone(),
+        };
+        BudgetedRetry {
+            retry: Retry::new(policy, service),
+            budget,
+        }
+    }
+
+    /// Calls `f` with the `Outcome` of each request once it is no longer
+    /// retried, as with `Retry::on_outcome`.
+    ///
+    /// Requests that the budget prevents from being retried are reported as
+    /// `Outcome::BudgetExhausted`.
+    pub fn on_outcome<F>(self, f: F) -> Self
+    where
+        F: Fn(Outcome) + Send + Sync + 'static,
+    {
+        BudgetedRetry {
+            retry: self.retry.on_outcome(f),
+            ..self
+        }
+    }
+
+    /// Returns the budget shared by all requests through this service.
+    pub fn budget(&self) -> &Arc<Budget> {
+        &self.budget
+    }
+}
+
+impl<P, S, Request> Service<Request> for BudgetedRetry<P, S>
+where
+    P: Policy<Request, S::Response, S::Error> + Clone,
+    S: Service<Request> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<Budgeted<P>, S, Request>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.retry.poll_ready()
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.budget.deposit();
+        self.retry.call(request)
+    }
+}
+
+// ===== impl Budgeted =====
+
+impl<P, Req, Res, E> Policy<Req, Res, E> for Budgeted<P>
+where
+    P: Policy<Req, Res, E>,
+{
+    type Future = BudgetedFuture<P::Future>;
+
+    fn retry(&self, req: &Req, res: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, res).ok()
+    }
+
+    fn retry_or_give_up(&self, req: &Req, res: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        let future = self.policy.retry_or_give_up(req, res)?;
+        self.budget
+            .withdraw_cost(self.policy.retry_cost(req))
+            .map_err(|_| Outcome::BudgetExhausted)?;
+        Ok(BudgetedFuture {
+            future,
+            budget: Some(self.budget.clone()),
+        })
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        self.policy.clone_request(req)
+    }
+
+    fn size_hint(&self, req: &Req) -> Option<usize> {
+        self.policy.size_hint(req)
+    }
+
+    fn is_replayable(&self, req: &Req) -> bool {
+        self.policy.is_replayable(req)
+    }
+
+    fn retry_cost(&self, req: &Req) -> u32 {
+        self.policy.retry_cost(req)
+    }
+
+    fn on_future_error(
+        &self,
+        error: <Self::Future as Future>::Error,
+        result: Result<Res, E>,
+    ) -> Result<Res, E> {
+        self.policy.on_future_error(error, result)
+    }
+}
+
+// ===== impl SimpleRetry =====
+
+impl<C, F> SimpleRetry<C, F> {
+    /// Create new `SimpleRetry` policy
+    pub fn new(max_retries: usize, clone_request: C, is_retryable: F) -> Self {
+        SimpleRetry {
+            remaining: max_retries,
+            clone_request,
+            is_retryable,
+        }
+    }
+}
+
+impl<C, F, Req, Res, E> Policy<Req, Res, E> for SimpleRetry<C, F>
+where
+    C: Fn(&Req) -> Option<Req> + Clone,
+    F: Fn(&E) -> bool + Clone,
+{
+    type Future = FutureResult<Self, ()>;
+
+    fn retry(&self, req: &Req, res: Result<&Res, &E>) -> Option<Self::Future> {
+        self.retry_or_give_up(req, res).ok()
+    }
+
+    fn retry_or_give_up(&self, _: &Req, res: Result<&Res, &E>) -> Result<Self::Future, Outcome> {
+        match res {
+            Ok(_) => Err(Outcome::Succeeded),
+            Err(e) if !(self.is_retryable)(e) => Err(Outcome::NotRetryable),
+            Err(_) if self.remaining == 0 => Err(Outcome::AttemptsExhausted),
+            Err(_) => Ok(future::ok(SimpleRetry {
+                remaining: self.remaining - 1,
+                clone_request: self.clone_request.clone(),
+                is_retryable: self.is_retryable.clone(),
+            })),
+        }
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        (self.clone_request)(req)
+    }
+}
+
+// ===== impl BudgetedFuture =====
+
+impl<F: Future> Future for BudgetedFuture<F> {
+    type Item = Budgeted<F::Item>;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let policy = try_ready!(self.future.poll());
+        let budget = self.budget.take().expect("polled after complete");
+        Ok(Async::Ready(Budgeted { policy, budget }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tower_timeout;
+
+    use self::tower_timeout::test_util::with_mock_timer;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use tower_retry::test_util::CountingPolicy;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Fails every request.
+    #[derive(Clone)]
+    struct Fail;
+
+    impl Service<()> for Fail {
+        type Response = ();
+        type Error = ();
+        type Future = FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::err(())
+        }
+    }
+
+    /// A request that cannot be cloned implicitly.
+    struct Req(&'static str);
+
+    /// Fails each request its first two times, with a retryable error.
+    #[derive(Clone)]
+    struct Flaky(Rc<Cell<usize>>);
+
+    impl Service<Req> for Flaky {
+        type Response = &'static str;
+        type Error = &'static str;
+        type Future = FutureResult<&'static str, &'static str>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: Req) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            match req.0 {
+                "fatal" => future::err("fatal"),
+                _ if self.0.get() <= 2 => future::err("retry me"),
+                name => future::ok(name),
+            }
+        }
+    }
+
+    /// Fails each request its first time, asking for a retry after a second.
+    #[derive(Clone)]
+    struct RetryLater(Rc<Cell<usize>>);
+
+    impl Service<Req> for RetryLater {
+        type Response = &'static str;
+        type Error = Duration;
+        type Future = FutureResult<&'static str, Duration>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: Req) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            match self.0.get() {
+                1 => future::err(Duration::from_secs(1)),
+                _ => future::ok(req.0),
+            }
+        }
+    }
+
+    #[test]
+    fn retry_after_waits_for_extracted_delay() {
+        with_mock_timer(|time, timer| {
+            let calls = Rc::new(Cell::new(0));
+            let mut svc = RetryLater(calls.clone()).with_retry_after_extraction(
+                1,
+                |req: &Req| Some(Req(req.0)),
+                |e: &Duration| Some(*e),
+            );
+
+            let mut rsp = svc.call(Req("hello"));
+            let mut poll = || future::lazy(|| Ok::<_, ()>(rsp.poll())).wait().unwrap();
+            assert!(poll().unwrap().is_not_ready());
+            assert_eq!(calls.get(), 1);
+
+            *time.as_mut() += Duration::from_millis(999);
+            timer.turn(None).unwrap();
+            assert!(poll().unwrap().is_not_ready());
+            assert_eq!(calls.get(), 1);
+
+            *time.as_mut() += Duration::from_millis(1);
+            timer.turn(None).unwrap();
+            assert_eq!(poll(), Ok(Async::Ready("hello")));
+            assert_eq!(calls.get(), 2);
+        });
+    }
+
+    #[test]
+    fn simple_retry_clones_with_closure() {
+        let calls = Rc::new(Cell::new(0));
+        let mut svc = Flaky(calls.clone()).simple_retry(
+            2,
+            |req: &Req| Some(Req(req.0)),
+            |e: &&'static str| *e == "retry me",
+        );
+
+        assert_eq!(svc.call(Req("hello")).wait(), Ok("hello"));
+        assert_eq!(calls.get(), 3);
+
+        // Errors that are not retryable are returned immediately.
+        assert_eq!(svc.call(Req("fatal")).wait(), Err("fatal"));
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[test]
+    fn simple_retry_gives_up_after_max() {
+        let calls = Rc::new(Cell::new(0));
+        let mut svc = Flaky(calls.clone()).simple_retry(1, |req: &Req| Some(Req(req.0)), |_: &_| true);
+
+        assert_eq!(svc.call(Req("hello")).wait(), Err("retry me"));
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn budget_caps_retries_across_calls() {
+        // Each request earns exactly one retry, with nothing in reserve.
+        let budget = Arc::new(Budget::new(Duration::from_secs(1), 0, 1.0));
+        let policy = CountingPolicy::new(5);
+        let mut svc = Fail.retry_with_budget(policy.clone(), budget);
+
+        for _ in 0..3 {
+            assert!(svc.poll_ready().unwrap().is_ready());
+            assert_eq!(svc.call(()).wait(), Err(()));
+        }
+
+        // Without the budget, each call would have made six attempts.
+        assert_eq!(policy.attempts(), 6);
+    }
+
+    #[test]
+    fn budget_exhaustion_is_reported() {
+        use std::sync::Mutex;
+
+        // Each request earns exactly one retry, with nothing in reserve.
+        let budget = Arc::new(Budget::new(Duration::from_secs(1), 0, 1.0));
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let mut svc = {
+            let outcomes = outcomes.clone();
+            Fail.retry_with_budget(CountingPolicy::new(5), budget)
+                .on_outcome(move |outcome| outcomes.lock().unwrap().push(outcome))
+        };
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(()).wait(), Err(()));
+        assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::BudgetExhausted]);
+    }
+
+    #[test]
+    fn simple_retry_outcomes() {
+        use std::sync::Mutex;
+
+        let calls = Rc::new(Cell::new(0));
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let mut svc = {
+            let outcomes = outcomes.clone();
+            let retryable = |e: &&'static str| *e == "retry me";
+            Flaky(calls.clone())
+                .simple_retry(1, |req: &Req| Some(Req(req.0)), retryable)
+                .on_outcome(move |outcome| outcomes.lock().unwrap().push(outcome))
+        };
+
+        assert_eq!(svc.call(Req("hello")).wait(), Err("retry me"));
+        assert_eq!(svc.call(Req("fatal")).wait(), Err("fatal"));
+        assert_eq!(svc.call(Req("hello")).wait(), Ok("hello"));
+        assert_eq!(
+            *outcomes.lock().unwrap(),
+            vec![Outcome::AttemptsExhausted, Outcome::NotRetryable, Outcome::Succeeded]
+        );
+    }
+
+    /// Retries as `CountingPolicy` does, at a fixed cost per retry.
+    #[derive(Clone)]
+    struct Costed(CountingPolicy, u32);
+
+    impl Policy<(), (), ()> for Costed {
+        type Future = Box<Future<Item = Self, Error = ()>>;
+
+        fn retry(&self, req: &(), res: Result<&(), &()>) -> Option<Self::Future> {
+            let cost = self.1;
+            self.0.retry(req, res).map(|f| {
+                let f = f.map(move |policy| Costed(policy, cost));
+                Box::new(f) as Self::Future
+            })
+        }
+
+        fn clone_request(&self, req: &()) -> Option<()> {
+            self.0.clone_request(req)
+        }
+
+        fn retry_cost(&self, _: &()) -> u32 {
+            self.1
+        }
+    }
+
+    /// Returns the attempts made for one failing call, with a budget of four.
+    fn attempts_with_cost(cost: u32) -> usize {
+        // Three retries in reserve, and one more earned by the call.
+        let budget = Arc::new(Budget::new(Duration::from_secs(1), 3, 1.0));
+        let policy = CountingPolicy::new(10);
+        let mut svc = Fail.retry_with_budget(Costed(policy.clone(), cost), budget);
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(()).wait(), Err(()));
+        policy.attempts()
+    }
+
+    #[test]
+    fn expensive_requests_retry_less() {
+        assert_eq!(attempts_with_cost(1), 5);
+        assert_eq!(attempts_with_cost(3), 2);
+    }
+}
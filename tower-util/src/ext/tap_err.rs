@@ -0,0 +1,137 @@
+use futures::{Future, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `tap_err` combinator, observing each error produced by a
+/// response future without changing it.
+///
+/// Responses pass through untouched, and errors from `poll_ready` are not
+/// observed, so this adds nothing to the success path.
+///
+/// This is created by the `ServiceExt::tap_err` method.
+pub struct TapErr<S, F, R> {
+    service: S,
+    f: F,
+    _p: PhantomData<fn(R)>,
+}
+
+pub struct TapErrFuture<T, F> {
+    f: F,
+    fut: T,
+}
+
+impl<S, F, R> TapErr<S, F, R> {
+    /// Create new `TapErr` combinator
+    pub fn new(service: S, f: F) -> Self
+    where
+        S: Service<R>,
+        F: FnMut(&S::Error) + Clone,
+    {
+        TapErr {
+            service,
+            f,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> Clone for TapErr<S, F, R>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        TapErr {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R> Service<R> for TapErr<S, F, R>
+where
+    S: Service<R>,
+    F: FnMut(&S::Error) + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TapErrFuture<S::Future, F>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        TapErrFuture {
+            f: self.f.clone(),
+            fut: self.service.call(req),
+        }
+    }
+}
+
+impl<T, F> Future for TapErrFuture<T, F>
+where
+    T: Future,
+    F: FnMut(&T::Error),
+{
+    type Item = T::Item;
+    type Error = T::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.fut.poll().map_err(|e| {
+            (self.f)(&e);
+            e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{err, ok, FutureResult};
+    use futures::Async;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Fails requests for odd numbers.
+    struct Srv;
+
+    impl Service<u32> for Srv {
+        type Response = u32;
+        type Error = u32;
+        type Future = FutureResult<u32, u32>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, n: u32) -> Self::Future {
+            if n % 2 == 0 {
+                ok(n)
+            } else {
+                err(n)
+            }
+        }
+    }
+
+    #[test]
+    fn test_fires_only_on_error() {
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let mut srv = {
+            let errors = errors.clone();
+            Srv.tap_err(move |e: &u32| errors.borrow_mut().push(*e))
+        };
+
+        assert_eq!(srv.call(2).poll(), Ok(Async::Ready(2)));
+        assert!(errors.borrow().is_empty());
+
+        assert_eq!(srv.call(3).poll(), Err(3));
+        assert_eq!(srv.call(4).poll(), Ok(Async::Ready(4)));
+        assert_eq!(srv.call(5).poll(), Err(5));
+        assert_eq!(*errors.borrow(), vec![3, 5]);
+    }
+}
@@ -0,0 +1,134 @@
+use futures::future::{self, Either, FutureResult};
+use futures::Poll;
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `map_request_result` combinator, transforming each request
+/// with a fallible function before it is dispatched to the inner service.
+///
+/// A request that fails to transform fails immediately, with the transform's
+/// error converted with `From`, and is never dispatched.
+///
+/// This is created by the `ServiceExt::map_request_result` method.
+pub struct TryMapRequest<S, F, R2> {
+    service: S,
+    f: F,
+    _p: PhantomData<fn(R2)>,
+}
+
+impl<S, F, R2> TryMapRequest<S, F, R2> {
+    /// Create new `TryMapRequest` combinator
+    pub fn new<R, E>(service: S, f: F) -> Self
+    where
+        S: Service<R>,
+        F: FnMut(R2) -> Result<R, E>,
+        S::Error: From<E>,
+    {
+        TryMapRequest {
+            service,
+            f,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R2> Clone for TryMapRequest<S, F, R2>
+where
+    S: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        TryMapRequest {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R, R2, E> Service<R2> for TryMapRequest<S, F, R2>
+where
+    S: Service<R>,
+    F: FnMut(R2) -> Result<R, E>,
+    S::Error: From<E>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, FutureResult<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready()
+    }
+
+    fn call(&mut self, req: R2) -> Self::Future {
+        match (self.f)(req) {
+            Ok(req) => Either::A(self.service.call(req)),
+            Err(e) => Either::B(future::err(e.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{Async, Future};
+    use std::cell::Cell;
+    use std::num::ParseIntError;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Malformed,
+        Negative,
+    }
+
+    impl From<ParseIntError> for Error {
+        fn from(_: ParseIntError) -> Self {
+            Error::Malformed
+        }
+    }
+
+    /// Doubles non-negative numbers, counting the requests it serves.
+    struct Double(Rc<Cell<usize>>);
+
+    impl Service<i64> for Double {
+        type Response = i64;
+        type Error = Error;
+        type Future = FutureResult<i64, Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: i64) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            if req < 0 {
+                future::err(Error::Negative)
+            } else {
+                future::ok(req * 2)
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_request_fails_without_dispatch() {
+        let served = Rc::new(Cell::new(0));
+        let mut svc = Double(served.clone()).map_request_result(|req: &'static str| req.parse::<i64>());
+
+        assert_eq!(svc.call("nope").wait(), Err(Error::Malformed));
+        assert_eq!(served.get(), 0);
+    }
+
+    #[test]
+    fn valid_request_passes_through() {
+        let served = Rc::new(Cell::new(0));
+        let mut svc = Double(served.clone()).map_request_result(|req: &'static str| req.parse::<i64>());
+
+        assert_eq!(svc.call("21").wait(), Ok(42));
+        assert_eq!(svc.call("-1").wait(), Err(Error::Negative));
+        assert_eq!(served.get(), 2);
+    }
+}
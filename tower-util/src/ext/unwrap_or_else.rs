@@ -0,0 +1,188 @@
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+use std::marker::PhantomData;
+
+/// Service for the `unwrap_or_else` combinator, turning errors into fallback
+/// responses.
+///
+/// Errors from response futures are passed to `f`, and the response it returns
+/// is yielded instead. If `poll_ready` fails, the service reports that it is
+/// ready, and the next request is answered with `f`'s response to that error
+/// without being dispatched. The service therefore never fails, and its error
+/// type `E` may be chosen freely.
+///
+/// This is created by the `ServiceExt::unwrap_or_else` method.
+pub struct UnwrapOrElse<S, F, R, E>
+where
+    S: Service<R>,
+{
+    service: S,
+    f: F,
+    /// Answers the next request, after `poll_ready` failed.
+    fallback: Option<S::Response>,
+    _p: PhantomData<fn(R) -> E>,
+}
+
+pub struct UnwrapOrElseFuture<T, F, E>
+where
+    T: Future,
+{
+    state: State<T, F>,
+    _p: PhantomData<fn() -> E>,
+}
+
+enum State<T, F>
+where
+    T: Future,
+{
+    Pending(T, F),
+    Fallback(Option<T::Item>),
+}
+
+impl<S, F, R, E> UnwrapOrElse<S, F, R, E>
+where
+    S: Service<R>,
+{
+    /// Create new `UnwrapOrElse` combinator
+    pub fn new(service: S, f: F) -> Self
+    where
+        F: Fn(S::Error) -> S::Response + Clone,
+    {
+        UnwrapOrElse {
+            service,
+            f,
+            fallback: None,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R, E> Clone for UnwrapOrElse<S, F, R, E>
+where
+    S: Service<R> + Clone,
+    S::Response: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        UnwrapOrElse {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            fallback: self.fallback.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R, E> Service<R> for UnwrapOrElse<S, F, R, E>
+where
+    S: Service<R>,
+    F: Fn(S::Error) -> S::Response + Clone,
+{
+    type Response = S::Response;
+    type Error = E;
+    type Future = UnwrapOrElseFuture<S::Future, F, E>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        if self.fallback.is_some() {
+            return Ok(Async::Ready(()));
+        }
+
+        match self.service.poll_ready() {
+            Ok(ready) => Ok(ready),
+            Err(e) => {
+                self.fallback = Some((self.f)(e));
+                Ok(Async::Ready(()))
+            }
+        }
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let state = match self.fallback.take() {
+            Some(response) => State::Fallback(Some(response)),
+            None => State::Pending(self.service.call(req), self.f.clone()),
+        };
+
+        UnwrapOrElseFuture {
+            state,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T, F, E> Future for UnwrapOrElseFuture<T, F, E>
+where
+    T: Future,
+    F: Fn(T::Error) -> T::Item,
+{
+    type Item = T::Item;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.state {
+            State::Pending(ref mut fut, ref f) => match fut.poll() {
+                Ok(ready) => Ok(ready),
+                Err(e) => Ok(Async::Ready(f(e))),
+            },
+            State::Fallback(ref mut response) => {
+                let response = response.take().expect("polled after complete");
+                Ok(Async::Ready(response))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+
+    use super::*;
+    use ServiceExt;
+
+    /// Fails requests, and optionally readiness, with a fixed error.
+    struct Unavailable {
+        not_ready: bool,
+    }
+
+    impl Service<()> for Unavailable {
+        type Response = &'static str;
+        type Error = &'static str;
+        type Future = FutureResult<&'static str, &'static str>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            if self.not_ready {
+                return Err("not ready");
+            }
+            Ok(().into())
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::err("unavailable")
+        }
+    }
+
+    fn degraded(not_ready: bool) -> UnwrapOrElse<Unavailable, fn(&'static str) -> &'static str, (), ()> {
+        fn fallback(error: &'static str) -> &'static str {
+            match error {
+                "unavailable" => "cached",
+                _ => "offline",
+            }
+        }
+
+        Unavailable { not_ready }.unwrap_or_else(fallback as fn(_) -> _)
+    }
+
+    #[test]
+    fn error_becomes_fallback_response() {
+        let mut svc = degraded(false);
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(()).wait(), Ok("cached"));
+    }
+
+    #[test]
+    fn poll_ready_error_answers_next_request() {
+        let mut svc = degraded(true);
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call(()).wait(), Ok("offline"));
+    }
+}
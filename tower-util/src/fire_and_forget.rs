@@ -0,0 +1,221 @@
+//! Contains `FireAndForget` and related types and functions.
+//!
+//! See `FireAndForget` documentation for more details.
+
+use futures::future::{self, Executor, FutureResult};
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+use std::{error, fmt};
+
+/// Dispatches requests for their side effects only, without waiting for their
+/// responses.
+///
+/// Each response future is spawned onto an executor, which drives it to
+/// completion in the background, and `call` resolves to `()` immediately.
+/// Responses are discarded, and errors are logged.
+pub struct FireAndForget<S, E> {
+    service: S,
+    executor: E,
+}
+
+/// Drives a response future to completion, discarding its result.
+pub struct Background<F> {
+    fut: F,
+}
+
+/// Error produced by `FireAndForget`.
+#[derive(Debug)]
+pub enum Error<T> {
+    /// The inner service failed to become ready.
+    Inner(T),
+    /// The executor refused to spawn a response future.
+    Spawn,
+}
+
+// ===== impl FireAndForget =====
+
+impl<S, E> FireAndForget<S, E> {
+    /// Creates a `FireAndForget` that spawns response futures onto `executor`.
+    pub fn new<Request>(service: S, executor: E) -> Self
+    where
+        S: Service<Request>,
+        E: Executor<Background<S::Future>>,
+    {
+        FireAndForget { service, executor }
+    }
+}
+
+impl<S, E, Request> Service<Request> for FireAndForget<S, E>
+where
+    S: Service<Request>,
+    S::Error: fmt::Debug,
+    E: Executor<Background<S::Future>>,
+{
+    type Response = ();
+    type Error = Error<S::Error>;
+    type Future = FutureResult<(), Self::Error>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.service.poll_ready().map_err(Error::Inner)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let fut = self.service.call(request);
+        match self.executor.execute(Background { fut }) {
+            Ok(()) => future::ok(()),
+            Err(_) => future::err(Error::Spawn),
+        }
+    }
+}
+
+impl<S: Clone, E: Clone> Clone for FireAndForget<S, E> {
+    fn clone(&self) -> Self {
+        FireAndForget {
+            service: self.service.clone(),
+            executor: self.executor.clone(),
+        }
+    }
+}
+
+impl<S: fmt::Debug, E> fmt::Debug for FireAndForget<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FireAndForget")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+// ===== impl Background =====
+
+impl<F> Future for Background<F>
+where
+    F: Future,
+    F::Error: fmt::Debug,
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        match self.fut.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(_)) => Ok(Async::Ready(())),
+            Err(e) => {
+                warn!("fire-and-forget request failed: {:?}", e);
+                Ok(Async::Ready(()))
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for Background<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Background").finish()
+    }
+}
+
+// ===== impl Error =====
+
+impl<T> fmt::Display for Error<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Inner(ref why) => fmt::Display::fmt(why, f),
+            Error::Spawn => f.pad("failed to spawn response future"),
+        }
+    }
+}
+
+impl<T> error::Error for Error<T>
+where
+    T: error::Error,
+{
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Inner(ref why) => Some(why),
+            Error::Spawn => None,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match *self {
+            Error::Inner(_) => "inner service error",
+            Error::Spawn => "failed to spawn response future",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::ExecuteError;
+    use futures::sync::oneshot;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Responds once the test sends a response.
+    struct Deferred(Rc<RefCell<Vec<oneshot::Sender<&'static str>>>>);
+
+    impl Service<()> for Deferred {
+        type Response = &'static str;
+        type Error = oneshot::Canceled;
+        type Future = oneshot::Receiver<&'static str>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            let (tx, rx) = oneshot::channel();
+            self.0.borrow_mut().push(tx);
+            rx
+        }
+    }
+
+    /// Holds spawned futures so that the test may drive them.
+    #[derive(Clone, Default)]
+    struct Manual(Rc<RefCell<Vec<Background<oneshot::Receiver<&'static str>>>>>);
+
+    impl Executor<Background<oneshot::Receiver<&'static str>>> for Manual {
+        fn execute(
+            &self,
+            fut: Background<oneshot::Receiver<&'static str>>,
+        ) -> Result<(), ExecuteError<Background<oneshot::Receiver<&'static str>>>> {
+            self.0.borrow_mut().push(fut);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn caller_does_not_wait_for_response() {
+        let senders = Rc::new(RefCell::new(Vec::new()));
+        let executor = Manual::default();
+        let mut svc = Deferred(senders.clone()).fire_and_forget(executor.clone());
+
+        assert!(Service::<()>::poll_ready(&mut svc).unwrap().is_ready());
+        assert!(svc.call(()).wait().is_ok());
+        assert!(svc.call(()).wait().is_ok());
+
+        let mut spawned = executor.0.borrow_mut().drain(..).collect::<Vec<_>>();
+        assert_eq!(spawned.len(), 2);
+        future::lazy(|| {
+            for fut in spawned.iter_mut() {
+                assert!(fut.poll().unwrap().is_not_ready());
+            }
+            Ok::<_, ()>(())
+        }).wait().unwrap();
+
+        // The inner futures still run to completion, whether they succeed or
+        // fail.
+        let mut senders = senders.borrow_mut();
+        senders.remove(0).send("done").unwrap();
+        senders.clear();
+        for fut in spawned {
+            assert_eq!(fut.wait(), Ok(()));
+        }
+    }
+}
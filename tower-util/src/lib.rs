@@ -2,19 +2,64 @@
 
 #[macro_use]
 extern crate futures;
+#[macro_use]
+extern crate log;
+#[cfg(test)]
+extern crate tokio;
+#[cfg(feature = "rate-limit")]
+extern crate tokio_timer;
+#[cfg(feature = "load")]
+extern crate tower_balance;
+#[cfg(feature = "buffer")]
+extern crate tower_buffer;
 extern crate tower_direct_service;
+#[cfg(feature = "discover")]
+extern crate tower_discover;
+#[cfg(feature = "load")]
+extern crate tower_in_flight_limit;
+#[cfg(feature = "rate-limit")]
+extern crate tower_rate_limit;
+#[cfg(feature = "retry")]
+extern crate tower_retry;
 extern crate tower_service;
+#[cfg(feature = "timeout")]
+extern crate tower_timeout;
+
+#[macro_use]
+mod chain;
 
 pub mod boxed;
+pub mod bridge;
+pub mod builder;
+#[cfg(feature = "load")]
+mod concurrency_limit_load;
+mod concurrency_observe;
 pub mod either;
 pub mod ext;
+pub mod fire_and_forget;
 mod make_service;
 pub mod option;
+pub mod per_key_concurrency;
+mod poll_fn;
+pub mod queue_ahead;
+mod rc_service;
+mod ready_cache;
 mod service_fn;
 
-pub use boxed::BoxService;
-pub use either::EitherService;
+pub use boxed::{BoxService, BoxedFuture, CloneBoxService};
+pub use bridge::Bridge;
+pub use builder::ServiceBuilder;
+#[cfg(feature = "load")]
+pub use concurrency_limit_load::ConcurrencyLimitLoad;
+pub use concurrency_observe::{ConcurrencyObserve, ResponseFuture as ConcurrencyObserveFuture};
+pub use either::{EitherService, TryEither};
 pub use ext::ServiceExt;
+pub use fire_and_forget::FireAndForget;
 pub use make_service::MakeService;
-pub use option::OptionService;
+pub use option::{optional_none, OptionService};
+pub use per_key_concurrency::PerKeyConcurrency;
+pub use poll_fn::{poll_fn_service, PollContext, PollFnService};
+pub use queue_ahead::QueueAhead;
+pub use rc_service::RcService;
+pub use ready_cache::ReadyCache;
 pub use service_fn::ServiceFn;
@@ -3,8 +3,15 @@
 //! See `OptionService` documentation for more details.
 //!
 use futures::{Future, Poll};
+#[cfg(feature = "discover")]
+use futures::Async;
+#[cfg(feature = "discover")]
+use tower_discover::{Change, Discover};
 use tower_service::Service;
 
+#[cfg(feature = "discover")]
+use std::fmt;
+
 /// Optionally forwards requests to an inner service.
 ///
 /// If the inner service is `None`, `Error::None` is returned as the response.
@@ -12,6 +19,20 @@ pub struct OptionService<T> {
     inner: Option<T>,
 }
 
+/// Builds discovered services fallibly, inserting `OptionService::none` for
+/// services that fail to build.
+///
+/// Each inserted service is passed to `F` along with its key. If `F` fails,
+/// the failure is logged and the key is still inserted, with a placeholder
+/// that fails every request with `Error::None`. This keeps the key's slot, e.g.
+/// so that a later replacement or removal applies as usual, without aborting
+/// discovery.
+#[cfg(feature = "discover")]
+pub struct OptionDiscover<D, F> {
+    discover: D,
+    make_service: F,
+}
+
 /// Response future returned by `OptionService`.
 pub struct ResponseFuture<T> {
     inner: Option<T>,
@@ -24,6 +45,15 @@ pub enum Error<T> {
     None,
 }
 
+/// Returns an `OptionService` that responds to all requests with
+/// `Error::None`.
+///
+/// This is the absent counterpart to `ServiceExt::optional`, e.g. for a layer
+/// that is not configured.
+pub fn optional_none<T>() -> OptionService<T> {
+    OptionService::none()
+}
+
 // ===== impl OptionService =====
 
 impl<T> OptionService<T> {
@@ -75,3 +105,144 @@ where T: Future,
         }
     }
 }
+
+// ===== impl OptionDiscover =====
+
+#[cfg(feature = "discover")]
+impl<D, F, S, E> OptionDiscover<D, F>
+where
+    D: Discover,
+    F: FnMut(&D::Key, D::Service) -> Result<S, E>,
+{
+    pub fn new(discover: D, make_service: F) -> Self {
+        OptionDiscover {
+            discover,
+            make_service,
+        }
+    }
+}
+
+#[cfg(feature = "discover")]
+impl<D, F, S, E> Discover for OptionDiscover<D, F>
+where
+    D: Discover,
+    D::Key: fmt::Debug,
+    F: FnMut(&D::Key, D::Service) -> Result<S, E>,
+    E: fmt::Debug,
+{
+    type Key = D::Key;
+    type Service = OptionService<S>;
+    type Error = D::Error;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::Error> {
+        let change = match try_ready!(self.discover.poll()) {
+            Change::Insert(key, svc) => {
+                let svc = match (self.make_service)(&key, svc) {
+                    Ok(svc) => OptionService::some(svc),
+                    Err(e) => {
+                        warn!("failed to build service for {:?}: {:?}", key, e);
+                        OptionService::none()
+                    }
+                };
+                Change::Insert(key, svc)
+            }
+            Change::Remove(key) => Change::Remove(key),
+        };
+
+        Ok(Async::Ready(change))
+    }
+
+    fn is_done(&self) -> bool {
+        self.discover.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::Async;
+    #[cfg(feature = "discover")]
+    use tower_discover::test_util::Fixed;
+
+    use super::*;
+    use ServiceExt;
+
+    struct Echo;
+
+    impl Service<&'static str> for Echo {
+        type Response = &'static str;
+        type Error = ();
+        type Future = FutureResult<&'static str, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            future::ok(req)
+        }
+    }
+
+    #[test]
+    fn optional_dispatches_to_inner() {
+        let mut svc = Echo.optional();
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.call("hello").wait().unwrap(), "hello");
+    }
+
+    #[test]
+    fn optional_none_fails_requests() {
+        let mut svc = optional_none::<Echo>();
+        assert!(svc.poll_ready().unwrap().is_ready());
+        match svc.call("hello").wait() {
+            Err(Error::None) => {}
+            _ => panic!("expected a none service"),
+        }
+    }
+
+    #[cfg(feature = "discover")]
+    fn connect(_: &usize, addr: &'static str) -> Result<Echo, &'static str> {
+        match addr {
+            "bad" => Err("invalid address"),
+            _ => Ok(Echo),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "discover")]
+    fn failed_construction_inserts_none() {
+        let addrs = vec![Change::Insert(0, "good"), Change::Insert(1, "bad")];
+        let mut discover = OptionDiscover::new(Fixed::new(addrs), connect);
+
+        let mut services = Vec::new();
+        while let Async::Ready(change) = discover.poll().unwrap() {
+            match change {
+                Change::Insert(key, svc) => services.push((key, svc)),
+                Change::Remove(_) => panic!("unexpected removal"),
+            }
+        }
+        assert_eq!(services.iter().map(|&(k, _)| k).collect::<Vec<_>>(), vec![0, 1]);
+
+        let (_, ref mut good) = services[0];
+        assert!(good.poll_ready().unwrap().is_ready());
+        assert_eq!(good.call("hello").wait().unwrap(), "hello");
+
+        let (_, ref mut bad) = services[1];
+        assert!(bad.poll_ready().unwrap().is_ready());
+        match bad.call("hello").wait() {
+            Err(Error::None) => {}
+            _ => panic!("expected a none service"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "discover")]
+    fn done_once_inner_is_done() {
+        let mut discover = OptionDiscover::new(Fixed::new(vec![Change::Insert(0, "good")]), connect);
+        discover.discover.finish();
+        assert!(!discover.is_done());
+
+        assert!(discover.poll().unwrap().is_ready());
+        assert!(discover.is_done());
+    }
+}
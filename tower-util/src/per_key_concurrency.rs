@@ -0,0 +1,303 @@
+//! Limits the number of in-flight requests for each key.
+//!
+//! See `PerKeyConcurrency` documentation for more details.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::{error, fmt};
+
+use futures::{Future, Poll};
+use tower_service::Service;
+
+/// Limits the number of in-flight requests for each key derived from the
+/// request, e.g. each tenant.
+///
+/// A request whose key is already at its limit fails with
+/// `Error::AtCapacity`, while requests for other keys proceed. Since the key
+/// is only known once a request is dispatched, a key at its limit does not
+/// apply backpressure to `poll_ready`. Clones share the in-flight counts.
+pub struct PerKeyConcurrency<S, F, K>
+where
+    K: Hash + Eq,
+{
+    inner: S,
+    key: F,
+    max: usize,
+    in_flight: Arc<Mutex<HashMap<K, usize>>>,
+}
+
+/// Errors produced by `PerKeyConcurrency`.
+#[derive(Debug)]
+pub enum Error<T> {
+    /// The inner service produced an error.
+    Inner(T),
+
+    /// The request's key already had the maximum number of requests in flight.
+    AtCapacity,
+}
+
+/// Response future returned by `PerKeyConcurrency`.
+pub struct ResponseFuture<T, K>
+where
+    K: Hash + Eq,
+{
+    /// `None` if the request was shed.
+    inner: Option<T>,
+    handle: Option<Handle<K>>,
+}
+
+/// Decrements the in-flight count of a key when dropped.
+struct Handle<K>
+where
+    K: Hash + Eq,
+{
+    key: Option<K>,
+    in_flight: Arc<Mutex<HashMap<K, usize>>>,
+}
+
+// ===== impl PerKeyConcurrency =====
+
+impl<S, F, K> PerKeyConcurrency<S, F, K>
+where
+    K: Hash + Eq,
+{
+    /// Wraps `inner`, allowing at most `max` requests in flight for each key
+    /// returned by `key`.
+    pub fn new<R>(inner: S, key: F, max: usize) -> Self
+    where
+        S: Service<R>,
+        F: Fn(&R) -> K,
+    {
+        PerKeyConcurrency {
+            inner,
+            key,
+            max,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the number of requests currently in flight for `key`.
+    pub fn in_flight(&self, key: &K) -> usize {
+        let in_flight = self.in_flight.lock().expect("in_flight lock");
+        in_flight.get(key).cloned().unwrap_or(0)
+    }
+}
+
+impl<S, F, K, R> Service<R> for PerKeyConcurrency<S, F, K>
+where
+    S: Service<R>,
+    F: Fn(&R) -> K,
+    K: Hash + Eq + Clone,
+{
+    type Response = S::Response;
+    type Error = Error<S::Error>;
+    type Future = ResponseFuture<S::Future, K>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready().map_err(Error::Inner)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let key = (self.key)(&request);
+        {
+            let mut in_flight = self.in_flight.lock().expect("in_flight lock");
+            let n = in_flight.get(&key).cloned().unwrap_or(0);
+            if n >= self.max {
+                debug!("key at capacity; shedding request");
+                return ResponseFuture {
+                    inner: None,
+                    handle: None,
+                };
+            }
+            in_flight.insert(key.clone(), n + 1);
+        }
+
+        ResponseFuture {
+            inner: Some(self.inner.call(request)),
+            handle: Some(Handle {
+                key: Some(key),
+                in_flight: self.in_flight.clone(),
+            }),
+        }
+    }
+}
+
+impl<S, F, K> Clone for PerKeyConcurrency<S, F, K>
+where
+    S: Clone,
+    F: Clone,
+    K: Hash + Eq,
+{
+    /// Clones share the in-flight counts.
+    fn clone(&self) -> Self {
+        PerKeyConcurrency {
+            inner: self.inner.clone(),
+            key: self.key.clone(),
+            max: self.max,
+            in_flight: self.in_flight.clone(),
+        }
+    }
+}
+
+impl<S, F, K> fmt::Debug for PerKeyConcurrency<S, F, K>
+where
+    S: fmt::Debug,
+    K: Hash + Eq + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PerKeyConcurrency")
+            .field("inner", &self.inner)
+            .field("max", &self.max)
+            .field("in_flight", &self.in_flight)
+            .finish()
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<T, K> Future for ResponseFuture<T, K>
+where
+    T: Future,
+    K: Hash + Eq,
+{
+    type Item = T::Item;
+    type Error = Error<T::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let ret = match self.inner {
+            Some(ref mut inner) => inner.poll().map_err(Error::Inner),
+            None => return Err(Error::AtCapacity),
+        };
+        if ret.as_ref().map(|a| a.is_ready()).unwrap_or(true) {
+            // Release the key's capacity eagerly rather than when the future is
+            // dropped.
+            self.handle.take();
+        }
+        ret
+    }
+}
+
+// ===== impl Handle =====
+
+impl<K> Drop for Handle<K>
+where
+    K: Hash + Eq,
+{
+    fn drop(&mut self) {
+        let key = self.key.take().expect("key");
+        let mut in_flight = self.in_flight.lock().expect("in_flight lock");
+        let idle = match in_flight.get_mut(&key) {
+            Some(n) => {
+                *n -= 1;
+                *n == 0
+            }
+            None => false,
+        };
+        // Forget idle keys, so that the map does not grow without bound.
+        if idle {
+            in_flight.remove(&key);
+        }
+    }
+}
+
+// ===== impl Error =====
+
+impl<T> fmt::Display for Error<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Inner(ref why) => fmt::Display::fmt(why, f),
+            Error::AtCapacity => f.pad("too many requests in flight for key"),
+        }
+    }
+}
+
+impl<T> error::Error for Error<T>
+where
+    T: error::Error,
+{
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Inner(ref why) => Some(why),
+            Error::AtCapacity => None,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match *self {
+            Error::Inner(_) => "inner service error",
+            Error::AtCapacity => "too many requests in flight for key",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, Empty};
+    use futures::Async;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Never responds, so that requests stay in flight until dropped.
+    struct Pending;
+
+    impl Service<&'static str> for Pending {
+        type Response = ();
+        type Error = ();
+        type Future = Empty<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: &'static str) -> Self::Future {
+            future::empty()
+        }
+    }
+
+    fn tenant(request: &&'static str) -> char {
+        request.chars().next().expect("tenant")
+    }
+
+    #[test]
+    fn key_at_capacity_does_not_block_others() {
+        let mut svc = Pending.throttle_concurrency_per_key(tenant, 1);
+
+        let mut a1 = svc.call("a1");
+        assert!(a1.poll().unwrap().is_not_ready());
+        match svc.call("a2").poll() {
+            Err(Error::AtCapacity) => {}
+            _ => panic!("expected key a to be at capacity"),
+        }
+
+        let mut b1 = svc.call("b1");
+        assert!(b1.poll().unwrap().is_not_ready());
+        assert_eq!(svc.in_flight(&'a'), 1);
+        assert_eq!(svc.in_flight(&'b'), 1);
+
+        // Completing a request frees its key's capacity.
+        drop(a1);
+        assert_eq!(svc.in_flight(&'a'), 0);
+        assert!(svc.call("a3").poll().unwrap().is_not_ready());
+    }
+
+    #[test]
+    fn key_may_be_extracted_by_closure() {
+        let prefix = 2;
+        let key = move |request: &&'static str| request[..prefix].to_owned();
+        let mut svc = Pending.throttle_concurrency_per_key(key, 1);
+
+        let mut ab1 = svc.call("ab1");
+        assert!(ab1.poll().unwrap().is_not_ready());
+        assert!(svc.call("ac1").poll().unwrap().is_not_ready());
+        match svc.call("ab2").poll() {
+            Err(Error::AtCapacity) => {}
+            _ => panic!("expected key ab to be at capacity"),
+        }
+        assert_eq!(svc.in_flight(&"ab".to_owned()), 1);
+    }
+}
@@ -0,0 +1,144 @@
+use futures::future::{self, Either, FutureResult};
+use futures::{Async, IntoFuture, Poll};
+use tower_service::Service;
+
+use std::fmt;
+
+/// Returns a new `PollFnService` backed by the closure `f`.
+///
+/// See `PollFnService` for details.
+pub fn poll_fn_service<T, R, F>(f: T) -> PollFnService<T>
+where
+    T: FnMut(PollContext, Option<R>) -> Poll<Option<F>, F::Error>,
+    F: IntoFuture,
+{
+    PollFnService::new(f)
+}
+
+/// A `Service` implemented by a single closure that handles both readiness
+/// and requests.
+///
+/// The closure is invoked with `None` from `poll_ready`, where it returns
+/// `Ready(None)` when the service is ready, `NotReady` to apply backpressure,
+/// or an error. It is invoked with `Some(request)` from `call`, where it must
+/// return `Ready(Some(response))` or an error.
+///
+/// Because readiness and responses are expressed by the same closure, this is
+/// convenient for building test doubles whose behavior depends on the order
+/// of calls, such as in property tests.
+pub struct PollFnService<T> {
+    f: T,
+    cx: PollContext,
+}
+
+/// Describes the history of a `PollFnService` to its closure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PollContext {
+    polls: usize,
+    calls: usize,
+}
+
+// ===== impl PollFnService =====
+
+impl<T> PollFnService<T> {
+    /// Returns a new `PollFnService` with the given closure.
+    pub fn new(f: T) -> Self {
+        PollFnService {
+            f,
+            cx: PollContext::default(),
+        }
+    }
+}
+
+impl<T, R, F> Service<R> for PollFnService<T>
+where
+    T: FnMut(PollContext, Option<R>) -> Poll<Option<F>, F::Error>,
+    F: IntoFuture,
+{
+    type Response = F::Item;
+    type Error = F::Error;
+    type Future = Either<F::Future, FutureResult<F::Item, F::Error>>;
+
+    fn poll_ready(&mut self) -> Poll<(), F::Error> {
+        let cx = self.cx;
+        self.cx.polls += 1;
+
+        match (self.f)(cx, None)? {
+            Async::Ready(None) => Ok(Async::Ready(())),
+            Async::Ready(Some(_)) => panic!("PollFnService responded without a request"),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let cx = self.cx;
+        self.cx.polls = 0;
+        self.cx.calls += 1;
+
+        match (self.f)(cx, Some(req)) {
+            Ok(Async::Ready(Some(rsp))) => Either::A(rsp.into_future()),
+            Ok(_) => panic!("PollFnService did not respond to a request"),
+            Err(e) => Either::B(future::err(e)),
+        }
+    }
+}
+
+impl<T> fmt::Debug for PollFnService<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PollFnService")
+            .field("cx", &self.cx)
+            .finish()
+    }
+}
+
+// ===== impl PollContext =====
+
+impl PollContext {
+    /// Returns the number of times `poll_ready` has been called since the last
+    /// request.
+    pub fn polls(&self) -> usize {
+        self.polls
+    }
+
+    /// Returns the number of requests that have been made.
+    pub fn calls(&self) -> usize {
+        self.calls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::Future;
+
+    use super::*;
+
+    #[test]
+    fn backpressure_then_respond() {
+        let mut srv = poll_fn_service(|cx: PollContext, req: Option<&'static str>| {
+            match req {
+                // Refuse the first two polls before each request.
+                None if cx.polls() < 2 => Ok(Async::NotReady),
+                None => Ok(Async::Ready(None)),
+                Some(req) => Ok(Async::Ready(Some(Ok::<_, ()>((req, cx.calls()))))),
+            }
+        });
+
+        for i in 0..2 {
+            assert!(srv.poll_ready().unwrap().is_not_ready());
+            assert!(srv.poll_ready().unwrap().is_not_ready());
+            assert!(srv.poll_ready().unwrap().is_ready());
+            assert_eq!(srv.call("hello").wait(), Ok(("hello", i)));
+        }
+    }
+
+    #[test]
+    fn error_from_call() {
+        let mut srv = poll_fn_service(|_, req: Option<&'static str>| match req {
+            None => Ok(Async::Ready(None::<Result<(), _>>)),
+            Some(_) => Err("rejected"),
+        });
+
+        assert!(srv.poll_ready().unwrap().is_ready());
+        assert_eq!(srv.call("hello").wait(), Err("rejected"));
+    }
+}
@@ -0,0 +1,322 @@
+//! Queues requests in-process while the inner service is not ready.
+//!
+//! See `QueueAhead` documentation for more details.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::{error, fmt};
+
+use futures::task::{self, Task};
+use futures::{Async, Future, Poll};
+use tower_service::Service;
+
+/// Holds up to `capacity` requests while the inner service is not ready, and
+/// dispatches them in order as it becomes ready.
+///
+/// This is a lightweight alternative to `tower-buffer` for single-threaded
+/// use: no worker task is spawned. Instead, queued requests are dispatched
+/// from `poll_ready`, so the owner of a `QueueAhead` must keep polling it, or
+/// its queued requests will never be dispatched.
+///
+/// # `!Send`
+///
+/// Queued requests share their response slots through `Rc`, so neither a
+/// `QueueAhead` nor its response futures are `Send`.
+pub struct QueueAhead<S, R>
+where
+    S: Service<R>,
+{
+    inner: S,
+    queue: VecDeque<(R, Rc<RefCell<Slot<S::Future>>>)>,
+    capacity: usize,
+    /// Whether the inner service has been polled ready and not yet called.
+    inner_ready: bool,
+}
+
+/// Errors produced by `QueueAhead`.
+#[derive(Debug)]
+pub enum Error<T> {
+    /// The inner service produced an error.
+    Inner(T),
+
+    /// The request was queued, but dropped before it was dispatched, e.g.
+    /// because the inner service failed.
+    Closed,
+}
+
+/// Response future returned by `QueueAhead`.
+pub struct ResponseFuture<F> {
+    state: State<F>,
+}
+
+enum State<F> {
+    Queued(Rc<RefCell<Slot<F>>>),
+    Dispatched(F),
+}
+
+/// Receives a queued request's response future once it is dispatched.
+struct Slot<F> {
+    future: Option<F>,
+    /// Notified once the request is dispatched or dropped.
+    task: Option<Task>,
+    closed: bool,
+}
+
+// ===== impl QueueAhead =====
+
+impl<S, R> QueueAhead<S, R>
+where
+    S: Service<R>,
+{
+    /// Wraps `inner`, queueing up to `capacity` requests while it is not ready.
+    pub fn new(inner: S, capacity: usize) -> Self {
+        QueueAhead {
+            inner,
+            queue: VecDeque::new(),
+            capacity,
+            inner_ready: false,
+        }
+    }
+
+    /// Returns the number of requests waiting to be dispatched.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Dispatches queued requests for as long as the inner service is ready.
+    fn dispatch_queued(&mut self) -> Result<(), S::Error> {
+        while !self.queue.is_empty() {
+            if !self.poll_inner()? {
+                return Ok(());
+            }
+
+            let (request, slot) = self.queue.pop_front().expect("queue is not empty");
+            self.inner_ready = false;
+            let future = self.inner.call(request);
+            let mut slot = slot.borrow_mut();
+            slot.future = Some(future);
+            if let Some(task) = slot.task.take() {
+                task.notify();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn poll_inner(&mut self) -> Result<bool, S::Error> {
+        if !self.inner_ready {
+            self.inner_ready = self.inner.poll_ready()?.is_ready();
+        }
+        Ok(self.inner_ready)
+    }
+
+    /// Fails every queued request with `Error::Closed`.
+    fn close(&mut self) {
+        for (_, slot) in self.queue.drain(..) {
+            let mut slot = slot.borrow_mut();
+            slot.closed = true;
+            if let Some(task) = slot.task.take() {
+                task.notify();
+            }
+        }
+    }
+}
+
+impl<S, R> Service<R> for QueueAhead<S, R>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = Error<S::Error>;
+    type Future = ResponseFuture<S::Future>;
+
+    /// Dispatches queued requests, and returns `Ready` if the next request may
+    /// be dispatched or queued.
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        let polled = self.dispatch_queued().and_then(|()| {
+            if self.queue.is_empty() {
+                self.poll_inner()?;
+            }
+            Ok(())
+        });
+        if let Err(e) = polled {
+            self.close();
+            return Err(Error::Inner(e));
+        }
+
+        if self.inner_ready || self.queue.len() < self.capacity {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.inner_ready && self.queue.is_empty() {
+            self.inner_ready = false;
+            return ResponseFuture {
+                state: State::Dispatched(self.inner.call(request)),
+            };
+        }
+
+        assert!(self.queue.len() < self.capacity, "queue is full; poll_ready must be called first");
+        trace!("inner service not ready; queueing request");
+        let slot = Rc::new(RefCell::new(Slot {
+            future: None,
+            task: None,
+            closed: false,
+        }));
+        self.queue.push_back((request, slot.clone()));
+        ResponseFuture {
+            state: State::Queued(slot),
+        }
+    }
+}
+
+impl<S, R> Drop for QueueAhead<S, R>
+where
+    S: Service<R>,
+{
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<S, R> fmt::Debug for QueueAhead<S, R>
+where
+    S: Service<R> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("QueueAhead")
+            .field("inner", &self.inner)
+            .field("queued", &self.queue.len())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<F> Future for ResponseFuture<F>
+where
+    F: Future,
+{
+    type Item = F::Item;
+    type Error = Error<F::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let future = match self.state {
+                State::Dispatched(ref mut future) => return future.poll().map_err(Error::Inner),
+                State::Queued(ref slot) => {
+                    let mut slot = slot.borrow_mut();
+                    match slot.future.take() {
+                        Some(future) => future,
+                        None if slot.closed => return Err(Error::Closed),
+                        None => {
+                            slot.task = Some(task::current());
+                            return Ok(Async::NotReady);
+                        }
+                    }
+                }
+            };
+            self.state = State::Dispatched(future);
+        }
+    }
+}
+
+// ===== impl Error =====
+
+impl<T> fmt::Display for Error<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Inner(ref why) => fmt::Display::fmt(why, f),
+            Error::Closed => f.pad("queued request was dropped"),
+        }
+    }
+}
+
+impl<T> error::Error for Error<T>
+where
+    T: error::Error,
+{
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Inner(ref why) => Some(why),
+            Error::Closed => None,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match *self {
+            Error::Inner(_) => "inner service error",
+            Error::Closed => "queued request was dropped",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use std::cell::Cell;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Echoes requests, but only while the shared flag is set.
+    struct Gated(Rc<Cell<bool>>);
+
+    impl Service<&'static str> for Gated {
+        type Response = &'static str;
+        type Error = ();
+        type Future = FutureResult<&'static str, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            if self.0.get() {
+                Ok(Async::Ready(()))
+            } else {
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, request: &'static str) -> Self::Future {
+            future::ok(request)
+        }
+    }
+
+    #[test]
+    fn queued_requests_drain_on_readiness() {
+        let ready = Rc::new(Cell::new(false));
+        let mut svc = Gated(ready.clone()).buffer_requests(2);
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        let first = svc.call("first");
+        assert!(svc.poll_ready().unwrap().is_ready());
+        let second = svc.call("second");
+        assert_eq!(svc.queued(), 2);
+        assert!(svc.poll_ready().unwrap().is_not_ready());
+
+        ready.set(true);
+        assert!(svc.poll_ready().unwrap().is_ready());
+        assert_eq!(svc.queued(), 0);
+        assert_eq!(first.wait().unwrap(), "first");
+        assert_eq!(second.wait().unwrap(), "second");
+    }
+
+    #[test]
+    fn dropped_queue_closes_requests() {
+        let mut svc = Gated(Rc::new(Cell::new(false))).buffer_requests(1);
+
+        assert!(svc.poll_ready().unwrap().is_ready());
+        let queued = svc.call("queued");
+        drop(svc);
+        match queued.wait() {
+            Err(Error::Closed) => {}
+            _ => panic!("expected the queued request to be closed"),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use futures::Poll;
+use tower_service::Service;
+
+/// Shares a service between handles on a single thread.
+///
+/// Each clone of an `RcService` dispatches requests to the same inner service
+/// through an `Rc<RefCell<S>>`. This avoids the cost of the synchronization
+/// that sharing through `Arc<Mutex<S>>` would impose.
+///
+/// # `!Send`
+///
+/// Because it is built on `Rc`, an `RcService` is neither `Send` nor `Sync`.
+/// It can only be used where futures are never moved between threads, such as
+/// on a `current_thread` executor. Neither can its response futures be sent,
+/// if they borrow from the inner service's state.
+///
+/// # Readiness
+///
+/// Readiness is a property of the inner service, so it is shared by all
+/// handles: a request dispatched through one handle may consume readiness
+/// observed through another. Each handle should call `poll_ready` immediately
+/// before each `call`, without yielding in between.
+///
+/// # Panics
+///
+/// The inner service is borrowed only for the duration of `poll_ready` and
+/// `call`. If the inner service re-enters a handle to itself from either of
+/// these methods, the borrow will panic.
+pub struct RcService<S, R> {
+    inner: Rc<RefCell<S>>,
+    _p: PhantomData<fn(R)>,
+}
+
+// ===== impl RcService =====
+
+impl<S, R> RcService<S, R>
+where
+    S: Service<R>,
+{
+    pub fn new(inner: S) -> Self {
+        RcService {
+            inner: Rc::new(RefCell::new(inner)),
+            _p: PhantomData,
+        }
+    }
+
+    /// Returns the number of handles sharing the inner service.
+    pub fn handles(&self) -> usize {
+        Rc::strong_count(&self.inner)
+    }
+}
+
+impl<S, R> Service<R> for RcService<S, R>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.borrow_mut().poll_ready()
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.inner.borrow_mut().call(request)
+    }
+}
+
+impl<S, R> Clone for RcService<S, R> {
+    fn clone(&self) -> Self {
+        RcService {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, R> fmt::Debug for RcService<S, R>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RcService")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use futures::{Async, Future};
+    use tokio::runtime::current_thread::Runtime;
+
+    use super::*;
+    use ServiceExt;
+
+    /// Numbers each request it receives.
+    struct Counter(usize);
+
+    impl Service<()> for Counter {
+        type Response = usize;
+        type Error = ();
+        type Future = FutureResult<usize, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.0 += 1;
+            future::ok(self.0)
+        }
+    }
+
+    #[test]
+    fn clones_share_inner_service() {
+        let mut rt = Runtime::new().unwrap();
+
+        let a = RcService::new(Counter(0));
+        let b = a.clone();
+        assert_eq!(a.handles(), 2);
+
+        let calls = future::lazy(move || {
+            let calls = vec![a.clone(), b.clone(), a, b]
+                .into_iter()
+                .map(|svc| svc.ready().and_then(|mut svc| svc.call(())))
+                .collect::<Vec<_>>();
+            future::join_all(calls)
+        });
+
+        assert_eq!(rt.block_on(calls), Ok(vec![1, 2, 3, 4]));
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use futures::{Async, Poll};
+use tower_service::Service;
+
+/// Remembers that an inner service is ready until a request is dispatched.
+///
+/// Some services do real work in `poll_ready`. Once such a service has
+/// returned `Ready`, `ReadyCache` answers subsequent `poll_ready` calls
+/// without consulting it again. The cached readiness is consumed by `call`,
+/// so the inner service is polled at most once per request that succeeds in
+/// becoming ready.
+pub struct ReadyCache<S, R> {
+    inner: S,
+    is_ready: bool,
+    _p: PhantomData<fn() -> R>,
+}
+
+// ===== impl ReadyCache =====
+
+impl<S, R> ReadyCache<S, R>
+where
+    S: Service<R>,
+{
+    /// Wraps `inner` so that its readiness is cached between calls.
+    pub fn new(inner: S) -> Self {
+        ReadyCache {
+            inner,
+            is_ready: false,
+            _p: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes `self`, returning the inner service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, R> Service<R> for ReadyCache<S, R>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        if self.is_ready {
+            return Ok(Async::Ready(()));
+        }
+
+        // An error leaves the cache empty so that the next poll retries.
+        let ready = self.inner.poll_ready()?;
+        self.is_ready = ready.is_ready();
+        Ok(ready)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.is_ready = false;
+        self.inner.call(request)
+    }
+}
+
+impl<S, R> Clone for ReadyCache<S, R>
+where
+    S: Clone,
+{
+    /// Clones the inner service. The clone must establish its own readiness.
+    fn clone(&self) -> Self {
+        ReadyCache {
+            inner: self.inner.clone(),
+            is_ready: false,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, R> fmt::Debug for ReadyCache<S, R>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadyCache")
+            .field("inner", &self.inner)
+            .field("is_ready", &self.is_ready)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{ok, FutureResult};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    struct Srv {
+        polls: Rc<Cell<usize>>,
+        ready: Rc<Cell<bool>>,
+    }
+
+    impl Service<()> for Srv {
+        type Response = ();
+        type Error = ();
+        type Future = FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            self.polls.set(self.polls.get() + 1);
+            if self.ready.get() {
+                Ok(Async::Ready(()))
+            } else {
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            ok(())
+        }
+    }
+
+    fn new_cache() -> (ReadyCache<Srv, ()>, Rc<Cell<usize>>, Rc<Cell<bool>>) {
+        let polls = Rc::new(Cell::new(0));
+        let ready = Rc::new(Cell::new(true));
+        let srv = Srv {
+            polls: polls.clone(),
+            ready: ready.clone(),
+        };
+        (ReadyCache::new(srv), polls, ready)
+    }
+
+    #[test]
+    fn polls_once_per_call() {
+        let (mut srv, polls, _) = new_cache();
+
+        for i in 1..4 {
+            assert!(srv.poll_ready().unwrap().is_ready());
+            assert!(srv.poll_ready().unwrap().is_ready());
+            assert!(srv.poll_ready().unwrap().is_ready());
+            assert_eq!(polls.get(), i);
+
+            srv.call(());
+        }
+    }
+
+    #[test]
+    fn not_ready_is_not_cached() {
+        let (mut srv, polls, ready) = new_cache();
+        ready.set(false);
+
+        assert!(srv.poll_ready().unwrap().is_not_ready());
+        assert!(srv.poll_ready().unwrap().is_not_ready());
+        assert_eq!(polls.get(), 2);
+
+        ready.set(true);
+        assert!(srv.poll_ready().unwrap().is_ready());
+        assert!(srv.poll_ready().unwrap().is_ready());
+        assert_eq!(polls.get(), 3);
+    }
+}